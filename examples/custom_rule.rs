@@ -0,0 +1,32 @@
+//! A jswag binary with one additional rule written in Rust: it reports every
+//! method named `finalize`. Build it with `cargo build --example custom_rule`
+//! and use it like `jswag` itself.
+
+extern crate jswag;
+
+use jswag::code::FileMap;
+use jswag::custom::{Node, Rule};
+use jswag::diag::Report;
+
+struct NoFinalize;
+
+impl Rule for NoFinalize {
+    fn id(&self) -> &str {
+        "no-finalize"
+    }
+
+    fn check_node(&self, _: &FileMap, node: Node, out: &mut Vec<Report>) {
+        if let Node::Method(m) = node {
+            if m.name.name == "finalize" {
+                out.push(Report::simple_warning(
+                    "`finalize` is deprecated, close resources explicitly",
+                    m.name.span,
+                ));
+            }
+        }
+    }
+}
+
+fn main() {
+    jswag::run_with_rules(vec![Box::new(NoFinalize)]);
+}
@@ -0,0 +1,13 @@
+package com.example;
+
+public class Main {
+    public static void main(String[] args) {
+        // The internal parser doesn't know `try` yet, so `javac` decides
+        // whether this file is fine
+        try {
+            System.out.println(Greeter.greet("World"));
+        } catch (RuntimeException e) {
+            System.out.println("Failed: " + e);
+        }
+    }
+}
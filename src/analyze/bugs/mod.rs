@@ -0,0 +1,512 @@
+//! The `bugs` analysis pass.
+//!
+//! Finds code that compiles, but most likely doesn't do what was intended.
+//! These are typical mistakes of beginners:
+//!
+//! - comparing strings with `==` or `!=`
+//! - assignments in `if` conditions (`if (done = true)`)
+//! - empty `catch` blocks
+//! - a `;` directly after the condition of `if`, `for` or `while`
+//! - `case`s falling through to the next one without a comment saying so
+//! - integer divisions whose result is used as floating point number
+//!
+//! Types of expressions are only known for literals and variables of
+//! primitive types or `String` declared in this file. Try statements aren't
+//! part of the AST, so empty `catch` blocks are searched in the tokens.
+//!
+//! The parser can't parse `try` yet, so files containing it have no AST. Only
+//! the empty `catch` blocks are searched in these files.
+
+use analyze::{self, Finding};
+use base::code::{FileMap, Span};
+use base::diag::Report;
+use check::flow;
+use check::symbols::SymbolTable;
+use syntax::ast::{self, BinOpType, ExprType, StatementType, UnaryOpType};
+use syntax::lex::{Keyword, Lit, Token, TokenSpan};
+
+pub const STRING_COMPARISON: &'static str = "string-comparison";
+pub const ASSIGNMENT_IN_CONDITION: &'static str = "assignment-in-condition";
+pub const EMPTY_CATCH: &'static str = "empty-catch";
+pub const EMPTY_BODY: &'static str = "empty-body";
+pub const FALLTHROUGH: &'static str = "switch-fallthrough";
+pub const INTEGER_DIVISION: &'static str = "integer-division";
+
+/// What we know about the type of an expression
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    /// `int`, `long`, `short`, `byte` or `char`
+    Integer,
+    /// `float` or `double`
+    Floating,
+    Str,
+    Other,
+}
+
+struct Checker<'a> {
+    src: &'a str,
+    tokens: Vec<TokenSpan>,
+    symbols: SymbolTable<'a>,
+    /// Kind of the return type of the current method
+    returns: Kind,
+    findings: Vec<Finding>,
+}
+
+pub fn check<'a>(file_map: &'a FileMap, ast: &'a ast::CompilationUnit) -> Vec<Finding> {
+    let mut checker = Checker {
+        src: file_map.src(),
+        tokens: analyze::tokenize(file_map),
+        symbols: SymbolTable::build(ast),
+        returns: Kind::Other,
+        findings: Vec::new(),
+    };
+    for ty in &ast.types {
+        checker.type_def(ty);
+    }
+    checker.findings.extend(empty_catches(&checker.tokens));
+    checker.findings
+}
+
+/// Runs the rules which only need the tokens, for files the parser can't
+/// parse.
+pub fn check_tokens(file_map: &FileMap) -> Vec<Finding> {
+    empty_catches(&analyze::tokenize(file_map))
+}
+
+/// Searches `catch` blocks without any content (not even a comment).
+fn empty_catches(tokens: &[TokenSpan]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let real: Vec<_> = tokens.iter().filter(|ts| ts.tok != Token::Whitespace).collect();
+    for (i, ts) in real.iter().enumerate() {
+        if ts.tok != Token::KeyW(Keyword::Catch) {
+            continue;
+        }
+
+        // Skip the parameter
+        let mut depth = 0;
+        let mut j = i + 1;
+        while j < real.len() {
+            match real[j].tok {
+                Token::ParenOp => depth += 1,
+                Token::ParenCl => depth -= 1,
+                _ => {},
+            }
+            j += 1;
+            if depth == 0 {
+                break;
+            }
+        }
+
+        if j + 1 < real.len() && real[j].tok == Token::BraceOp
+            && real[j + 1].tok == Token::BraceCl
+        {
+            let rep = Report::simple_warning(
+                "empty `catch` block",
+                Span::new(real[j].span.lo, real[j + 1].span.hi),
+            ).with_note(
+                "handle the exception or explain in a comment why it can be ignored",
+            );
+            findings.push(Finding::new(EMPTY_CATCH, rep));
+        }
+    }
+    findings
+}
+
+impl<'a> Checker<'a> {
+    fn type_def(&mut self, ty: &'a ast::TypeDef) {
+        match *ty {
+            ast::TypeDef::NormalClass(ref c) => {
+                for member in &c.members {
+                    if let ast::ClassMember::Method(ref m) = *member {
+                        self.method(m);
+                    }
+                }
+            },
+            ast::TypeDef::NormalInterface(ref i) => {
+                for m in &i.methods {
+                    self.method(m);
+                }
+                for ty in &i.types {
+                    self.type_def(ty);
+                }
+            },
+        }
+    }
+
+    fn method(&mut self, m: &'a ast::Method) {
+        self.returns = kind_of_type(&m.ret_ty);
+        if let Some(ref block) = m.block {
+            self.block(block);
+        }
+    }
+
+    fn block(&mut self, block: &'a ast::Block) {
+        for stmt in &block.stmts {
+            self.block_stmt(stmt);
+        }
+    }
+
+    fn block_stmt(&mut self, stmt: &'a ast::BlockStatement) {
+        match *stmt {
+            ast::BlockStatement::LocalVariableDecl { ref ty, ref vars, .. } => {
+                for var in vars {
+                    if let Some(ref init) = var.init {
+                        self.expr(init);
+                        if var.dims == 0 && kind_of_type(ty) == Kind::Floating {
+                            self.check_integer_division(init);
+                        }
+                    }
+                }
+            },
+            ast::BlockStatement::Statement(ref stmt) => self.stmt(stmt),
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'a ast::Statement) {
+        match stmt.stmt {
+            StatementType::Empty
+                | StatementType::Break(_)
+                | StatementType::Continue(_)
+                | StatementType::Return(None) => {},
+            StatementType::Block(ref block) => self.block(block),
+            StatementType::Expr(ref e) | StatementType::Throw(ref e) => self.expr(e),
+            StatementType::Return(Some(ref e)) => {
+                self.expr(e);
+                if self.returns == Kind::Floating {
+                    self.check_integer_division(e);
+                }
+            },
+            StatementType::IfThenElse { ref cond, ref then_branch, ref else_branch } => {
+                if is_assignment(cond) {
+                    let rep = Report::simple_warning("assignment in `if` condition", cond.span)
+                        .with_note("use `==` to compare values");
+                    self.findings.push(Finding::new(ASSIGNMENT_IN_CONDITION, rep));
+                }
+                self.expr(cond);
+                self.check_empty_body("if", then_branch);
+                self.stmt(then_branch);
+                if let Some(ref else_branch) = *else_branch {
+                    self.stmt(else_branch);
+                }
+            },
+            StatementType::While { ref cond, ref body } => {
+                self.expr(cond);
+                self.check_empty_body("while", body);
+                self.stmt(body);
+            },
+            StatementType::DoWhile { ref cond, ref body } => {
+                self.stmt(body);
+                self.expr(cond);
+            },
+            StatementType::For { ref init, ref cond, ref update, ref body } => {
+                match *init {
+                    ast::ForInit::VarDecl(ref decl) => self.block_stmt(decl),
+                    ast::ForInit::Stmts(ref stmts) => for s in stmts {
+                        self.stmt(s);
+                    },
+                }
+                if let Some(ref cond) = *cond {
+                    self.expr(cond);
+                }
+                for s in update {
+                    self.stmt(s);
+                }
+                self.check_empty_body("for", body);
+                self.stmt(body);
+            },
+            StatementType::Switch { ref val, ref arms, .. } => {
+                self.expr(val);
+                for arm in arms {
+                    self.block_stmt(&arm.block);
+                }
+                self.check_fallthrough(arms);
+            },
+        }
+    }
+
+    fn expr(&mut self, e: &'a ast::Expr) {
+        match e.expr {
+            ExprType::BinOp { op, ref lhs, ref rhs } => {
+                match op {
+                    BinOpType::Equals | BinOpType::Ne => self.check_string_comparison(e, lhs, rhs),
+                    BinOpType::Assign if self.kind_of(lhs) == Kind::Floating => {
+                        self.check_integer_division(rhs);
+                    },
+                    _ if is_arithmetic(op) && self.kind_of(e) == Kind::Floating => {
+                        self.check_integer_division(lhs);
+                        self.check_integer_division(rhs);
+                    },
+                    _ => {},
+                }
+                self.expr(lhs);
+                self.expr(rhs);
+            },
+            ExprType::Cast { ref ty, ref expr } => {
+                if kind_of_type(ty) == Kind::Floating {
+                    self.check_integer_division(expr);
+                }
+                self.expr(expr);
+            },
+            ExprType::Conditional { ref cond, ref if_branch, ref else_branch } => {
+                self.expr(cond);
+                self.expr(if_branch);
+                self.expr(else_branch);
+            },
+            ExprType::UnaryOp { ref expr, .. } => self.expr(expr),
+            ExprType::FieldAccess { root: Some(ref root), .. } => self.expr(root),
+            ExprType::ArrayAccess { ref obj, ref idx } => {
+                self.expr(obj);
+                self.expr(idx);
+            },
+            ExprType::MethodInvocation { ref name, ref args } => {
+                if let ast::MethodInvocationType::Expr(ref obj, _) = *name {
+                    self.expr(obj);
+                }
+                for arg in args {
+                    self.expr(arg);
+                }
+            },
+            ExprType::ArrayInit { ref items } => {
+                for item in items {
+                    self.expr(item);
+                }
+            },
+            ExprType::InstanceCreation { ref args, .. } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+            },
+            ExprType::ArrayCreation { ref expr_dims, ref init, .. } => {
+                for e in expr_dims {
+                    self.expr(e);
+                }
+                if let Some(ref init) = *init {
+                    self.expr(init);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn check_string_comparison(&mut self, e: &ast::Expr, lhs: &ast::Expr, rhs: &ast::Expr) {
+        // Comparing with `null` is fine
+        if is_null(lhs) || is_null(rhs) {
+            return;
+        }
+        if self.kind_of(lhs) != Kind::Str && self.kind_of(rhs) != Kind::Str {
+            return;
+        }
+
+        let rep = Report::simple_warning(
+            "strings are compared by reference instead of by their contents",
+            e.span,
+        ).with_note(format!(
+            "use `{}.equals({})` to compare the contents",
+            &self.src[lhs.span.into_range()],
+            &self.src[rhs.span.into_range()],
+        ));
+        self.findings.push(Finding::new(STRING_COMPARISON, rep));
+    }
+
+    /// Checks whether the expression, whose value is used as floating point
+    /// number, is a division of two integers.
+    fn check_integer_division(&mut self, e: &ast::Expr) {
+        if let ExprType::BinOp { op: BinOpType::Div, ref lhs, ref rhs } = e.expr {
+            if self.kind_of(lhs) == Kind::Integer && self.kind_of(rhs) == Kind::Integer {
+                let rep = Report::simple_warning(
+                    "integer division in floating point context",
+                    e.span,
+                ).with_note(
+                    "the result is rounded towards zero before it's converted; cast one \
+                        of the operands to `double` to divide as floating point numbers",
+                );
+                self.findings.push(Finding::new(INTEGER_DIVISION, rep));
+            }
+        }
+    }
+
+    fn check_empty_body(&mut self, keyword: &str, body: &ast::Statement) {
+        if let StatementType::Empty = body.stmt {
+            let rep = Report::simple_warning(
+                format!("empty body of `{}` statement", keyword),
+                body.span,
+            ).with_note(
+                "this `;` ends the statement, so the following code isn't part of it",
+            );
+            self.findings.push(Finding::new(EMPTY_BODY, rep));
+        }
+    }
+
+    /// Reports every `case` that's reached by falling through from the
+    /// previous one, unless a comment mentions it (e.g. `// falls through`).
+    fn check_fallthrough(&mut self, arms: &[ast::SwitchArm]) {
+        // Every arm holds one statement. The statements of one `case` are
+        // split into multiple arms, of which only the first has labels.
+        let mut groups: Vec<Vec<&ast::BlockStatement>> = Vec::new();
+        for arm in arms {
+            match groups.last_mut() {
+                Some(group) if arm.labels.is_empty() => {
+                    group.push(&arm.block);
+                    continue;
+                },
+                _ => {},
+            }
+            groups.push(vec![&arm.block]);
+        }
+
+        for pair in groups.windows(2) {
+            let last = pair[0][pair[0].len() - 1];
+            if is_jump(last) {
+                continue;
+            }
+            let (end, next) = match (flow::span_of(last), flow::span_of(pair[1][0])) {
+                (Some(last), Some(next)) => (last.hi, next.lo),
+                _ => continue,
+            };
+
+            let between = self.tokens.iter()
+                .filter(|ts| ts.span.lo >= end && ts.span.hi <= next);
+            let mut label = None;
+            let mut commented = false;
+            for ts in between {
+                match ts.tok {
+                    Token::Comment => {
+                        let text = self.src[ts.span.into_range()].to_lowercase();
+                        commented |= text.contains("fall");
+                    },
+                    Token::KeyW(Keyword::Case) | Token::KeyW(Keyword::Default) => {
+                        label = label.or(Some(ts.span));
+                    },
+                    _ => {},
+                }
+            }
+
+            if let (Some(label), false) = (label, commented) {
+                let rep = Report::simple_warning(
+                    "the previous case falls through to this one",
+                    label,
+                ).with_note(
+                    "add `break;` or, if this is intended, a comment like `// falls through`",
+                );
+                self.findings.push(Finding::new(FALLTHROUGH, rep));
+            }
+        }
+    }
+
+    /// Returns the kind of the variable the identifier refers to.
+    fn kind_of_name(&self, name: &ast::Ident) -> Kind {
+        self.symbols.resolve(name)
+            .map(|symbol| kind_of_type(symbol.ty))
+            .unwrap_or(Kind::Other)
+    }
+
+    fn kind_of(&self, e: &ast::Expr) -> Kind {
+        match e.expr {
+            ExprType::Literal(ref lit) => match *lit {
+                Lit::Str(_) => Kind::Str,
+                Lit::Char(_) | Lit::Integer { .. } => Kind::Integer,
+                Lit::Float { .. } => Kind::Floating,
+                _ => Kind::Other,
+            },
+            ExprType::Name(ref path) if path.segments.len() == 1 => {
+                self.kind_of_name(&path.segments[0])
+            },
+            ExprType::Cast { ref ty, .. } => kind_of_type(ty),
+            ExprType::UnaryOp { op: UnaryOpType::Not, .. } => Kind::Other,
+            ExprType::UnaryOp { ref expr, .. } => self.kind_of(expr),
+            ExprType::Conditional { ref if_branch, ref else_branch, .. } => {
+                let kind = self.kind_of(if_branch);
+                if kind == self.kind_of(else_branch) { kind } else { Kind::Other }
+            },
+            ExprType::BinOp { op, ref lhs, ref rhs } if is_arithmetic(op) => {
+                let is_add = match op { BinOpType::Add => true, _ => false };
+                match (self.kind_of(lhs), self.kind_of(rhs)) {
+                    (Kind::Str, _) | (_, Kind::Str) if is_add => Kind::Str,
+                    (Kind::Integer, Kind::Integer) => Kind::Integer,
+                    (Kind::Floating, Kind::Integer)
+                        | (Kind::Integer, Kind::Floating)
+                        | (Kind::Floating, Kind::Floating) => Kind::Floating,
+                    _ => Kind::Other,
+                }
+            },
+            ExprType::BinOp { op, ref lhs, .. } if is_assignment_op(op) => self.kind_of(lhs),
+            _ => Kind::Other,
+        }
+    }
+}
+
+fn kind_of_type(ty: &ast::Type) -> Kind {
+    if ty.dims > 0 {
+        return Kind::Other;
+    }
+    match ty.name.segments.last().map(|s| &s.name[..]) {
+        Some("int") | Some("long") | Some("short") | Some("byte") | Some("char") => {
+            Kind::Integer
+        },
+        Some("float") | Some("double") => Kind::Floating,
+        Some("String") => Kind::Str,
+        _ => Kind::Other,
+    }
+}
+
+fn is_arithmetic(op: BinOpType) -> bool {
+    match op {
+        BinOpType::Add | BinOpType::Sub | BinOpType::Mul | BinOpType::Div | BinOpType::Mod => true,
+        _ => false,
+    }
+}
+
+fn is_assignment_op(op: BinOpType) -> bool {
+    match op {
+        BinOpType::Assign
+            | BinOpType::MulAssign
+            | BinOpType::DivAssign
+            | BinOpType::ModAssign
+            | BinOpType::AddAssign
+            | BinOpType::SubAssign
+            | BinOpType::ShlAssign
+            | BinOpType::ShrAssign
+            | BinOpType::ShrUnAssign
+            | BinOpType::AndAssign
+            | BinOpType::XorAssign
+            | BinOpType::OrAssign => true,
+        _ => false,
+    }
+}
+
+fn is_assignment(e: &ast::Expr) -> bool {
+    match e.expr {
+        ExprType::BinOp { op, .. } => is_assignment_op(op),
+        _ => false,
+    }
+}
+
+fn is_null(e: &ast::Expr) -> bool {
+    match e.expr {
+        ExprType::Literal(Lit::Null) => true,
+        _ => false,
+    }
+}
+
+/// Whether the statement never completes normally, because it always jumps
+/// somewhere else.
+fn is_jump(stmt: &ast::BlockStatement) -> bool {
+    match *stmt {
+        ast::BlockStatement::Statement(ref stmt) => is_jump_stmt(stmt),
+        _ => false,
+    }
+}
+
+fn is_jump_stmt(stmt: &ast::Statement) -> bool {
+    match stmt.stmt {
+        StatementType::Break(_)
+            | StatementType::Continue(_)
+            | StatementType::Return(_)
+            | StatementType::Throw(_) => true,
+        StatementType::Block(ref block) => block.stmts.last().map(is_jump).unwrap_or(false),
+        StatementType::IfThenElse { ref then_branch, else_branch: Some(ref else_branch), .. } => {
+            is_jump_stmt(then_branch) && is_jump_stmt(else_branch)
+        },
+        _ => false,
+    }
+}
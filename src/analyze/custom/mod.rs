@@ -0,0 +1,244 @@
+//! Project-specific rules.
+//!
+//! A rule implements `Rule`: it's shown every node of the AST and emits
+//! reports about it. Teams add rules without changing jswag by putting rule
+//! files into `.jswag/rules/`, which describe the nodes to report
+//! declaratively (see `pattern`). Rules written in Rust are passed to
+//! `jswag::run_with_rules` by a program using jswag as a library. All rules
+//! run together with the analysis passes, whenever files are analyzed.
+
+pub mod pattern;
+
+use analyze::Finding;
+use base::code::FileMap;
+use base::diag::Report;
+use std::fmt;
+use std::rc::Rc;
+use syntax::ast::{self, ExprType, StatementType};
+
+/// A node of the AST shown to rules
+#[derive(Clone, Copy)]
+pub enum Node<'a> {
+    Import(&'a ast::Import),
+    Class(&'a ast::Class),
+    Interface(&'a ast::Interface),
+    Method(&'a ast::Method),
+    Field(&'a ast::Field),
+    /// Declaration of local variables with their type
+    LocalVariables(&'a ast::Type, &'a [ast::VariableDeclarator]),
+    Expr(&'a ast::Expr),
+}
+
+/// A check which is run on every node of the AST
+pub trait Rule {
+    /// Name of the rule shown with its findings, e.g. `no-system-exit`
+    fn id(&self) -> &str;
+
+    /// Checks one node and adds reports about it to `out`.
+    fn check_node(&self, file_map: &FileMap, node: Node, out: &mut Vec<Report>);
+}
+
+/// Rules written in Rust, which are given to `jswag::run_with_rules`
+#[derive(Clone, Default)]
+pub struct RustRules(pub Rc<Vec<Box<Rule>>>);
+
+impl fmt::Debug for RustRules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ids: Vec<_> = self.0.iter().map(|r| r.id()).collect();
+        write!(f, "RustRules({:?})", ids)
+    }
+}
+
+/// Runs the given rules on every node of the compilation unit.
+pub fn check(rules: &[&Rule], file_map: &FileMap, ast: &ast::CompilationUnit) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    walk(ast, &mut |node| {
+        for rule in rules {
+            let mut reports = Vec::new();
+            rule.check_node(file_map, node, &mut reports);
+            findings.extend(reports.into_iter().map(|rep| {
+                Finding::new(rule.id().to_string(), rep)
+            }));
+        }
+    });
+    findings
+}
+
+/// Calls `f` for every node of the compilation unit, in source order.
+pub fn walk<'a, F: FnMut(Node<'a>)>(ast: &'a ast::CompilationUnit, f: &mut F) {
+    let mut walker = Walker { f: f };
+    for import in &ast.imports {
+        (walker.f)(Node::Import(import));
+    }
+    for ty in &ast.types {
+        walker.type_def(ty);
+    }
+}
+
+struct Walker<'f, F: 'f> {
+    f: &'f mut F,
+}
+
+impl<'a, 'f, F: FnMut(Node<'a>)> Walker<'f, F> {
+    fn type_def(&mut self, ty: &'a ast::TypeDef) {
+        match *ty {
+            ast::TypeDef::NormalClass(ref class) => {
+                (self.f)(Node::Class(class));
+                for member in &class.members {
+                    self.member(member);
+                }
+            },
+            ast::TypeDef::NormalInterface(ref interface) => {
+                (self.f)(Node::Interface(interface));
+                for field in &interface.constants {
+                    self.field(field);
+                }
+                for method in &interface.methods {
+                    self.method(method);
+                }
+                for ty in &interface.types {
+                    self.type_def(ty);
+                }
+            },
+        }
+    }
+
+    fn member(&mut self, member: &'a ast::ClassMember) {
+        match *member {
+            ast::ClassMember::Method(ref method) => self.method(method),
+            ast::ClassMember::Field(ref field) => self.field(field),
+        }
+    }
+
+    fn field(&mut self, field: &'a ast::Field) {
+        (self.f)(Node::Field(field));
+    }
+
+    fn method(&mut self, method: &'a ast::Method) {
+        (self.f)(Node::Method(method));
+        if let Some(ref block) = method.block {
+            self.block(block);
+        }
+    }
+
+    fn block(&mut self, block: &'a ast::Block) {
+        for stmt in &block.stmts {
+            self.block_stmt(stmt);
+        }
+    }
+
+    fn block_stmt(&mut self, stmt: &'a ast::BlockStatement) {
+        match *stmt {
+            ast::BlockStatement::LocalVariableDecl { ref ty, ref vars, .. } => {
+                (self.f)(Node::LocalVariables(ty, vars));
+                for init in vars.iter().filter_map(|var| var.init.as_ref()) {
+                    self.expr(init);
+                }
+            },
+            ast::BlockStatement::Statement(ref stmt) => self.stmt(stmt),
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'a ast::Statement) {
+        match stmt.stmt {
+            StatementType::Empty
+                | StatementType::Break(_)
+                | StatementType::Continue(_)
+                | StatementType::Return(None) => {},
+            StatementType::Block(ref block) => self.block(block),
+            StatementType::Expr(ref e)
+                | StatementType::Throw(ref e)
+                | StatementType::Return(Some(ref e)) => self.expr(e),
+            StatementType::IfThenElse { ref cond, ref then_branch, ref else_branch } => {
+                self.expr(cond);
+                self.stmt(then_branch);
+                if let Some(ref else_branch) = *else_branch {
+                    self.stmt(else_branch);
+                }
+            },
+            StatementType::While { ref cond, ref body } => {
+                self.expr(cond);
+                self.stmt(body);
+            },
+            StatementType::DoWhile { ref cond, ref body } => {
+                self.stmt(body);
+                self.expr(cond);
+            },
+            StatementType::For { ref init, ref cond, ref update, ref body } => {
+                match *init {
+                    ast::ForInit::VarDecl(ref decl) => self.block_stmt(decl),
+                    ast::ForInit::Stmts(ref stmts) => for s in stmts {
+                        self.stmt(s);
+                    },
+                }
+                if let Some(ref cond) = *cond {
+                    self.expr(cond);
+                }
+                for s in update {
+                    self.stmt(s);
+                }
+                self.stmt(body);
+            },
+            StatementType::Switch { ref val, ref arms, .. } => {
+                self.expr(val);
+                for arm in arms {
+                    self.block_stmt(&arm.block);
+                }
+            },
+        }
+    }
+
+    fn expr(&mut self, e: &'a ast::Expr) {
+        (self.f)(Node::Expr(e));
+        match e.expr {
+            ExprType::BinOp { ref lhs, ref rhs, .. } => {
+                self.expr(lhs);
+                self.expr(rhs);
+            },
+            ExprType::Conditional { ref cond, ref if_branch, ref else_branch } => {
+                self.expr(cond);
+                self.expr(if_branch);
+                self.expr(else_branch);
+            },
+            ExprType::UnaryOp { ref expr, .. } | ExprType::Cast { ref expr, .. } => {
+                self.expr(expr);
+            },
+            ExprType::FieldAccess { root: Some(ref root), .. } => self.expr(root),
+            ExprType::ArrayAccess { ref obj, ref idx } => {
+                self.expr(obj);
+                self.expr(idx);
+            },
+            ExprType::MethodInvocation { ref name, ref args } => {
+                if let ast::MethodInvocationType::Expr(ref obj, _) = *name {
+                    self.expr(obj);
+                }
+                for arg in args {
+                    self.expr(arg);
+                }
+            },
+            ExprType::ArrayInit { ref items } => {
+                for item in items {
+                    self.expr(item);
+                }
+            },
+            ExprType::InstanceCreation { ref args, ref body, .. } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                // Members of anonymous classes
+                for member in body.iter().flat_map(|members| members) {
+                    self.member(member);
+                }
+            },
+            ExprType::ArrayCreation { ref expr_dims, ref init, .. } => {
+                for e in expr_dims {
+                    self.expr(e);
+                }
+                if let Some(ref init) = *init {
+                    self.expr(init);
+                }
+            },
+            _ => {},
+        }
+    }
+}
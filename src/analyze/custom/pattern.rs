@@ -0,0 +1,260 @@
+//! Declarative rules, read from the TOML files in `.jswag/rules/`.
+//!
+//! Every file contains one or more rules:
+//!
+//! ```toml
+//! [[rule]]
+//! id = "no-system-exit"
+//! kind = "call"
+//! name = "System.exit"
+//! message = "`{name}` ends the whole program"
+//! note = "throw an exception instead"     # optional
+//! severity = "error"                      # optional, default: "warning"
+//! ```
+//!
+//! `kind` selects the nodes: `call` (method invocations), `new` (instance
+//! creations), `method`, `field`, `class` (classes and interfaces) or
+//! `import`. Their name as written in the source is matched against `name`,
+//! in which `*` matches any sequence of characters. Calls on the result of
+//! an expression, like `foo().bar()`, only have the method name. `{name}` in
+//! the message is replaced with the name of the node.
+
+use base::code::{FileMap, Span};
+use base::diag::{Report, ReportKind};
+use config;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use super::{Node, Rule};
+use syntax::ast::{self, ExprType};
+
+/// The directory of rule files. It's searched in the current working
+/// directory and all of its parents, like the configuration file.
+pub const RULES_DIR: &'static str = ".jswag/rules";
+
+/// Names of all kinds of nodes a rule can match
+pub const KINDS: &'static [&'static str] = &["call", "new", "method", "field", "class", "import"];
+
+/// Kinds of nodes a rule can match
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    Call,
+    New,
+    Method,
+    Field,
+    Class,
+    Import,
+}
+
+impl Kind {
+    fn parse(s: &str) -> Option<Kind> {
+        match s {
+            "call" => Some(Kind::Call),
+            "new" => Some(Kind::New),
+            "method" => Some(Kind::Method),
+            "field" => Some(Kind::Field),
+            "class" => Some(Kind::Class),
+            "import" => Some(Kind::Import),
+            _ => None,
+        }
+    }
+}
+
+/// A rule from a rule file
+#[derive(Clone, Debug)]
+pub struct PatternRule {
+    id: String,
+    kind: Kind,
+    /// Pattern for the name, may contain `*`
+    name: String,
+    message: String,
+    note: Option<String>,
+    severity: ReportKind,
+}
+
+/// A rule file as it's decoded from TOML
+#[derive(RustcDecodable)]
+struct RawRules {
+    rule: Vec<RawRule>,
+}
+
+#[derive(RustcDecodable)]
+struct RawRule {
+    id: String,
+    kind: String,
+    name: String,
+    message: String,
+    note: Option<String>,
+    severity: Option<String>,
+}
+
+impl PatternRule {
+    /// A rule which reports every node of the given kind whose name matches
+    /// `name`, with the name as message. Returns `None` if the kind is
+    /// invalid.
+    pub fn matcher(kind: &str, name: &str) -> Option<PatternRule> {
+        Kind::parse(kind).map(|kind| PatternRule {
+            id: "match".into(),
+            kind: kind,
+            name: name.into(),
+            message: "{name}".into(),
+            note: None,
+            severity: ReportKind::Warning,
+        })
+    }
+}
+
+impl Rule for PatternRule {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn check_node(&self, _: &FileMap, node: Node, out: &mut Vec<Report>) {
+        let (name, span) = match (self.kind, node) {
+            (Kind::Call, Node::Expr(e)) => match e.expr {
+                ExprType::MethodInvocation { ref name, .. } => (call_name(name), e.span),
+                _ => return,
+            },
+            (Kind::New, Node::Expr(e)) => match e.expr {
+                ExprType::InstanceCreation { ref name, .. } => (path_name(name), e.span),
+                _ => return,
+            },
+            (Kind::Method, Node::Method(m)) => (m.name.name.clone(), m.name.span),
+            (Kind::Field, Node::Field(f)) => (f.name.name.clone(), f.name.span),
+            (Kind::Class, Node::Class(c)) => (c.name.name.clone(), c.name.span),
+            (Kind::Class, Node::Interface(i)) => (i.name.name.clone(), i.name.span),
+            (Kind::Import, Node::Import(import)) => match import_name(import) {
+                Some(name) => name,
+                None => return,
+            },
+            _ => return,
+        };
+        if !matches(&self.name, &name) {
+            return;
+        }
+
+        let msg = self.message.replace("{name}", &name);
+        let mut rep = match self.severity {
+            ReportKind::Error => Report::simple_error(msg, span),
+            ReportKind::Warning => Report::simple_warning(msg, span),
+        };
+        if let Some(ref note) = self.note {
+            rep = rep.with_note(note.replace("{name}", &name));
+        }
+        out.push(rep);
+    }
+}
+
+/// Loads the rules of all files in the rules directory, if there is one.
+/// Errors contain the path of the invalid file.
+pub fn load() -> Result<Vec<PatternRule>, String> {
+    let dir = match config::find_upwards(RULES_DIR, |p| p.is_dir()) {
+        Some(dir) => dir,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut paths: Vec<PathBuf> = match fs::read_dir(&dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|ext| ext == "toml").unwrap_or(false))
+            .collect(),
+        Err(e) => return Err(format!("'{}': {}", dir.display(), e)),
+    };
+    paths.sort();
+
+    let mut rules = Vec::new();
+    for path in paths {
+        match load_file(&path) {
+            Ok(file_rules) => rules.extend(file_rules),
+            Err(e) => return Err(format!("'{}': {}", path.display(), e)),
+        }
+    }
+    Ok(rules)
+}
+
+fn load_file(path: &Path) -> Result<Vec<PatternRule>, config::Error> {
+    let mut src = String::new();
+    try!(File::open(path).and_then(|mut f| f.read_to_string(&mut src)));
+    let raw: RawRules = try!(config::decode(&src));
+
+    let mut rules = Vec::new();
+    for rule in raw.rule {
+        // Ids are part of baseline files, which are separated by spaces
+        if rule.id.is_empty() || rule.id.contains(char::is_whitespace) {
+            return Err(config::Error::Invalid(
+                format!("invalid rule id '{}' (must not be empty or contain spaces)", rule.id)
+            ));
+        }
+        let kind = match Kind::parse(&rule.kind) {
+            Some(kind) => kind,
+            None => return Err(config::Error::Invalid(format!(
+                "invalid kind '{}' of rule '{}' (expected one of: {})",
+                rule.kind,
+                rule.id,
+                KINDS.join(", "),
+            ))),
+        };
+        let severity = match rule.severity.as_ref().map(|s| &s[..]) {
+            None | Some("warning") => ReportKind::Warning,
+            Some("error") => ReportKind::Error,
+            Some(other) => return Err(config::Error::Invalid(format!(
+                "invalid severity '{}' of rule '{}' (expected 'warning' or 'error')",
+                other,
+                rule.id,
+            ))),
+        };
+
+        rules.push(PatternRule {
+            id: rule.id,
+            kind: kind,
+            name: rule.name,
+            message: rule.message,
+            note: rule.note,
+            severity: severity,
+        });
+    }
+    Ok(rules)
+}
+
+fn call_name(name: &ast::MethodInvocationType) -> String {
+    match *name {
+        ast::MethodInvocationType::SimpleName(ref ident)
+            | ast::MethodInvocationType::Expr(_, ref ident) => ident.name.clone(),
+        ast::MethodInvocationType::SimplePath(ref path) => path_name(path),
+    }
+}
+
+fn path_name(path: &ast::Path) -> String {
+    let segments: Vec<_> = path.segments.iter().map(|s| &s.name[..]).collect();
+    segments.join(".")
+}
+
+/// The imported name (ending with `.*` for on-demand imports) and its span.
+fn import_name(import: &ast::Import) -> Option<(String, Span)> {
+    let (path, on_demand) = match *import {
+        ast::Import::SingleType(ref p) | ast::Import::SingleStatic(ref p) => (p, false),
+        ast::Import::TypeOnDemand(ref p) | ast::Import::StaticOnDemand(ref p) => (p, true),
+    };
+    path.span().map(|span| {
+        let name = path_name(path);
+        (if on_demand { name + ".*" } else { name }, span)
+    })
+}
+
+/// Matches the name against a pattern in which `*` matches any sequence of
+/// characters.
+fn matches(pattern: &str, name: &str) -> bool {
+    match pattern.find('*') {
+        None => pattern == name,
+        Some(star) => {
+            let (prefix, rest) = (&pattern[..star], &pattern[star + 1..]);
+            if !name.starts_with(prefix) {
+                return false;
+            }
+            let tail = &name[prefix.len()..];
+            (0..tail.len() + 1)
+                .filter(|&i| tail.is_char_boundary(i))
+                .any(|i| matches(rest, &tail[i..]))
+        },
+    }
+}
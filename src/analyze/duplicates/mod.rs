@@ -0,0 +1,157 @@
+//! The `duplicates` analysis pass.
+//!
+//! Searches sequences of tokens which appear more than once, in one file or
+//! in different files of the job. Whitespace, comments as well as the
+//! package and import declarations (which are often the same) are ignored.
+//! Optionally, identifiers and literals are treated as equal, to also find
+//! copies whose names or values were changed.
+//!
+//! In contrast to the other passes, this one needs all files at once, so it's
+//! run by `analyze_all` after all files were analyzed.
+
+use analyze::{self, Finding};
+use base::code::{FileMap, Span};
+use base::diag::Report;
+use config::DuplicatesConfig;
+use std::collections::HashMap;
+use syntax::lex::Token;
+
+pub const DUPLICATE: &'static str = "duplicate-code";
+
+/// The relevant tokens of one file
+struct Tokens {
+    /// Normalized token texts, each one as an index into a table of all
+    /// distinct texts
+    syms: Vec<usize>,
+    spans: Vec<Span>,
+}
+
+/// Two occurrences of the same token sequence: file and token index of both
+/// and the number of tokens
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Match {
+    first: (usize, usize),
+    second: (usize, usize),
+    len: usize,
+}
+
+/// Searches duplicates in the given files. Returns the findings together
+/// with the index of the file they belong to.
+pub fn check(config: &DuplicatesConfig, files: &[&FileMap]) -> Vec<(usize, Finding)> {
+    let mut table = HashMap::new();
+    let files_tokens: Vec<_> = files.iter()
+        .map(|file_map| normalize(config, file_map, &mut table))
+        .collect();
+
+    let mut findings = Vec::new();
+    for m in find_matches(&files_tokens, config.min_tokens) {
+        let span = |(file, idx): (usize, usize)| {
+            let spans = &files_tokens[file].spans;
+            Span::new(spans[idx].lo, spans[idx + m.len - 1].hi)
+        };
+        let first = span(m.first);
+        let second = span(m.second);
+        let msg = format!("duplicated code ({} tokens)", m.len);
+
+        if m.first.0 == m.second.0 {
+            let rep = Report::simple_warning(msg, second)
+                .with_span_note("first occurrence", first);
+            findings.push((m.second.0, Finding::new(DUPLICATE, rep)));
+        } else {
+            // Spans can only be shown in their own file, so both locations
+            // get a report referring to the other one
+            let rep = Report::simple_warning(msg.clone(), first)
+                .with_note(format!("also found in {}", location(&files[m.second.0], second)));
+            findings.push((m.first.0, Finding::new(DUPLICATE, rep)));
+
+            let rep = Report::simple_warning(msg, second)
+                .with_note(format!("also found in {}", location(&files[m.first.0], first)));
+            findings.push((m.second.0, Finding::new(DUPLICATE, rep)));
+        }
+    }
+    findings
+}
+
+/// Collects the tokens of the file which are compared. Texts of tokens are
+/// mapped to indices in `table`.
+fn normalize(config: &DuplicatesConfig, file_map: &FileMap, table: &mut HashMap<String, usize>)
+    -> Tokens
+{
+    let src = file_map.src();
+    let mut tokens = Tokens {
+        syms: Vec::new(),
+        spans: Vec::new(),
+    };
+
+    for ts in analyze::code_tokens(file_map) {
+        let text = match ts.tok {
+            Token::Ident(_) if config.ignore_identifiers => "<ident>",
+            Token::Literal(_) if config.ignore_literals => "<literal>",
+            _ => &src[ts.span.into_range()],
+        };
+        let next = table.len();
+        let sym = *table.entry(text.to_string()).or_insert(next);
+        tokens.syms.push(sym);
+        tokens.spans.push(ts.span);
+    }
+    tokens
+}
+
+/// Finds all pairs of token sequences with at least `min` tokens which are
+/// equal. Only the longest possible sequences are returned and sequences
+/// within one file don't overlap.
+fn find_matches(files: &[Tokens], min: usize) -> Vec<Match> {
+    // All starting positions of each sequence of `min` tokens
+    let mut starts: HashMap<&[usize], Vec<(usize, usize)>> = HashMap::new();
+    for (file, tokens) in files.iter().enumerate() {
+        for (idx, window) in tokens.syms.windows(min).enumerate() {
+            starts.entry(window).or_insert_with(Vec::new).push((file, idx));
+        }
+    }
+
+    let mut matches = Vec::new();
+    for positions in starts.values().filter(|p| p.len() > 1) {
+        for (i, &first) in positions.iter().enumerate() {
+            for &second in &positions[i + 1..] {
+                let a = &files[first.0].syms;
+                let b = &files[second.0].syms;
+
+                // If the previous tokens are equal as well, this is part of
+                // a longer match which is found at its start
+                if first.1 > 0 && second.1 > 0 && a[first.1 - 1] == b[second.1 - 1] {
+                    continue;
+                }
+
+                let mut len = min;
+                while first.1 + len < a.len() && second.1 + len < b.len()
+                    && a[first.1 + len] == b[second.1 + len]
+                {
+                    len += 1;
+                }
+                if first.0 == second.0 && second.1 < first.1 + len {
+                    len = second.1 - first.1;
+                    if len < min {
+                        continue;
+                    }
+                }
+
+                matches.push(Match {
+                    first: first,
+                    second: second,
+                    len: len,
+                });
+            }
+        }
+    }
+
+    // Positions are stored in a hash map, so sort to get a stable output
+    matches.sort();
+    matches
+}
+
+/// Describes the location of the span, e.g. `'Foo.java', lines 3 to 17`.
+fn location(file_map: &FileMap, span: Span) -> String {
+    let lo = file_map.get_line_idx(span.lo).0 + 1;
+    let hi = file_map.get_line_idx(span.hi).0 + 1;
+    format!("'{}', lines {} to {}", file_map.filename(), lo, hi)
+}
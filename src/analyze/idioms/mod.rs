@@ -0,0 +1,117 @@
+//! The `idioms` analysis pass.
+//!
+//! Suggests language features which make code shorter, but only if they are
+//! available in the Java version given by `--java-version` (or the config
+//! key `java_version`). Without a version, nothing is suggested.
+//!
+//! - `var` for local variables initialized with `new` of the same type
+//!   (Java 10)
+//! - text blocks instead of concatenated string literals spanning multiple
+//!   lines (Java 15)
+
+use analyze::Finding;
+use analyze::custom::{self, Node};
+use base::code::{FileMap, Span};
+use base::diag::Report;
+use syntax::ast::{self, BinOpType, ExprType};
+use syntax::lex::Lit;
+
+pub const USE_VAR: &'static str = "use-var";
+pub const TEXT_BLOCK: &'static str = "text-block";
+
+/// Minimum number of lines of a concatenation suggested as text block
+const MIN_TEXT_BLOCK_LINES: usize = 3;
+
+pub fn check(java_version: Option<u32>, file_map: &FileMap, ast: &ast::CompilationUnit)
+    -> Vec<Finding>
+{
+    let version = match java_version {
+        Some(version) => version,
+        None => return Vec::new(),
+    };
+
+    let src = file_map.src();
+    let mut findings = Vec::new();
+    // End of the last concatenation suggested as text block, since its
+    // operands are visited afterwards
+    let mut concat_end = 0;
+    custom::walk(ast, &mut |node| {
+        match node {
+            Node::LocalVariables(ty, vars) if version >= 10 => {
+                if let Some(finding) = check_var(src, ty, vars) {
+                    findings.push(finding);
+                }
+            },
+            Node::Expr(e) if version >= 15 && e.span.lo.0 >= concat_end => {
+                if let Some(finding) = check_text_block(src, e) {
+                    concat_end = e.span.hi.0;
+                    findings.push(finding);
+                }
+            },
+            _ => {},
+        }
+    });
+    findings
+}
+
+/// Checks a declaration like `Foo foo = new Foo();`, whose type is repeated.
+fn check_var(src: &str, ty: &ast::Type, vars: &[ast::VariableDeclarator]) -> Option<Finding> {
+    if vars.len() != 1 || vars[0].dims != 0 || ty.dims != 0 {
+        return None;
+    }
+    let var = &vars[0];
+    let (name, body) = match var.init.as_ref().map(|init| &init.expr) {
+        Some(&ExprType::InstanceCreation { ref name, ref body, .. }) => (name, body),
+        _ => return None,
+    };
+    let span = match ty.name.span() {
+        Some(span) => span,
+        None => return None,
+    };
+
+    // `var` would have the type of the anonymous class
+    let ty_text = &src[span.into_range()];
+    if body.is_some() || name.span().map(|s| &src[s.into_range()]) != Some(ty_text) {
+        return None;
+    }
+
+    let rep = Report::simple_warning(
+        format!("the type `{}` of `{}` is repeated", ty_text, var.name.name),
+        span,
+    ).with_note("use `var` instead, the type is clear from the initializer");
+    Some(Finding::new(USE_VAR, rep).with_edit(span, "var"))
+}
+
+/// Checks whether the expression is a concatenation of string literals
+/// spanning multiple lines, which all end with a line break.
+fn check_text_block(src: &str, e: &ast::Expr) -> Option<Finding> {
+    let mut literals = Vec::new();
+    if !collect_literals(e, &mut literals) || literals.len() < MIN_TEXT_BLOCK_LINES {
+        return None;
+    }
+    let lines_end = literals.iter().take(literals.len() - 1).all(|s| s.ends_with('\n'));
+    if !lines_end || !src[e.span.into_range()].contains('\n') {
+        return None;
+    }
+
+    let rep = Report::simple_warning(
+        format!("string of {} lines built by concatenation", literals.len()),
+        Span::new(e.span.lo, e.span.lo),
+    ).with_note("use a text block (`\"\"\"`) instead");
+    Some(Finding::new(TEXT_BLOCK, rep))
+}
+
+/// Collects the string literals of a concatenation. Returns false if it
+/// contains anything else.
+fn collect_literals<'a>(e: &'a ast::Expr, out: &mut Vec<&'a str>) -> bool {
+    match e.expr {
+        ExprType::BinOp { op: BinOpType::Add, ref lhs, ref rhs } => {
+            collect_literals(lhs, out) && collect_literals(rhs, out)
+        },
+        ExprType::Literal(Lit::Str(ref s)) => {
+            out.push(s);
+            true
+        },
+        _ => false,
+    }
+}
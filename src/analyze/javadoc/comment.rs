@@ -0,0 +1,82 @@
+//! Parsing of the block tags (like `@param`) of Javadoc comments.
+
+use base::code::{BytePos, Span};
+
+/// A block tag of a Javadoc comment
+#[derive(Clone, Debug)]
+pub struct Tag<'a> {
+    /// Name of the tag without the `@`, e.g. `param`
+    pub name: &'a str,
+    /// The first word after the tag name, e.g. the parameter name of `@param`
+    pub arg: Option<&'a str>,
+    /// Span of the tag name and its argument
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct Javadoc<'a> {
+    pub tags: Vec<Tag<'a>>,
+    /// Whether the comment contains `{@inheritDoc}`, i.e. the documentation
+    /// is taken from an overridden method
+    pub inherit_doc: bool,
+}
+
+impl<'a> Javadoc<'a> {
+    /// Returns all tags with one of the given names.
+    pub fn tags_named(&self, names: &[&str]) -> Vec<&Tag<'a>> {
+        self.tags.iter().filter(|t| names.contains(&t.name)).collect()
+    }
+}
+
+/// Parses the Javadoc comment (including `/**` and `*/`) at `span` in `src`.
+///
+/// Block tags have to be at the beginning of a line (after an optional `*`).
+/// Tags in the middle of a line are part of the description.
+pub fn parse<'a>(src: &'a str, span: Span) -> Javadoc<'a> {
+    let text = &src[span.into_range()];
+    let mut tags = Vec::new();
+
+    let mut line_start = span.lo.0 as usize;
+    for line in text.split('\n') {
+        let offset = line_start;
+        line_start += line.len() + 1;
+
+        // Skip the comment start and leading asterisks
+        let rest = line.trim_left();
+        let rest = if rest.starts_with("/**") { &rest[3..] } else { rest };
+        let rest = rest.trim_left_matches('*').trim_left();
+        if !rest.starts_with('@') {
+            continue;
+        }
+        let at = offset + (rest.as_ptr() as usize - line.as_ptr() as usize);
+
+        let rest = rest.trim_right().trim_right_matches("*/");
+        let name_len = rest[1..]
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(rest.len() - 1);
+        let name = &rest[1..1 + name_len];
+        if name.is_empty() {
+            continue;
+        }
+
+        let after_name = &rest[1 + name_len..];
+        let arg = after_name.split_whitespace().next();
+        let end = match arg {
+            Some(arg) => {
+                at + (arg.as_ptr() as usize - rest.as_ptr() as usize) + arg.len()
+            },
+            None => at + 1 + name_len,
+        };
+
+        tags.push(Tag {
+            name: name,
+            arg: arg,
+            span: Span::new(BytePos(at as u32), BytePos(end as u32)),
+        });
+    }
+
+    Javadoc {
+        tags: tags,
+        inherit_doc: text.contains("{@inheritDoc}"),
+    }
+}
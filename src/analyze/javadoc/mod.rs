@@ -0,0 +1,275 @@
+//! The `javadoc` analysis pass.
+//!
+//! Requires Javadoc comments on all types and methods which are at least as
+//! visible as configured (`javadoc.visibility`) and checks that the block
+//! tags of method comments match the declaration: every parameter has to be
+//! documented with `@param`, `@return` has to be present exactly for non-void
+//! methods and `@throws` has to match the declared exceptions.
+//!
+//! Comments aren't part of the AST, so the comment of a declaration is
+//! searched in the tokens before its name. The modifiers of types are read
+//! from the tokens as well, since the AST doesn't store them.
+
+mod comment;
+
+use analyze::{self, Finding};
+use base::code::{FileMap, Span};
+use base::diag::Report;
+use config::JavadocConfig;
+use self::comment::Javadoc;
+use syntax::ast::{self, ItemExt, Visibility};
+use syntax::lex::{Keyword, Token, TokenSpan};
+
+pub const MISSING: &'static str = "missing-javadoc";
+pub const PARAM: &'static str = "javadoc-param";
+pub const RETURN: &'static str = "javadoc-return";
+pub const THROWS: &'static str = "javadoc-throws";
+
+/// Unchecked exceptions from `java.lang` which are commonly documented with
+/// `@throws`, but not declared.
+const UNCHECKED: &'static [&'static str] = &[
+    "ArithmeticException",
+    "ArrayIndexOutOfBoundsException",
+    "ClassCastException",
+    "IllegalArgumentException",
+    "IllegalStateException",
+    "IndexOutOfBoundsException",
+    "NullPointerException",
+    "NumberFormatException",
+    "RuntimeException",
+    "UnsupportedOperationException",
+];
+
+/// What we know about a declaration from the tokens before its name
+struct Header<'a> {
+    doc: Option<Javadoc<'a>>,
+    vis: Visibility,
+    override_: bool,
+}
+
+struct Checker<'a> {
+    config: &'a JavadocConfig,
+    src: &'a str,
+    tokens: Vec<TokenSpan>,
+    findings: Vec<Finding>,
+}
+
+pub fn check(config: &JavadocConfig, file_map: &FileMap, ast: &ast::CompilationUnit)
+    -> Vec<Finding>
+{
+    let mut checker = Checker {
+        config: config,
+        src: file_map.src(),
+        tokens: analyze::tokenize(file_map),
+        findings: Vec::new(),
+    };
+    for ty in &ast.types {
+        checker.check_type(ty, Visibility::Public);
+    }
+    checker.findings
+}
+
+impl<'a> Checker<'a> {
+    /// Checks a type and all of its members. `outer` is the visibility of the
+    /// enclosing type: a member can't be more visible than that.
+    fn check_type(&mut self, ty: &ast::TypeDef, outer: Visibility) {
+        let name = match ty.ident() {
+            Some(name) => name,
+            None => return,
+        };
+        let header = self.header(name);
+        let vis = least_visible(outer, header.vis);
+        let kind = match *ty {
+            ast::TypeDef::NormalClass(_) => "class",
+            ast::TypeDef::NormalInterface(_) => "interface",
+        };
+        if header.doc.is_none() && self.is_required(vis) {
+            self.missing(kind, name);
+        }
+
+        match *ty {
+            ast::TypeDef::NormalClass(ref c) => {
+                for member in &c.members {
+                    if let ast::ClassMember::Method(ref m) = *member {
+                        self.check_method(m, vis);
+                    }
+                }
+            },
+            ast::TypeDef::NormalInterface(ref i) => {
+                for m in &i.methods {
+                    self.check_method(m, vis);
+                }
+                for ty in &i.types {
+                    self.check_type(ty, vis);
+                }
+            },
+        }
+    }
+
+    fn check_method(&mut self, m: &ast::Method, outer: Visibility) {
+        let header = self.header(&m.name);
+        let doc = match header.doc {
+            Some(doc) => doc,
+            None => {
+                // Overriding methods inherit the documentation
+                if !header.override_ && self.is_required(least_visible(outer, m.vis)) {
+                    self.missing("method", &m.name);
+                }
+                return;
+            },
+        };
+        if doc.inherit_doc {
+            return;
+        }
+
+        // `@param`
+        let mut documented = Vec::new();
+        for tag in doc.tags_named(&["param"]) {
+            let arg = match tag.arg {
+                Some(arg) => arg,
+                None => {
+                    self.warn(PARAM, "`@param` tag without parameter name", tag.span);
+                    continue;
+                },
+            };
+            // Type parameters, e.g. `@param <T>`
+            if arg.starts_with('<') {
+                continue;
+            }
+
+            if !m.params.iter().any(|p| p.name.name == arg) {
+                let msg = format!("`@param` documents unknown parameter `{}`", arg);
+                self.warn(PARAM, msg, tag.span);
+            } else if documented.contains(&arg) {
+                let msg = format!("parameter `{}` is documented twice", arg);
+                self.warn(PARAM, msg, tag.span);
+            } else {
+                documented.push(arg);
+            }
+        }
+        for param in &m.params {
+            if !documented.contains(&&param.name.name[..]) {
+                self.warn(
+                    PARAM,
+                    format!("parameter `{}` is not documented", param.name.name),
+                    param.name.span,
+                );
+            }
+        }
+
+        // `@return`
+        let is_void = m.ret_ty.dims == 0 && m.ret_ty.name.segments.len() == 1
+            && m.ret_ty.name.segments[0].name == "void";
+        let returns = doc.tags_named(&["return"]);
+        if is_void {
+            for tag in returns {
+                self.warn(RETURN, "`@return` tag on a method returning `void`", tag.span);
+            }
+        } else if returns.is_empty() {
+            self.warn(RETURN, "the return value is not documented", m.name.span);
+        }
+
+        // `@throws`
+        let declared: Vec<_> = m.throws.iter()
+            .filter_map(|ty| ty.name.segments.last())
+            .collect();
+        let mut documented = Vec::new();
+        for tag in doc.tags_named(&["throws", "exception"]) {
+            let arg = match tag.arg {
+                Some(arg) => arg,
+                None => {
+                    self.warn(THROWS, "`@throws` tag without exception name", tag.span);
+                    continue;
+                },
+            };
+            let simple = arg.rsplit('.').next().unwrap_or(arg);
+            documented.push(simple);
+
+            if !declared.iter().any(|d| d.name == simple) && !UNCHECKED.contains(&simple) {
+                let msg = format!(
+                    "`@throws` documents `{}`, which is not declared to be thrown",
+                    arg,
+                );
+                self.warn(THROWS, msg, tag.span);
+            }
+        }
+        for ex in declared {
+            if !documented.contains(&&ex.name[..]) {
+                let msg = format!("exception `{}` is not documented", ex.name);
+                self.warn(THROWS, msg, ex.span);
+            }
+        }
+    }
+
+    /// Collects information about the declaration of the given name from the
+    /// tokens before it: everything up to the end of the previous
+    /// declaration or statement.
+    fn header(&self, name: &ast::Ident) -> Header<'a> {
+        let mut header = Header {
+            doc: None,
+            vis: Visibility::Package,
+            override_: false,
+        };
+        let end = match self.tokens.binary_search_by_key(&name.span.lo, |ts| ts.span.lo) {
+            Ok(idx) => idx,
+            Err(_) => return header,
+        };
+
+        let src = self.src;
+        let mut after = None;
+        for ts in self.tokens[..end].iter().rev() {
+            match ts.tok {
+                Token::Semi | Token::BraceOp | Token::BraceCl => break,
+                Token::Comment if header.doc.is_none() => {
+                    if src[ts.span.into_range()].starts_with("/**") {
+                        header.doc = Some(comment::parse(src, ts.span));
+                    }
+                },
+                Token::KeyW(Keyword::Public) => header.vis = Visibility::Public,
+                Token::KeyW(Keyword::Protected) => header.vis = Visibility::Protected,
+                Token::KeyW(Keyword::Private) => header.vis = Visibility::Private,
+                Token::At => if after == Some("Override") {
+                    header.override_ = true;
+                },
+                _ => {},
+            }
+            if ts.tok.is_real() {
+                after = match ts.tok {
+                    Token::Ident(_) => Some(&src[ts.span.into_range()]),
+                    _ => None,
+                };
+            }
+        }
+        header
+    }
+
+    fn is_required(&self, vis: Visibility) -> bool {
+        rank(vis) >= rank(self.config.visibility)
+    }
+
+    fn missing(&mut self, kind: &str, name: &ast::Ident) {
+        self.warn(
+            MISSING,
+            format!("missing Javadoc comment for {} `{}`", kind, name.name),
+            name.span,
+        );
+    }
+
+    fn warn<S: Into<String>>(&mut self, rule: &'static str, msg: S, span: Span) {
+        self.findings.push(Finding::new(rule, Report::simple_warning(msg, span)));
+    }
+}
+
+/// Orders visibilities from `private` (0) to `public` (3).
+fn rank(vis: Visibility) -> u8 {
+    match vis {
+        Visibility::Private => 0,
+        Visibility::Package => 1,
+        Visibility::Protected => 2,
+        Visibility::Public => 3,
+    }
+}
+
+fn least_visible(a: Visibility, b: Visibility) -> Visibility {
+    if rank(a) <= rank(b) { a } else { b }
+}
@@ -0,0 +1,419 @@
+//! The `metrics` analysis pass.
+//!
+//! Measures the complexity and size of every method and class and warns if
+//! one of them exceeds the thresholds of the `[metrics]` configuration:
+//!
+//! - *cyclomatic complexity*: one plus the number of decision points
+//!   (conditions of `if`, loops and `?:`, `case` labels, `&&` and `||`)
+//! - *cognitive complexity*: as defined by SonarSource; control structures
+//!   cost more the deeper they are nested, sequences of equal boolean
+//!   operators count only once
+//! - *nesting depth*: maximum number of nested control structures
+//! - number of parameters
+//! - length of methods and classes in lines, from the line of the name to
+//!   the closing brace
+//!
+//! The metrics can also be written as a report (`--metrics-report`).
+
+pub mod report;
+
+use analyze::{self, Finding};
+use base::code::{FileMap, Span};
+use base::diag::Report;
+use config::MetricsConfig;
+use syntax::ast::{self, BinOpType, ExprType, ItemExt, StatementType};
+use syntax::lex::{Token, TokenSpan};
+
+pub const CYCLOMATIC: &'static str = "cyclomatic-complexity";
+pub const COGNITIVE: &'static str = "cognitive-complexity";
+pub const NESTING: &'static str = "nesting-depth";
+pub const PARAMS: &'static str = "parameter-count";
+pub const METHOD_LENGTH: &'static str = "method-length";
+pub const CLASS_LENGTH: &'static str = "class-length";
+
+/// Metrics of one class or interface
+#[derive(Clone, Debug)]
+pub struct ClassMetrics {
+    pub name: String,
+    /// Span of the name
+    pub span: Span,
+    pub length: usize,
+    pub methods: Vec<MethodMetrics>,
+}
+
+/// Metrics of one method
+#[derive(Clone, Debug)]
+pub struct MethodMetrics {
+    pub name: String,
+    /// Span of the name
+    pub span: Span,
+    pub cyclomatic: usize,
+    pub cognitive: usize,
+    pub nesting: usize,
+    pub params: usize,
+    pub length: usize,
+}
+
+pub fn check(config: &MetricsConfig, file_map: &FileMap, ast: &ast::CompilationUnit)
+    -> Vec<Finding>
+{
+    let mut findings = Vec::new();
+    {
+        let mut limit = |rule, what: &str, value: usize, max: usize, span| {
+            if max > 0 && value > max {
+                findings.push(Finding::new(rule, Report::simple_warning(
+                    format!("{} is {}, but should be at most {}", what, value, max),
+                    span,
+                )));
+            }
+        };
+
+        for class in measure(file_map, ast) {
+            let what = format!("length of `{}` (in lines)", class.name);
+            limit(CLASS_LENGTH, &what, class.length, config.max_class_length, class.span);
+
+            for m in class.methods {
+                let name = format!("`{}`", m.name);
+                let checks = [
+                    (CYCLOMATIC, "cyclomatic complexity", m.cyclomatic, config.max_cyclomatic),
+                    (COGNITIVE, "cognitive complexity", m.cognitive, config.max_cognitive),
+                    (NESTING, "nesting depth", m.nesting, config.max_nesting),
+                    (PARAMS, "number of parameters", m.params, config.max_params),
+                    (METHOD_LENGTH, "length (in lines)", m.length, config.max_method_length),
+                ];
+                for &(rule, what, value, max) in &checks {
+                    limit(rule, &format!("{} of {}", what, name), value, max, m.span);
+                }
+            }
+        }
+    }
+    findings
+}
+
+/// Measures all classes and interfaces of the compilation unit.
+pub fn measure(file_map: &FileMap, ast: &ast::CompilationUnit) -> Vec<ClassMetrics> {
+    let tokens = analyze::tokenize(file_map);
+    let mut classes = Vec::new();
+    for ty in &ast.types {
+        measure_type(file_map, &tokens, ty, &mut classes);
+    }
+    classes
+}
+
+fn measure_type(
+    file_map: &FileMap,
+    tokens: &[TokenSpan],
+    ty: &ast::TypeDef,
+    out: &mut Vec<ClassMetrics>,
+) {
+    let name = match ty.ident() {
+        Some(name) => name,
+        None => return,
+    };
+
+    let mut nested = Vec::new();
+    let methods: Vec<_> = match *ty {
+        ast::TypeDef::NormalClass(ref c) => {
+            c.members.iter().filter_map(|m| match *m {
+                ast::ClassMember::Method(ref m) => Some(m),
+                _ => None,
+            }).collect()
+        },
+        ast::TypeDef::NormalInterface(ref i) => {
+            nested.extend(i.types.iter());
+            i.methods.iter().collect()
+        },
+    };
+
+    out.push(ClassMetrics {
+        name: name.name.clone(),
+        span: name.span,
+        length: length(file_map, tokens, name.span),
+        methods: methods.iter().map(|m| measure_method(file_map, tokens, m)).collect(),
+    });
+    for ty in nested {
+        measure_type(file_map, tokens, ty, out);
+    }
+}
+
+fn measure_method(file_map: &FileMap, tokens: &[TokenSpan], m: &ast::Method) -> MethodMetrics {
+    let mut counter = Counter {
+        method: &m.name.name,
+        cyclomatic: 1,
+        cognitive: 0,
+        nesting: 0,
+        max_nesting: 0,
+    };
+    if let Some(ref block) = m.block {
+        counter.block(block);
+    }
+
+    MethodMetrics {
+        name: m.name.name.clone(),
+        span: m.name.span,
+        cyclomatic: counter.cyclomatic,
+        cognitive: counter.cognitive,
+        nesting: counter.max_nesting,
+        params: m.params.len(),
+        length: length(file_map, tokens, m.name.span),
+    }
+}
+
+/// Returns the number of lines from the given name to the end of the body
+/// following it: the matching closing brace or a `;` if there is no body.
+fn length(file_map: &FileMap, tokens: &[TokenSpan], name: Span) -> usize {
+    let start = match tokens.binary_search_by_key(&name.lo, |ts| ts.span.lo) {
+        Ok(idx) => idx,
+        Err(_) => return 0,
+    };
+
+    let mut parens = 0i32;
+    let mut braces = 0i32;
+    let mut end = name.hi;
+    for ts in &tokens[start..] {
+        end = ts.span.lo;
+        match ts.tok {
+            Token::ParenOp => parens += 1,
+            Token::ParenCl => parens -= 1,
+            Token::Semi if parens == 0 && braces == 0 => break,
+            Token::BraceOp => braces += 1,
+            Token::BraceCl => {
+                braces -= 1;
+                if braces == 0 {
+                    break;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    let first = file_map.get_line_idx(name.lo);
+    let last = file_map.get_line_idx(end);
+    (last.0 - first.0) as usize + 1
+}
+
+/// Counts the complexity of one method
+struct Counter<'a> {
+    /// Name of the method, to find recursive calls
+    method: &'a str,
+    cyclomatic: usize,
+    cognitive: usize,
+    nesting: usize,
+    max_nesting: usize,
+}
+
+impl<'a> Counter<'a> {
+    fn block(&mut self, block: &ast::Block) {
+        for stmt in &block.stmts {
+            self.block_stmt(stmt);
+        }
+    }
+
+    fn block_stmt(&mut self, stmt: &ast::BlockStatement) {
+        match *stmt {
+            ast::BlockStatement::LocalVariableDecl { ref vars, .. } => {
+                for init in vars.iter().filter_map(|v| v.init.as_ref()) {
+                    self.expr(init);
+                }
+            },
+            ast::BlockStatement::Statement(ref stmt) => self.stmt(stmt),
+        }
+    }
+
+    /// Visits a statement nested in a control structure.
+    fn nested(&mut self, stmt: &ast::Statement) {
+        self.nesting += 1;
+        if self.nesting > self.max_nesting {
+            self.max_nesting = self.nesting;
+        }
+        self.stmt(stmt);
+        self.nesting -= 1;
+    }
+
+    fn stmt(&mut self, stmt: &ast::Statement) {
+        match stmt.stmt {
+            StatementType::Empty
+                | StatementType::Break(None)
+                | StatementType::Continue(None)
+                | StatementType::Return(None) => {},
+            // Jumps to labels break the linear flow
+            StatementType::Break(Some(_)) | StatementType::Continue(Some(_)) => {
+                self.cognitive += 1;
+            },
+            StatementType::Block(ref block) => self.block(block),
+            StatementType::Expr(ref e)
+                | StatementType::Throw(ref e)
+                | StatementType::Return(Some(ref e)) => self.expr(e),
+            StatementType::IfThenElse { ref cond, ref then_branch, ref else_branch } => {
+                self.if_else(cond, then_branch, else_branch.as_ref().map(|b| &**b), false);
+            },
+            StatementType::While { ref cond, ref body }
+                | StatementType::DoWhile { ref cond, ref body } => {
+                self.cyclomatic += 1;
+                self.cognitive += 1 + self.nesting;
+                self.expr(cond);
+                self.nested(body);
+            },
+            StatementType::For { ref init, ref cond, ref update, ref body } => {
+                self.cyclomatic += 1;
+                self.cognitive += 1 + self.nesting;
+                match *init {
+                    ast::ForInit::VarDecl(ref decl) => self.block_stmt(decl),
+                    ast::ForInit::Stmts(ref stmts) => for s in stmts {
+                        self.stmt(s);
+                    },
+                }
+                if let Some(ref cond) = *cond {
+                    self.expr(cond);
+                }
+                for s in update {
+                    self.stmt(s);
+                }
+                self.nested(body);
+            },
+            StatementType::Switch { ref val, ref arms, ref empty_arms } => {
+                let labels = arms.iter()
+                    .flat_map(|arm| arm.labels.iter())
+                    .chain(empty_arms.iter());
+                for label in labels {
+                    if let ast::SwitchLabel::Default = *label {
+                        continue;
+                    }
+                    self.cyclomatic += 1;
+                }
+                self.cognitive += 1 + self.nesting;
+                self.expr(val);
+
+                self.nesting += 1;
+                if self.nesting > self.max_nesting {
+                    self.max_nesting = self.nesting;
+                }
+                for arm in arms {
+                    self.block_stmt(&arm.block);
+                }
+                self.nesting -= 1;
+            },
+        }
+    }
+
+    /// `else if` doesn't increase the nesting and only costs one point of
+    /// cognitive complexity.
+    fn if_else(
+        &mut self,
+        cond: &ast::Expr,
+        then_branch: &ast::Statement,
+        else_branch: Option<&ast::Statement>,
+        is_else_if: bool,
+    ) {
+        self.cyclomatic += 1;
+        self.cognitive += if is_else_if { 1 } else { 1 + self.nesting };
+        self.expr(cond);
+        self.nested(then_branch);
+
+        match else_branch {
+            Some(&ast::Statement {
+                stmt: StatementType::IfThenElse { ref cond, ref then_branch, ref else_branch },
+                ..
+            }) => {
+                self.if_else(cond, then_branch, else_branch.as_ref().map(|b| &**b), true);
+            },
+            Some(stmt) => {
+                self.cognitive += 1;
+                self.nested(stmt);
+            },
+            None => {},
+        }
+    }
+
+    fn expr(&mut self, e: &ast::Expr) {
+        match e.expr {
+            ExprType::Conditional { ref cond, ref if_branch, ref else_branch } => {
+                self.cyclomatic += 1;
+                self.cognitive += 1 + self.nesting;
+                self.expr(cond);
+                self.nesting += 1;
+                self.expr(if_branch);
+                self.expr(else_branch);
+                self.nesting -= 1;
+            },
+            ExprType::BinOp { op: BinOpType::LogicalAnd, .. }
+                | ExprType::BinOp { op: BinOpType::LogicalOr, .. } => {
+                let mut ops = Vec::new();
+                let mut operands = Vec::new();
+                flatten_logical(e, &mut ops, &mut operands);
+
+                // Every change of the operator starts a new sequence
+                let changes = ops.windows(2).filter(|w| w[0] != w[1]).count();
+                self.cyclomatic += ops.len();
+                self.cognitive += 1 + changes;
+                for operand in operands {
+                    self.expr(operand);
+                }
+            },
+            ExprType::BinOp { ref lhs, ref rhs, .. } => {
+                self.expr(lhs);
+                self.expr(rhs);
+            },
+            ExprType::UnaryOp { ref expr, .. } | ExprType::Cast { ref expr, .. } => {
+                self.expr(expr);
+            },
+            ExprType::FieldAccess { root: Some(ref root), .. } => self.expr(root),
+            ExprType::ArrayAccess { ref obj, ref idx } => {
+                self.expr(obj);
+                self.expr(idx);
+            },
+            ExprType::MethodInvocation { ref name, ref args } => {
+                match *name {
+                    ast::MethodInvocationType::SimpleName(ref name) => {
+                        if name.name == self.method {
+                            // Recursion
+                            self.cognitive += 1;
+                        }
+                    },
+                    ast::MethodInvocationType::Expr(ref obj, _) => self.expr(obj),
+                    ast::MethodInvocationType::SimplePath(_) => {},
+                }
+                for arg in args {
+                    self.expr(arg);
+                }
+            },
+            ExprType::ArrayInit { ref items } => {
+                for item in items {
+                    self.expr(item);
+                }
+            },
+            ExprType::InstanceCreation { ref args, .. } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+            },
+            ExprType::ArrayCreation { ref expr_dims, ref init, .. } => {
+                for e in expr_dims {
+                    self.expr(e);
+                }
+                if let Some(ref init) = *init {
+                    self.expr(init);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Collects the operators (`true` for `&&`) and operands of a tree of `&&`
+/// and `||` expressions in source order.
+fn flatten_logical<'e>(e: &'e ast::Expr, ops: &mut Vec<bool>, operands: &mut Vec<&'e ast::Expr>) {
+    match e.expr {
+        ExprType::BinOp { op: BinOpType::LogicalAnd, ref lhs, ref rhs } => {
+            flatten_logical(lhs, ops, operands);
+            ops.push(true);
+            flatten_logical(rhs, ops, operands);
+        },
+        ExprType::BinOp { op: BinOpType::LogicalOr, ref lhs, ref rhs } => {
+            flatten_logical(lhs, ops, operands);
+            ops.push(false);
+            flatten_logical(rhs, ops, operands);
+        },
+        _ => operands.push(e),
+    }
+}
@@ -0,0 +1,111 @@
+//! Writing the metrics of all files as a table or as CSV.
+
+use job::{MetricsFormat, MetricsReport};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use super::ClassMetrics;
+
+const HEADER: &'static [&'static str] = &[
+    "file",
+    "class",
+    "method",
+    "cyclomatic",
+    "cognitive",
+    "nesting",
+    "params",
+    "lines",
+];
+
+/// Writes the report for the metrics of the given files.
+pub fn write(report: &MetricsReport, files: &[(PathBuf, Vec<ClassMetrics>)]) -> io::Result<()> {
+    let rows = rows(files);
+    let text = match report.format {
+        MetricsFormat::Table => table(&rows),
+        MetricsFormat::Csv => csv(&rows),
+    };
+
+    match report.path {
+        Some(ref path) => File::create(path).and_then(|mut f| f.write_all(text.as_bytes())),
+        None => {
+            print!("{}", text);
+            Ok(())
+        },
+    }
+}
+
+/// One row per class (with the class length only) and one row per method.
+fn rows(files: &[(PathBuf, Vec<ClassMetrics>)]) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    for &(ref file, ref classes) in files {
+        let file = file.display().to_string();
+        for class in classes {
+            rows.push(vec![
+                file.clone(),
+                class.name.clone(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                class.length.to_string(),
+            ]);
+            for m in &class.methods {
+                rows.push(vec![
+                    file.clone(),
+                    class.name.clone(),
+                    m.name.clone(),
+                    m.cyclomatic.to_string(),
+                    m.cognitive.to_string(),
+                    m.nesting.to_string(),
+                    m.params.to_string(),
+                    m.length.to_string(),
+                ]);
+            }
+        }
+    }
+    rows
+}
+
+fn table(rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<_> = HEADER.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = ::std::cmp::max(*w, cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    let header: Vec<_> = HEADER.iter().map(|h| h.to_string()).collect();
+    for row in Some(&header).into_iter().chain(rows) {
+        let mut line = String::new();
+        for (i, (cell, &w)) in row.iter().zip(&widths).enumerate() {
+            // Names are left aligned, numbers right aligned
+            if i < 3 {
+                line.push_str(&format!("{:<1$}  ", cell, w));
+            } else {
+                line.push_str(&format!("{:>1$}  ", cell, w));
+            }
+        }
+        out.push_str(line.trim_right());
+        out.push('\n');
+    }
+    out
+}
+
+fn csv(rows: &[Vec<String>]) -> String {
+    let mut out = HEADER.join(",");
+    out.push('\n');
+    for row in rows {
+        let cells: Vec<_> = row.iter().map(|cell| {
+            if cell.contains(|c| c == ',' || c == '"' || c == '\n') {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.clone()
+            }
+        }).collect();
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,310 @@
+//! Analysis passes that run on successfully parsed compilation units.
+//!
+//! Every pass gets the file map and the AST of one compilation unit and
+//! returns a list of findings. The passes don't print anything themselves, so
+//! the results can be used by the command line interface as well as by the
+//! language server.
+//!
+//! Files which can't be parsed only get the rules working on the tokens,
+//! see `bugs::check_tokens`.
+
+pub mod bugs;
+pub mod custom;
+pub mod duplicates;
+pub mod idioms;
+pub mod javadoc;
+pub mod metrics;
+pub mod style;
+pub mod unused;
+
+use base::code::{FileMap, Span};
+use base::diag::{self, Report};
+use baseline::Baseline;
+use check;
+use config::Config;
+use fix;
+use job::{AnalyzePass, Job};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use syntax::{self, ast, lex};
+
+/// A problem found by an analysis pass.
+#[derive(Clone, Debug)]
+pub struct Finding {
+    /// Name of the rule that produced this finding, e.g. `indentation`
+    pub rule: Cow<'static, str>,
+    pub report: Report,
+    /// Machine-applicable edits which fix the problem. Empty if the problem
+    /// can't be fixed automatically.
+    pub fix: Vec<Edit>,
+}
+
+impl Finding {
+    /// Creates a finding without any fix
+    pub fn new<R: Into<Cow<'static, str>>>(rule: R, report: Report) -> Finding {
+        Finding {
+            rule: rule.into(),
+            report: report,
+            fix: vec![],
+        }
+    }
+
+    /// Adds an edit to the fix of this finding
+    pub fn with_edit<S: Into<String>>(mut self, span: Span, with: S) -> Finding {
+        self.fix.push(Edit {
+            span: span,
+            with: with.into(),
+        });
+        self
+    }
+}
+
+/// Replaces the code in `span` with `with`. Empty spans insert text.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Edit {
+    pub span: Span,
+    pub with: String,
+}
+
+/// Runs the given passes on all files of the job and prints the resulting
+/// reports. Fails if at least one report is an error.
+///
+/// If the job wants to fix findings, this is delegated to `fix::fix_file`.
+///
+/// If a metrics report was requested, it's written after all files were
+/// analyzed. The same goes for the `duplicates` pass, which compares all
+/// files with each other.
+///
+/// Findings known in the baseline of the job aren't printed, neither are
+/// findings outside of the changed lines with `--changed-since`.
+pub fn analyze_all(job: &Job, passes: &VecDeque<AnalyzePass>) -> Result<(), ()> {
+    let mut baseline = match job.baseline {
+        Some(ref path) => match Baseline::load(path) {
+            Ok(b) => Some(b),
+            Err(e) => {
+                msg!(Error, "Could not read baseline '{}': {}", path.display(), e);
+                return Err(());
+            },
+        },
+        None => None,
+    };
+
+    let mut failed = false;
+    let mut file_maps = Vec::new();
+    for file in job.checked_files() {
+        if let Some(mode) = job.fix {
+            if fix::fix_file(job, passes, file, mode).is_err() {
+                failed = true;
+            }
+            continue;
+        }
+
+        // Errors while reading the file were already reported by the
+        // `Check` job, which always runs before this one.
+        if let Ok(src) = check::read_file(job, file) {
+            file_maps.push(FileMap::new(file.to_string_lossy().into_owned(), src));
+        }
+    }
+
+    let mut all_metrics = Vec::new();
+    for analysis in analyze_files(passes, &job.config, file_maps) {
+        if print_new_findings(job, &mut baseline, &analysis.file_map, analysis.findings) {
+            failed = true;
+        }
+        if let (Some(_), Some(ast)) = (job.metrics_report.as_ref(), analysis.ast.as_ref()) {
+            let path = PathBuf::from(analysis.file_map.filename());
+            all_metrics.push((path, metrics::measure(&analysis.file_map, ast)));
+        }
+    }
+
+    if let Some(ref baseline) = baseline {
+        if baseline.hidden > 0 {
+            msg!(Note, "{} known finding(s) hidden by the baseline", baseline.hidden);
+        }
+    }
+
+    if let Some(ref report) = job.metrics_report {
+        if let Err(e) = metrics::report::write(report, &all_metrics) {
+            msg!(Error, "Could not write metrics report: {}", e);
+            failed = true;
+        }
+    }
+
+    if failed {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// What the analysis found in one file
+pub struct FileAnalysis {
+    /// Knows all lines, even if the parser stopped early
+    pub file_map: FileMap,
+    /// `None` if the file couldn't be parsed
+    pub ast: Option<ast::CompilationUnit>,
+    /// Errors and warnings of the parser
+    pub parse_errors: Vec<Report>,
+    pub findings: Vec<Finding>,
+}
+
+/// Runs the given passes on the files, including the `duplicates` pass,
+/// which compares them with each other. Files which can't be parsed only get
+/// the rules working on the tokens.
+///
+/// Everything collecting findings (the analysis, the baseline and the
+/// report) uses this, so that all of them find the same.
+pub fn analyze_files(passes: &VecDeque<AnalyzePass>, config: &Config, files: Vec<FileMap>)
+    -> Vec<FileAnalysis>
+{
+    let mut analyses: Vec<_> = files.into_iter().map(|file_map| {
+        let (ast, parse_errors) = syntax::parse_compilation_unit(&file_map);
+        match ast {
+            Some(ast) => FileAnalysis {
+                findings: analyze_file(passes, config, &file_map, &ast),
+                file_map: file_map,
+                ast: Some(ast),
+                parse_errors: parse_errors,
+            },
+            // The parser doesn't know some statements, e.g. `try`
+            None => {
+                let file_map = with_all_lines(&file_map);
+                let findings = if passes.contains(&AnalyzePass::Bugs) {
+                    bugs::check_tokens(&file_map)
+                } else {
+                    Vec::new()
+                };
+                FileAnalysis {
+                    file_map: file_map,
+                    ast: None,
+                    parse_errors: parse_errors,
+                    findings: findings,
+                }
+            },
+        }
+    }).collect();
+
+    if passes.contains(&AnalyzePass::Duplicates) {
+        let duplicates = {
+            let file_maps: Vec<_> = analyses.iter().map(|a| &a.file_map).collect();
+            duplicates::check(&config.duplicates, &file_maps)
+        };
+        for (idx, finding) in duplicates {
+            analyses[idx].findings.push(finding);
+        }
+    }
+    analyses
+}
+
+/// Prints the given findings, except those outside of the changed lines and
+/// those known in the baseline. Returns whether at least one of the printed
+/// findings is an error.
+fn print_new_findings(
+    job: &Job,
+    baseline: &mut Option<Baseline>,
+    file_map: &FileMap,
+    mut findings: Vec<Finding>,
+) -> bool {
+    if let Some(ref changes) = job.changes {
+        changes.retain_changed(file_map, &mut findings);
+    }
+    if let Some(ref mut baseline) = *baseline {
+        baseline.retain_new(file_map, &mut findings);
+    }
+    print_findings(&findings, file_map)
+}
+
+/// Prints the given findings and returns whether at least one of them is an
+/// error.
+pub fn print_findings(findings: &[Finding], file_map: &FileMap) -> bool {
+    let mut error = false;
+    for finding in findings {
+        diag::print(&finding.report, file_map, diag::PrintOptions::default());
+        if finding.report.kind == diag::ReportKind::Error {
+            error = true;
+        }
+    }
+    error
+}
+
+/// Runs the given passes and the custom rules of the configuration on one
+/// compilation unit and returns all findings.
+pub fn analyze_file(
+    passes: &VecDeque<AnalyzePass>,
+    config: &Config,
+    file_map: &FileMap,
+    ast: &ast::CompilationUnit,
+) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for pass in passes {
+        match *pass {
+            AnalyzePass::Style => {
+                findings.extend(style::check(&config.style, file_map, ast));
+            },
+            AnalyzePass::Javadoc => {
+                findings.extend(javadoc::check(&config.javadoc, file_map, ast));
+            },
+            AnalyzePass::Metrics => {
+                findings.extend(metrics::check(&config.metrics, file_map, ast));
+            },
+            AnalyzePass::Bugs => findings.extend(bugs::check(file_map, ast)),
+            AnalyzePass::Unused => findings.extend(unused::check(ast)),
+            AnalyzePass::Idioms => {
+                findings.extend(idioms::check(config.java_version, file_map, ast));
+            },
+            // Needs all files at once, see `analyze_all`
+            AnalyzePass::Duplicates => {},
+        }
+    }
+
+    let mut rules: Vec<_> = config.rules.iter().map(|r| r as &custom::Rule).collect();
+    rules.extend(config.rust_rules.0.iter().map(|r| &**r));
+    if !rules.is_empty() {
+        findings.extend(custom::check(&rules, file_map, ast));
+    }
+    findings
+}
+
+/// Returns the tokens of the file which make up the code: no whitespace and
+/// comments, and no package and import declarations at the beginning.
+pub fn code_tokens(file_map: &FileMap) -> Vec<lex::TokenSpan> {
+    let mut tokens = tokenize(file_map);
+    tokens.retain(|ts| ts.tok.is_real());
+
+    let mut header_len = 0;
+    while let Some(ts) = tokens.get(header_len) {
+        match ts.tok {
+            lex::Token::KeyW(lex::Keyword::Package) | lex::Token::KeyW(lex::Keyword::Import) => {
+                header_len += tokens[header_len..].iter()
+                    .position(|ts| ts.tok == lex::Token::Semi)
+                    .map(|pos| pos + 1)
+                    .unwrap_or(tokens.len() - header_len);
+            },
+            _ => break,
+        }
+    }
+    tokens.split_off(header_len)
+}
+
+/// Returns a copy of the file map which knows the beginnings of all lines.
+/// The parser only registers the lines it got to, so after a syntax error,
+/// reports about later lines can't be printed with the original one.
+fn with_all_lines(file_map: &FileMap) -> FileMap {
+    let copy = FileMap::new(file_map.filename(), file_map.src());
+    for _ in lex::Tokenizer::new(&copy).take_while(|res| res.is_ok()) {}
+    copy
+}
+
+/// Returns all tokens of the file, including whitespace and comments.
+///
+/// The tokenizer registers line beginnings in the file map it works on. To
+/// not mess up the lines of the original file map, a fresh copy is used.
+pub fn tokenize(file_map: &FileMap) -> Vec<lex::TokenSpan> {
+    let copy = FileMap::new(file_map.filename(), file_map.src());
+    let tokens = lex::Tokenizer::new(&copy)
+        .take_while(|res| res.is_ok())
+        .map(Result::unwrap)
+        .collect();
+    tokens
+}
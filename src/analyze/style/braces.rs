@@ -0,0 +1,112 @@
+//! Checks the placement of braces.
+//!
+//! An opening brace belongs at the end of the line that introduces the block
+//! (e.g. `if (x) {`). A closing brace has to be the first token in its line,
+//! unless the whole block is written in one line. Closing braces of array
+//! initializers may be placed anywhere.
+
+use analyze::Finding;
+use base::code::{FileMap, Span};
+use base::diag::Report;
+use syntax::lex::{Token, TokenSpan};
+use super::{last_line, leading_whitespace};
+
+pub const RULE: &'static str = "brace-placement";
+
+/// Whether an opening brace following the given token introduces the block
+/// of a declaration or statement (like `if (x) {`). All other braces start a
+/// block on their own or an array initializer, which may be placed on a new
+/// line.
+pub fn introduces_block(prev: &Token) -> bool {
+    match *prev {
+        Token::ParenCl | Token::BracketCl | Token::Arrow => true,
+        Token::Ident(_) | Token::KeyW(_) => true,
+        _ => false,
+    }
+}
+
+/// Whether an opening brace following the given token starts an array
+/// initializer (like `= {1, 2}`), given whether the enclosing braces are an
+/// array initializer, too.
+pub fn opens_initializer(prev: Option<&Token>, in_initializer: bool) -> bool {
+    match prev {
+        Some(&Token::Eq) | Some(&Token::BracketCl) | Some(&Token::Comma) => true,
+        Some(&Token::BraceOp) => in_initializer,
+        _ => false,
+    }
+}
+
+pub fn check(file_map: &FileMap, tokens: &[TokenSpan]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    // Line indices of all opening braces that weren't closed yet and whether
+    // they start an array initializer
+    let mut open: Vec<(_, bool)> = Vec::new();
+
+    for (i, ts) in tokens.iter().enumerate() {
+        let line = file_map.get_line_idx(ts.span.lo);
+        match ts.tok {
+            Token::BraceOp => {
+                let prev_idx = tokens[..i].iter().rposition(|t| t.tok.is_real());
+                let in_init = open.last().map(|o| o.1).unwrap_or(false);
+                let init = opens_initializer(prev_idx.map(|idx| &tokens[idx].tok), in_init);
+                open.push((line, init));
+
+                let prev_idx = match prev_idx {
+                    Some(idx) => idx,
+                    None => continue,
+                };
+                let prev = &tokens[prev_idx];
+                if !introduces_block(&prev.tok) || last_line(file_map, prev) == line {
+                    continue;
+                }
+
+                let prev_str = &file_map.src()[prev.span.into_range()];
+                let rep = Report::simple_warning(
+                    format!("opening brace should be placed at the end of the \
+                        previous line (after `{}`)", prev_str),
+                    ts.span,
+                );
+                let mut finding = Finding::new(RULE, rep);
+
+                // We can only move the brace if there are no comments between
+                if tokens[prev_idx + 1..i].iter().all(|t| t.tok == Token::Whitespace) {
+                    finding = finding.with_edit(Span::new(prev.span.hi, ts.span.lo), " ");
+                }
+                findings.push(finding);
+            },
+            Token::BraceCl => {
+                let open_line = match open.pop() {
+                    Some((_, true)) | None => continue,
+                    Some((l, false)) => l,
+                };
+
+                // Blocks written in one line are fine
+                if open_line == line {
+                    continue;
+                }
+
+                let prev = match tokens[..i].iter().rev().find(|t| t.tok != Token::Whitespace) {
+                    Some(prev) => prev,
+                    None => continue,
+                };
+                if last_line(file_map, prev) != line {
+                    continue;
+                }
+
+                let rep = Report::simple_warning(
+                    "closing brace should be placed at the beginning of a new line",
+                    ts.span,
+                );
+                let indent = leading_whitespace(file_map, open_line);
+                findings.push(Finding::new(RULE, rep).with_edit(
+                    Span::new(prev.span.hi, ts.span.lo),
+                    format!("\n{}", indent),
+                ));
+            },
+            _ => {},
+        }
+    }
+
+    findings
+}
@@ -0,0 +1,279 @@
+//! Rules about import declarations: unused, duplicate, wildcard and
+//! redundant (`java.lang`) imports as well as their order.
+//!
+//! The imports are taken from the AST, but references to imported names are
+//! searched in the tokens of the compilation unit: the AST doesn't contain all
+//! type references (e.g. in `extends` clauses or annotations), so looking
+//! only at the AST would report used imports as unused.
+//!
+//! The order is given by `StyleConfig::import_order`, a list of package
+//! prefixes. Every import belongs to the group with the longest matching
+//! prefix. The special group `*` matches all other imports and `static`
+//! matches all static imports. Imports have to be sorted by group and
+//! alphabetically within each group.
+
+use analyze::Finding;
+use base::code::{BytePos, FileMap, Span};
+use base::diag::Report;
+use config::StyleConfig;
+use std::cmp::Ordering;
+use syntax::ast;
+use syntax::lex::{Keyword, Token, TokenSpan};
+
+pub const UNUSED: &'static str = "unused-import";
+pub const DUPLICATE: &'static str = "duplicate-import";
+pub const WILDCARD: &'static str = "wildcard-import";
+pub const REDUNDANT: &'static str = "redundant-import";
+pub const ORDER: &'static str = "import-order";
+
+/// An import declaration with its position in the source code
+struct Decl<'a> {
+    import: &'a ast::Import,
+    /// Full name as written in the source, e.g. `java.util.*`
+    name: String,
+    /// From `import` to `;`
+    span: Span,
+}
+
+impl<'a> Decl<'a> {
+    fn path(&self) -> &ast::Path {
+        match *self.import {
+            ast::Import::SingleType(ref p)
+                | ast::Import::TypeOnDemand(ref p)
+                | ast::Import::SingleStatic(ref p)
+                | ast::Import::StaticOnDemand(ref p) => p,
+        }
+    }
+
+    fn is_static(&self) -> bool {
+        match *self.import {
+            ast::Import::SingleStatic(_) | ast::Import::StaticOnDemand(_) => true,
+            _ => false,
+        }
+    }
+
+    fn is_wildcard(&self) -> bool {
+        match *self.import {
+            ast::Import::TypeOnDemand(_) | ast::Import::StaticOnDemand(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The simple name this declaration imports, if it's not a wildcard
+    fn simple_name(&self) -> Option<&str> {
+        if self.is_wildcard() {
+            None
+        } else {
+            self.path().segments.last().map(|s| &s.name[..])
+        }
+    }
+}
+
+pub fn check(
+    config: &StyleConfig,
+    file_map: &FileMap,
+    ast: &ast::CompilationUnit,
+    tokens: &[TokenSpan],
+) -> Vec<Finding> {
+    let decls = declarations(ast, tokens);
+    let mut findings = Vec::new();
+    if decls.is_empty() {
+        return findings;
+    }
+
+    // All tokens after the last import
+    let last_import_end = decls[decls.len() - 1].span.hi;
+    let body: Vec<_> = tokens.iter().filter(|ts| ts.span.lo >= last_import_end).collect();
+
+    for (i, decl) in decls.iter().enumerate() {
+        let removal = removal_span(file_map.src(), decl.span);
+
+        let first = decls[..i].iter()
+            .find(|d| d.name == decl.name && d.is_static() == decl.is_static());
+        if let Some(first) = first {
+            let rep = Report::simple_warning(
+                format!("duplicate import of `{}`", decl.name),
+                decl.span,
+            ).with_span_note("first imported here", first.span);
+            findings.push(Finding::new(DUPLICATE, rep).with_edit(removal, ""));
+            continue;
+        }
+
+        if is_java_lang(decl) {
+            let rep = Report::simple_warning(
+                format!("`{}` doesn't need to be imported", decl.name),
+                decl.span,
+            ).with_note("all types of `java.lang` are imported automatically");
+            findings.push(Finding::new(REDUNDANT, rep).with_edit(removal, ""));
+            continue;
+        }
+
+        if decl.is_wildcard() {
+            let rep = Report::simple_warning(
+                format!("wildcard import `{}`", decl.name),
+                decl.span,
+            ).with_note("import the used types explicitly instead");
+            findings.push(Finding::new(WILDCARD, rep));
+            continue;
+        }
+
+        if let Some(name) = decl.simple_name() {
+            if !body.iter().any(|ts| is_reference(file_map.src(), ts, name)) {
+                let rep = Report::simple_warning(
+                    format!("unused import `{}`", decl.name),
+                    decl.span,
+                );
+                findings.push(Finding::new(UNUSED, rep).with_edit(removal, ""));
+            }
+        }
+    }
+
+    findings.extend(check_order(config, file_map.src(), &decls, tokens));
+    findings
+}
+
+/// Pairs the imports of the AST with the spans of their declarations.
+fn declarations<'a>(ast: &'a ast::CompilationUnit, tokens: &[TokenSpan]) -> Vec<Decl<'a>> {
+    // Imports are only allowed before all type declarations, so every
+    // `import` keyword we find is the start of the next import.
+    let mut spans = Vec::new();
+    let mut start = None;
+    for ts in tokens {
+        match ts.tok {
+            Token::KeyW(Keyword::Import) => start = Some(ts.span.lo),
+            Token::Semi => if let Some(lo) = start.take() {
+                spans.push(Span::new(lo, ts.span.hi));
+            },
+            Token::KeyW(Keyword::Class) | Token::KeyW(Keyword::Interface) => break,
+            _ => {},
+        }
+    }
+
+    ast.imports.iter().zip(spans).map(|(import, span)| {
+        let mut decl = Decl {
+            import: import,
+            name: String::new(),
+            span: span,
+        };
+        let mut name: Vec<_> = decl.path().segments.iter().map(|s| &s.name[..]).collect();
+        if decl.is_wildcard() {
+            name.push("*");
+        }
+        decl.name = name.join(".");
+        decl
+    }).collect()
+}
+
+/// Whether the declaration imports something from `java.lang` (but not from
+/// one of its subpackages).
+fn is_java_lang(decl: &Decl) -> bool {
+    if decl.is_static() {
+        return false;
+    }
+    let segments: Vec<_> = decl.path().segments.iter().map(|s| &s.name[..]).collect();
+    match *decl.import {
+        ast::Import::SingleType(_) => segments.len() == 3 && segments[..2] == ["java", "lang"],
+        ast::Import::TypeOnDemand(_) => segments == ["java", "lang"],
+        _ => false,
+    }
+}
+
+/// Whether the token references the given name. Names mentioned in Javadoc
+/// comments (e.g. in `{@link Foo}`) count as well, since removing the import
+/// would break the link.
+fn is_reference(src: &str, ts: &TokenSpan, name: &str) -> bool {
+    match ts.tok {
+        Token::Ident(ref ident) => ident == name,
+        Token::Comment => {
+            let text = &src[ts.span.into_range()];
+            text.starts_with("/**") && text
+                .split(|c: char| !c.is_alphanumeric() && c != '_' && c != '$')
+                .any(|word| word == name)
+        },
+        _ => false,
+    }
+}
+
+/// Returns the span to delete when removing the declaration: the whole line,
+/// if the declaration is the only thing in it.
+fn removal_span(src: &str, span: Span) -> Span {
+    let (lo, hi) = (span.lo.0 as usize, span.hi.0 as usize);
+    let line_start = src[..lo].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = src[hi..].find('\n').map(|i| hi + i + 1).unwrap_or(src.len());
+    if src[line_start..lo].trim().is_empty() && src[hi..line_end].trim().is_empty() {
+        Span::new(BytePos(line_start as u32), BytePos(line_end as u32))
+    } else {
+        span
+    }
+}
+
+/// Returns the index of the group the declaration belongs to.
+fn group(order: &[String], decl: &Decl) -> usize {
+    if decl.is_static() {
+        if let Some(i) = order.iter().position(|g| g == "static") {
+            return i;
+        }
+    }
+
+    let matching = order.iter().enumerate()
+        .filter(|&(_, g)| decl.name.starts_with(&format!("{}.", g)))
+        .max_by_key(|&(_, g)| g.len())
+        .map(|(i, _)| i);
+    matching
+        .or_else(|| order.iter().position(|g| g == "*"))
+        .unwrap_or(order.len())
+}
+
+fn check_order(config: &StyleConfig, src: &str, decls: &[Decl], tokens: &[TokenSpan])
+    -> Option<Finding>
+{
+    let order = &config.import_order;
+    let cmp = |a: &Decl, b: &Decl| {
+        (group(order, a), &a.name).cmp(&(group(order, b), &b.name))
+    };
+
+    let misplaced = (1..decls.len())
+        .find(|&i| cmp(&decls[i - 1], &decls[i]) == Ordering::Greater);
+    let misplaced = match misplaced {
+        Some(i) => &decls[i],
+        None => return None,
+    };
+    let before = decls.iter().find(|d| cmp(misplaced, d) == Ordering::Less);
+
+    let mut rep = Report::simple_warning(
+        format!("import `{}` is not sorted correctly", misplaced.name),
+        misplaced.span,
+    );
+    if let Some(before) = before {
+        rep = rep.with_span_note(
+            format!("it should be placed before `{}`", before.name),
+            before.span,
+        );
+    }
+    let mut finding = Finding::new(ORDER, rep);
+
+    // Rewrite all imports in the correct order, unless there are comments in
+    // between, which we wouldn't know where to put.
+    let block = Span::new(decls[0].span.lo, decls[decls.len() - 1].span.hi);
+    let has_comments = tokens.iter()
+        .any(|ts| ts.tok == Token::Comment && ts.span.lo >= block.lo && ts.span.hi <= block.hi);
+    if !has_comments {
+        let mut sorted: Vec<_> = decls.iter().collect();
+        sorted.sort_by(|a, b| cmp(a, b));
+
+        let mut text = String::new();
+        for (i, decl) in sorted.iter().enumerate() {
+            if i > 0 {
+                text.push('\n');
+                // Groups are separated by blank lines
+                if group(order, sorted[i - 1]) != group(order, decl) {
+                    text.push('\n');
+                }
+            }
+            text.push_str(&src[decl.span.into_range()]);
+        }
+        finding = finding.with_edit(block, text);
+    }
+
+    Some(finding)
+}
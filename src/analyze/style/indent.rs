@@ -0,0 +1,213 @@
+//! Checks that every line is indented according to its nesting depth.
+//!
+//! Every block increases the indentation by one level. Inside of `switch`
+//! blocks, the statements after a label get one additional level. Lines that
+//! continue a statement from a previous line only need to be indented at
+//! least as deep as the statement itself; we don't enforce a specific
+//! continuation indent.
+
+use analyze::Finding;
+use base::code::{FileMap, Span};
+use base::diag::Report;
+use config::StyleConfig;
+use syntax::lex::{Keyword, Token, TokenSpan};
+use super::{advance_column, last_line};
+
+pub const RULE: &'static str = "indentation";
+
+/// The expected indentation of a line
+#[derive(Clone, Copy, Debug)]
+pub struct LineIndent {
+    /// Expected indentation level (or the minimal level for continuation
+    /// lines)
+    pub level: usize,
+    /// Whether the line continues a statement from a previous line
+    pub continuation: bool,
+}
+
+struct Block {
+    /// Indentation level of the line which opened this block
+    outer: usize,
+    /// Whether this is the block of a `switch` statement
+    switch: bool,
+    /// Number of open parentheses outside of this block
+    parens: usize,
+}
+
+/// Keeps track of the expected indentation while walking through the tokens
+/// of a file.
+///
+/// This is used by the `indentation` rule as well as by the formatter, so
+/// that both always agree on the correct indentation.
+pub struct Indenter {
+    blocks: Vec<Block>,
+    parens: usize,
+    switch_pending: bool,
+    /// Level of the current line
+    line_level: usize,
+    /// Whether the current line starts with a switch label
+    label_line: bool,
+    /// The last real token
+    prev: Option<Token>,
+}
+
+impl Indenter {
+    pub fn new() -> Indenter {
+        Indenter {
+            blocks: Vec::new(),
+            parens: 0,
+            switch_pending: false,
+            line_level: 0,
+            label_line: false,
+            prev: None,
+        }
+    }
+
+    /// Has to be called with the first token of every line (before calling
+    /// `token()` with it) and returns the expected indentation of the line.
+    pub fn line_start(&mut self, tok: &Token) -> LineIndent {
+        let in_switch = self.blocks.last().map(|b| b.switch).unwrap_or(false);
+        let is_label = in_switch && match *tok {
+            Token::KeyW(Keyword::Case) | Token::KeyW(Keyword::Default) => true,
+            _ => false,
+        };
+        let continuation = *tok != Token::BraceCl && (self.parens > 0 || match self.prev {
+            None => false,
+            Some(Token::Semi) | Some(Token::BraceOp) | Some(Token::BraceCl) => false,
+            // The colon of a switch label ends the label
+            Some(Token::Colon) => !self.label_line,
+            Some(_) => true,
+        });
+
+        let level = match self.blocks.last() {
+            None => 0,
+            Some(b) if *tok == Token::BraceCl => b.outer,
+            Some(b) if b.switch && !is_label => b.outer + 2,
+            Some(b) => b.outer + 1,
+        };
+
+        self.label_line = is_label;
+        self.line_level = level;
+        LineIndent {
+            level: level,
+            continuation: continuation,
+        }
+    }
+
+    /// Overrides the level of the current line. Continuation lines may be
+    /// indented deeper than their expected level; blocks opened in such a
+    /// line are indented relative to the actual level.
+    pub fn set_line_level(&mut self, level: usize) {
+        self.line_level = level;
+    }
+
+    /// Whether the current line starts with a switch label
+    pub fn is_label_line(&self) -> bool {
+        self.label_line
+    }
+
+    /// Whether we are inside of parentheses (in the current block)
+    pub fn in_parens(&self) -> bool {
+        self.parens > 0
+    }
+
+    /// Has to be called for every token except whitespace.
+    pub fn token(&mut self, tok: &Token) {
+        match *tok {
+            Token::ParenOp => self.parens += 1,
+            Token::ParenCl => self.parens = if self.parens > 0 { self.parens - 1 } else { 0 },
+            Token::KeyW(Keyword::Switch) => self.switch_pending = true,
+            Token::BraceOp => {
+                self.blocks.push(Block {
+                    outer: self.line_level,
+                    switch: self.switch_pending,
+                    parens: self.parens,
+                });
+                self.switch_pending = false;
+                self.parens = 0;
+            },
+            Token::BraceCl => {
+                if let Some(b) = self.blocks.pop() {
+                    self.parens = b.parens;
+                }
+            },
+            _ => {},
+        }
+
+        if tok.is_real() {
+            self.prev = Some(tok.clone());
+        }
+    }
+}
+
+pub fn check(config: &StyleConfig, file_map: &FileMap, tokens: &[TokenSpan]) -> Vec<Finding> {
+    let src = file_map.src();
+    let width = config.indent_width;
+    let mut findings = Vec::new();
+    let mut indenter = Indenter::new();
+    let mut last = None;
+
+    for ts in tokens.iter().filter(|ts| ts.tok != Token::Whitespace) {
+        let line = file_map.get_line_idx(ts.span.lo);
+
+        // Check the indentation of the line, if this is its first token
+        if last != Some(line) {
+            // We can unwrap, because `line` was returned by the file map
+            let line_start = file_map.get_line_start(line).unwrap();
+            let indent = &src[line_start.0 as usize..ts.span.lo.0 as usize];
+            let indent_span = Span::new(line_start, ts.span.lo);
+            let expected = indenter.line_start(&ts.tok);
+
+            // Lines may start after a multi line comment
+            let is_indent = indent.chars().all(|c| c == ' ' || c == '\t');
+            if is_indent && expected.continuation {
+                let actual = indent_width(indent, width);
+                if actual < expected.level * width {
+                    findings.push(Finding::new(RULE, Report::simple_warning(
+                        format!(
+                            "continuation line is indented with {} columns, but \
+                                should be indented at least {} columns",
+                            actual,
+                            expected.level * width,
+                        ),
+                        ts.span,
+                    )));
+                }
+                indenter.set_line_level(::std::cmp::max(actual / width, expected.level));
+            } else if is_indent {
+                let expected_indent: String = ::std::iter::repeat(' ')
+                    .take(expected.level * width)
+                    .collect();
+                if indent != expected_indent {
+                    let msg = if indent.contains('\t') {
+                        format!(
+                            "line is indented with tabs, but should be indented \
+                                with {} spaces",
+                            expected.level * width,
+                        )
+                    } else {
+                        format!(
+                            "line is indented with {} spaces, but should be \
+                                indented with {} spaces",
+                            indent.len(),
+                            expected.level * width,
+                        )
+                    };
+                    let rep = Report::simple_warning(msg, ts.span);
+                    findings.push(Finding::new(RULE, rep).with_edit(indent_span, expected_indent));
+                }
+            }
+        }
+        last = Some(last_line(file_map, ts));
+
+        indenter.token(&ts.tok);
+    }
+
+    findings
+}
+
+/// Returns the width of the given indentation in columns, like `lines`
+/// measures it.
+fn indent_width(indent: &str, width: usize) -> usize {
+    indent.chars().fold(0, |column, c| advance_column(column, c, width))
+}
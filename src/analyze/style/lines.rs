@@ -0,0 +1,88 @@
+//! Rules about whole lines, which work on the raw source code: the maximum
+//! line length, trailing whitespace and the newline at the end of the file.
+//!
+//! Line lengths are measured in columns as displayed by a terminal: wide
+//! characters (e.g. CJK) count as two columns, combining characters as zero
+//! columns and tabs advance to the next multiple of the indentation width.
+
+use analyze::Finding;
+use base::code::{BytePos, FileMap, Span};
+use base::diag::Report;
+use config::StyleConfig;
+use super::advance_column;
+
+pub const LINE_LENGTH: &'static str = "line-length";
+pub const TRAILING_WHITESPACE: &'static str = "trailing-whitespace";
+pub const FINAL_NEWLINE: &'static str = "final-newline";
+
+pub fn check(config: &StyleConfig, file_map: &FileMap) -> Vec<Finding> {
+    let src = file_map.src();
+    let mut findings = Vec::new();
+
+    let mut start = 0;
+    for line in src.split('\n') {
+        // Windows line endings
+        let content = line.trim_right_matches('\r');
+
+        if config.max_line_length > 0 {
+            if let Some(finding) = check_length(config, content, start) {
+                findings.push(finding);
+            }
+        }
+
+        let trimmed = content.trim_right();
+        if config.trailing_whitespace && trimmed.len() < content.len() {
+            let span = Span::new(
+                BytePos((start + trimmed.len()) as u32),
+                BytePos((start + content.len()) as u32),
+            );
+            let rep = Report::simple_warning("trailing whitespace", span);
+            findings.push(Finding::new(TRAILING_WHITESPACE, rep).with_edit(span, ""));
+        }
+
+        start += line.len() + 1;
+    }
+
+    if config.final_newline && !src.is_empty() && !src.ends_with('\n') {
+        // Point at the last character of the file
+        let last = src.char_indices().rev().next().map(|(i, _)| i).unwrap_or(0);
+        let rep = Report::simple_warning(
+            "file should end with a newline",
+            Span::new(BytePos(last as u32), BytePos(src.len() as u32)),
+        );
+        let end = Span::empty_at(BytePos(src.len() as u32));
+        findings.push(Finding::new(FINAL_NEWLINE, rep).with_edit(end, "\n"));
+    }
+
+    findings
+}
+
+/// Checks the length of one line (without the line break), which starts at
+/// the byte offset `start`.
+fn check_length(config: &StyleConfig, line: &str, start: usize) -> Option<Finding> {
+    let max = config.max_line_length;
+    let mut columns = 0;
+    // Byte offset of the first character exceeding the limit
+    let mut exceeding = None;
+    for (i, c) in line.char_indices() {
+        columns = advance_column(columns, c, config.indent_width);
+        if columns > max && exceeding.is_none() {
+            exceeding = Some(i);
+        }
+    }
+
+    exceeding.map(|i| {
+        let span = Span::new(
+            BytePos((start + i) as u32),
+            BytePos((start + line.len()) as u32),
+        );
+        Finding::new(LINE_LENGTH, Report::simple_warning(
+            format!(
+                "line is {} columns long, but should be at most {} columns long",
+                columns,
+                max,
+            ),
+            span,
+        ))
+    })
+}
@@ -0,0 +1,60 @@
+//! The `style` analysis pass.
+//!
+//! Most style rules work on the token stream instead of the AST, because the
+//! AST doesn't contain any information about whitespace and comments. Rules
+//! about whole lines work on the source code.
+
+pub mod braces;
+pub mod imports;
+pub mod indent;
+pub mod lines;
+
+use analyze::{self, Finding};
+use base::code::{BytePos, FileMap, LineIdx};
+use config::StyleConfig;
+use syntax::ast;
+use syntax::lex::TokenSpan;
+use unicode_width::UnicodeWidthChar;
+
+/// Checks the compilation unit for style issues.
+pub fn check(
+    config: &StyleConfig,
+    file_map: &FileMap,
+    ast: &ast::CompilationUnit,
+) -> Vec<Finding> {
+    let tokens = analyze::tokenize(file_map);
+
+    let mut findings = Vec::new();
+    findings.extend(braces::check(file_map, &tokens));
+    findings.extend(imports::check(config, file_map, ast, &tokens));
+    findings.extend(indent::check(config, file_map, &tokens));
+    findings.extend(lines::check(config, file_map));
+    findings
+}
+
+/// Returns the whitespace at the beginning of the given line.
+fn leading_whitespace(file_map: &FileMap, line: LineIdx) -> &str {
+    let line = file_map.get_line(line).unwrap_or("");
+    let len = line.len() - line.trim_left_matches(|c| c == ' ' || c == '\t').len();
+    &line[..len]
+}
+
+/// Returns the column after the character `c`, which starts at column
+/// `column`. Tabs advance to the next tab stop, which is a multiple of
+/// `tab_width`.
+fn advance_column(column: usize, c: char, tab_width: usize) -> usize {
+    if c == '\t' {
+        column + tab_width - column % tab_width
+    } else {
+        column + c.width().unwrap_or(0)
+    }
+}
+
+/// Returns the index of the line the last byte of the token lives in.
+fn last_line(file_map: &FileMap, ts: &TokenSpan) -> LineIdx {
+    if ts.span.is_empty() {
+        file_map.get_line_idx(ts.span.lo)
+    } else {
+        file_map.get_line_idx(ts.span.hi - BytePos(1))
+    }
+}
@@ -0,0 +1,61 @@
+//! The `unused` analysis pass.
+//!
+//! Reports local variables, parameters, private fields and private methods
+//! which are never read (or called). Parameters are only checked for private
+//! and static methods: other methods might override a method or implement an
+//! interface and thus can't choose their parameters freely.
+
+use analyze::Finding;
+use base::diag::Report;
+use check::symbols::{SymbolId, SymbolKind, SymbolTable};
+use syntax::ast::{self, Visibility};
+
+pub const VARIABLE: &'static str = "unused-variable";
+pub const PARAMETER: &'static str = "unused-parameter";
+pub const FIELD: &'static str = "unused-field";
+pub const METHOD: &'static str = "unused-method";
+
+pub fn check(ast: &ast::CompilationUnit) -> Vec<Finding> {
+    let table = SymbolTable::build(ast);
+    let mut findings = Vec::new();
+
+    // Members are declared before the method bodies are resolved, so sort the
+    // symbols to report them in source order
+    let mut symbols: Vec<_> = table.symbols.iter().enumerate().collect();
+    symbols.sort_by_key(|&(_, symbol)| symbol.name.span.lo);
+
+    for (i, symbol) in symbols {
+        let id = SymbolId(i);
+        if table.is_read(id) {
+            continue;
+        }
+        let name = &symbol.name.name;
+        let private = symbol.vis == Some(Visibility::Private);
+
+        let (rule, msg) = match symbol.kind {
+            SymbolKind::Local if table.is_written(id) => {
+                (VARIABLE, format!("variable `{}` is assigned, but never read", name))
+            },
+            SymbolKind::Local => (VARIABLE, format!("unused variable `{}`", name)),
+            SymbolKind::Parameter => {
+                let checked = symbol.method
+                    .map(|m| (m.vis == Visibility::Private || m.static_) && m.name.name != "main")
+                    .unwrap_or(false);
+                if !checked {
+                    continue;
+                }
+                (PARAMETER, format!("unused parameter `{}`", name))
+            },
+            SymbolKind::Field if private => {
+                (FIELD, format!("private field `{}` is never read", name))
+            },
+            SymbolKind::Method if private => {
+                (METHOD, format!("private method `{}` is never called", name))
+            },
+            SymbolKind::Field | SymbolKind::Method => continue,
+        };
+
+        findings.push(Finding::new(rule, Report::simple_warning(msg, symbol.name.span)));
+    }
+    findings
+}
@@ -1,8 +1,19 @@
 pub const USAGE: &'static str = "
 Usage: jswag build [options] [<file>...]
        jswag run [options] [<file>...]
+       jswag jar [options] [<file>...]
+       jswag fix [options] [<file>...]
+       jswag fmt [options] [<file>...]
+       jswag baseline [options] [<file>...]
+       jswag grep [options] [<pattern>] [<file>...]
+       jswag dump-tokens [options] [<file>...]
+       jswag dump-ast [options] [<file>...]
+       jswag lsp [options]
+       jswag toolchains
+       jswag doctor [options]
+       jswag similarity [options] <dir>...
+       jswag raw [options] [<file>...]
        jswag [options] <file>...
-       jswag raw [<file>...]
        jswag (--help | --version)
 
 Commands:
@@ -13,19 +24,101 @@ Commands:
                 adds these parameters to the already added parameters of
                 `build`:
                     $ --run
+                If only one file is given, it's run with the source-file
+                mode of `java` (Java 11+) instead, so that no class files
+                are written next to it. With `--class-files` or
+                `--main-class`, it's compiled as before.
+    jar         Compiles all files into a temporary directory and packages
+                them into an executable JAR, whose main class is the first
+                class with a `main` method (like `run`) or the one given by
+                `--main-class`. The JAR is written to the file given by
+                `--jar-file` (default: named after the main class) and
+                includes the files in `--resources`.
     <none>      For compatibility this works similar to the original `javac`
                 command. Right now it's exactly the same as 'build', except
                 that the file list musn't be empty.
+    fix         Checks all files and fixes the findings of the analysis passes
+                in-place, as far as that is possible. Automatically adds these
+                parameters:
+                    $ --check --analyze style --fix
+    fmt         Checks all files and formats them in-place, using the same
+                style settings as the `style` analysis pass. With
+                `--check-format`, no files are changed; instead, a diff is
+                printed for every file that isn't formatted correctly and
+                jswag fails.
+    baseline    Checks all files and records the findings of the analysis
+                passes (`style` and those given with `--analyze`) in the
+                file given by `--baseline` (or 'jswag-baseline.txt'). Use it
+                with `--baseline` to only show new findings.
+    grep        Searches the files for a code pattern like
+                'catch ($T $e) {}', ignoring whitespace and comments. `$name`
+                matches an expression or type (the same code if used more
+                than once), `$_` matches anything and `...` any balanced
+                code. Alternatively, nodes are searched by `--kind` and
+                `--name` instead of a pattern.
+    dump-tokens Prints the tokens of the files as the internal parser sees
+                them (without whitespace), for debugging the parser.
+    dump-ast    Prints the syntax tree of the files as parsed by `--check`,
+                as an indented tree or as JSON with `--json`.
     raw         Does nothing automatically. Every task has to be explicitly
                 stated with command line parameters.
+    lsp         Starts a language server (LSP) communicating over stdin and
+                stdout. It reports all findings of `--check` and the analysis
+                passes (`style` and those given with `--analyze`) to the
+                editor whenever a document is opened or changed.
+    toolchains  Lists the installed JDKs found in JAVA_HOME, the usual
+                installation directories and SDKMAN, with their versions.
+    doctor      Checks the environment: whether `javac` and `java` are
+                found (respecting `--jdk`) and have the same version,
+                JAVA_HOME, color support of the terminal, the locale and
+                the configuration and rule files.
+    similarity  Compares submissions with each other to find plagiarism. Every
+                directory is one submission. Prints a ranking of all pairs and
+                writes an HTML report showing the matching regions side by
+                side into the directory given by `--output`.
 
 Actions:
     -a <check>, --analyze <check>   Run the given check. Implies `-c`.
+                                    Valid checks: 'style', 'javadoc',
+                                    'metrics', 'bugs', 'unused',
+                                    'duplicates', 'idioms'.
     -c, --check                     Check files for language errors with
                                     internal tools.
     -p, --pass-through              Call `javac` to compile the files.
     -r, --run                       Tries to execute the compiled classes in
                                     the order they were given. Requires `-p`.
+    --class-files                   Makes `run` compile the files into
+                                    class files next to them and run the
+                                    class, even for a single file.
+    --verify-parser                 Compares the internal parser with `javac`
+                                    and reports files whose syntax only one
+                                    of them rejects, with a minimal snippet.
+                                    Runs before everything else.
+    --fix                           Fixes the findings of all analysis passes
+                                    in-place, if possible. Requires `-a`.
+    --dry-run                       Doesn't change any files when fixing, but
+                                    prints a unified diff of all changes.
+                                    Implies `--fix`.
+    --check-format                  Doesn't change any files in `fmt`, but
+                                    prints a unified diff for every file
+                                    that isn't formatted and fails.
+    --metrics-report <report>       Writes the metrics of all methods and
+                                    classes as 'table' or 'csv' to stdout.
+                                    Append '=<file>' to write into a file
+                                    instead. Implies `--analyze metrics`.
+    --baseline <file>               Hides the findings recorded in the given
+                                    baseline file, so only new ones are
+                                    shown and fail the build.
+    --changed-since <rev>           Only checks files which were changed in
+                                    git since the given revision (including
+                                    uncommitted and untracked files) and
+                                    only shows findings in changed lines.
+    --report <report>               Writes a static HTML report of the whole
+                                    run into a directory, given as
+                                    'html=<dir>': all files with their
+                                    findings, the metrics and the output of
+                                    `javac` and `java`. The output of these
+                                    is then shown after they exited.
 
 Options:
     --lossy-decoding        Replace invalid UTF-8 or UTF-16 characters in the
@@ -33,6 +126,30 @@ Options:
                             instead of exiting.
     --encoding <encoding>   Forces a specific file decoding. Valid
                             values: 'utf8' [default: utf8]
+    --jdk <jdk>             The JDK whose `javac` and `java` are used, given by
+                            its directory or its version (e.g. '17'). See
+                            `jswag toolchains`. Default: the ones in PATH.
+    --java-version <version>
+                            The Java version of the code: '8', '11', '17' or
+                            '21'. It's passed to `javac` as `--release`
+                            (`-source` and `-target` for JDK 8) and the
+                            `idioms` pass only suggests features of this
+                            version. Overrides `java_version` of the
+                            configuration file.
+    --jar-file <file>       The JAR written by `jar`.
+    --main-class <class>    The class whose `main` method `run` and `jar` use,
+                            e.g. 'foo.Main'. Default: the first class with a
+                            `main` method.
+    --resources <dir>       A directory whose files `jar` includes in the JAR,
+                            with their paths relative to it.
+    --kind <kind>           The kind of nodes `grep` searches: 'call', 'new',
+                            'method', 'field', 'class' or 'import'.
+    --name <name>           The name of nodes `grep` searches, in which '*'
+                            matches anything.
+    --json                  Print the output of `dump-tokens` and
+                            `dump-ast` as JSON.
+    --output <dir>          Where `similarity` writes its report.
+                            [default: similarity-report]
     -h, --help              Show this message.
     -v, --verbose           More verbose messages.
     -V, --version           Show the version of jswag.
@@ -42,13 +159,44 @@ Options:
 pub struct Args {
     pub cmd_build: bool,
     pub cmd_run: bool,
+    pub cmd_jar: bool,
+    pub cmd_fix: bool,
+    pub cmd_fmt: bool,
+    pub cmd_baseline: bool,
+    pub cmd_grep: bool,
+    pub cmd_dump_tokens: bool,
+    pub cmd_dump_ast: bool,
     pub cmd_raw: bool,
+    pub cmd_lsp: bool,
+    pub cmd_similarity: bool,
+    pub cmd_toolchains: bool,
+    pub cmd_doctor: bool,
     pub arg_file: Vec<String>,
-    pub arg_analyze: Vec<String>,
+    pub arg_pattern: Option<String>,
+    pub arg_dir: Vec<String>,
+    pub flag_analyze: Vec<String>,
     pub flag_encoding: Encoding,
     pub flag_check: bool,
     pub flag_pass_through: bool,
     pub flag_run: bool,
+    pub flag_class_files: bool,
+    pub flag_verify_parser: bool,
+    pub flag_fix: bool,
+    pub flag_dry_run: bool,
+    pub flag_check_format: bool,
+    pub flag_metrics_report: Option<String>,
+    pub flag_report: Option<String>,
+    pub flag_baseline: Option<String>,
+    pub flag_changed_since: Option<String>,
+    pub flag_kind: Option<String>,
+    pub flag_name: Option<String>,
+    pub flag_java_version: Option<String>,
+    pub flag_jdk: Option<String>,
+    pub flag_jar_file: Option<String>,
+    pub flag_main_class: Option<String>,
+    pub flag_resources: Option<String>,
+    pub flag_json: bool,
+    pub flag_output: String,
     pub flag_verbose: bool,
     pub flag_version: bool,
     pub flag_lossy_decoding: bool,
@@ -0,0 +1,246 @@
+//! Baselines of known findings.
+//!
+//! `jswag baseline` records all current findings of the analysis passes in a
+//! file; with `--baseline <file>`, findings recorded there aren't shown
+//! anymore, so that only new ones fail the build.
+//!
+//! A finding is identified by its rule, its file and a fingerprint of the
+//! code around it: the lines of its span and the lines before and after,
+//! without the indentation. Line numbers are not part of it, so adding code
+//! elsewhere in the file doesn't invalidate the baseline. Every entry hides
+//! only one finding, so copying a line with a finding creates a new one.
+
+use analyze::{self, Finding};
+use base::code::FileMap;
+use check;
+use job::{AnalyzePass, Job};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Component, Path};
+
+/// The file `jswag baseline` writes if no `--baseline` is given
+pub const BASELINE_FILE_NAME: &'static str = "jswag-baseline.txt";
+
+const HEADER: &'static str = "# jswag baseline: <rule> <file> <fingerprint>";
+
+/// Identifies a finding: rule, file and fingerprint
+type Key = (String, String, u64);
+
+/// Known findings read from a baseline file
+pub struct Baseline {
+    /// How many findings with each key are known
+    known: HashMap<Key, usize>,
+    /// Number of findings hidden so far
+    pub hidden: usize,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> io::Result<Baseline> {
+        let mut known = HashMap::new();
+        for line in BufReader::new(try!(File::open(path))).lines() {
+            let line = try!(line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let key = match parse_line(&line) {
+                Some(key) => key,
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid line '{}'", line),
+                    ));
+                },
+            };
+            *known.entry(key).or_insert(0) += 1;
+        }
+
+        Ok(Baseline {
+            known: known,
+            hidden: 0,
+        })
+    }
+
+    /// Removes all known findings from `findings`.
+    pub fn retain_new(&mut self, file_map: &FileMap, findings: &mut Vec<Finding>) {
+        let mut hidden = 0;
+        findings.retain(|finding| {
+            match self.known.get_mut(&key(file_map, finding)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    hidden += 1;
+                    false
+                },
+                _ => true,
+            }
+        });
+        self.hidden += hidden;
+    }
+}
+
+/// Collects the findings of the given passes in all files of the job and
+/// writes them into the baseline file `path`.
+pub fn record(job: &Job, passes: &VecDeque<AnalyzePass>, path: &Path) -> Result<(), ()> {
+    // Errors while reading were already reported by the `Check` job
+    let file_maps = job.files.iter()
+        .filter_map(|file| {
+            check::read_file(job, file).ok()
+                .map(|src| FileMap::new(file.to_string_lossy().into_owned(), src))
+        })
+        .collect();
+
+    let mut keys = Vec::new();
+    for analysis in analyze::analyze_files(passes, &job.config, file_maps) {
+        for finding in &analysis.findings {
+            keys.push(key(&analysis.file_map, finding));
+        }
+    }
+    keys.sort();
+
+    msg!(Writing, "{} finding(s) to '{}'", keys.len(), path.display());
+    let res = File::create(path).and_then(|mut f| {
+        try!(writeln!(f, "{}", HEADER));
+        for (rule, file, fingerprint) in keys {
+            try!(writeln!(f, "{} {} {:016x}", rule, file, fingerprint));
+        }
+        Ok(())
+    });
+    res.map_err(|e| msg!(Error, "Could not write baseline '{}': {}", path.display(), e))
+}
+
+/// Parses a line like `empty-catch src/Foo.java 1f2e3d4c5b6a7988`. The file
+/// name may contain spaces.
+fn parse_line(line: &str) -> Option<Key> {
+    let rule_end = match line.find(' ') {
+        Some(pos) => pos,
+        None => return None,
+    };
+    let fingerprint_start = match line.rfind(' ') {
+        Some(pos) if pos > rule_end => pos,
+        _ => return None,
+    };
+    let fingerprint = match u64::from_str_radix(&line[fingerprint_start + 1..], 16) {
+        Ok(fp) => fp,
+        Err(_) => return None,
+    };
+    Some((
+        line[..rule_end].to_string(),
+        line[rule_end + 1..fingerprint_start].to_string(),
+        fingerprint,
+    ))
+}
+
+fn key(file_map: &FileMap, finding: &Finding) -> Key {
+    (finding.rule.to_string(), normalize_path(&file_map.filename()), fingerprint(file_map, finding))
+}
+
+/// The path with `/` as separator and without `.` components, so that
+/// `./Foo.java` and `Foo.java` are the same file.
+fn normalize_path(path: &str) -> String {
+    let parts: Vec<_> = Path::new(path).components()
+        .filter(|c| *c != Component::CurDir)
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    parts.join("/")
+}
+
+/// Hashes the trimmed lines of the finding's span and its neighbouring
+/// lines. Findings without a span are identified by their message.
+fn fingerprint(file_map: &FileMap, finding: &Finding) -> u64 {
+    let src = file_map.src();
+    let span = match finding.report.span {
+        Some(span) => span,
+        None => {
+            let msg = finding.report.remarks.first().map(|r| &r.desc[..]).unwrap_or("");
+            return fnv(msg.as_bytes(), FNV_OFFSET);
+        },
+    };
+
+    let lines: Vec<_> = src.lines().collect();
+    let line_of = |pos: usize| src[..pos].matches('\n').count();
+    let first = line_of(::std::cmp::min(span.lo.0 as usize, src.len()));
+    let last = line_of(::std::cmp::min(span.hi.0 as usize, src.len()));
+
+    let mut hash = FNV_OFFSET;
+    let context = first.saturating_sub(1)..::std::cmp::min(last + 2, lines.len());
+    for line in &lines[context] {
+        hash = fnv(line.trim().as_bytes(), hash);
+        hash = fnv(b"\n", hash);
+    }
+    hash
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// FNV-1a, which (unlike the hasher of the standard library) is guaranteed
+/// to stay the same, so baselines remain valid.
+fn fnv(bytes: &[u8], mut hash: u64) -> u64 {
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod test {
+    use analyze::Finding;
+    use base::code::{BytePos, FileMap, Span};
+    use base::diag::Report;
+    use super::*;
+
+    /// A finding of `rule` for the first occurrence of `s` in the file
+    fn finding_at(file_map: &FileMap, rule: &'static str, s: &str) -> Finding {
+        let lo = file_map.src().find(s).unwrap();
+        let span = Span::new(BytePos(lo as u32), BytePos((lo + s.len()) as u32));
+        Finding::new(rule, Report::simple_warning("test", span))
+    }
+
+    #[test]
+    fn parse_line_with_spaces_in_path() {
+        assert_eq!(
+            parse_line("empty-catch my dir/Foo Bar.java 1f2e3d4c5b6a7988"),
+            Some((
+                "empty-catch".to_string(),
+                "my dir/Foo Bar.java".to_string(),
+                0x1f2e3d4c5b6a7988,
+            )),
+        );
+        assert_eq!(parse_line("empty-catch Foo.java"), None);
+        assert_eq!(parse_line("empty-catch Foo.java xyz"), None);
+        assert_eq!(parse_line("empty-catch"), None);
+    }
+
+    #[test]
+    fn written_lines_parse_to_the_same_key() {
+        let file_map = FileMap::new("./my dir/Foo Bar.java", "class A {\n    int x;\n}\n");
+        let finding = finding_at(&file_map, "rule", "int x;");
+        let (rule, file, fingerprint) = key(&file_map, &finding);
+        assert_eq!(file, "my dir/Foo Bar.java");
+
+        let line = format!("{} {} {:016x}", rule, file, fingerprint);
+        assert_eq!(parse_line(&line), Some((rule, file, fingerprint)));
+    }
+
+    #[test]
+    fn fingerprint_ignores_position_and_indentation() {
+        let a = FileMap::new("A.java", "class A {\n    int x;\n}\n");
+        let b = FileMap::new("A.java", "// new\n\nclass A {\n\tint x;\n}\n");
+        assert_eq!(
+            fingerprint(&a, &finding_at(&a, "rule", "int x;")),
+            fingerprint(&b, &finding_at(&b, "rule", "int x;")),
+        );
+    }
+
+    #[test]
+    fn fingerprint_depends_on_neighbouring_lines() {
+        let a = FileMap::new("A.java", "class A {\n    int x;\n}\n");
+        let b = FileMap::new("A.java", "class A {\n    int x;\n    int y;\n}\n");
+        assert!(
+            fingerprint(&a, &finding_at(&a, "rule", "int x;"))
+                != fingerprint(&b, &finding_at(&b, "rule", "int x;"))
+        );
+    }
+}
@@ -0,0 +1,550 @@
+//! Control flow analysis of method bodies.
+//!
+//! Builds a control flow graph for every method body and uses it to find
+//! unreachable statements and non-void methods which can complete without
+//! returning a value. Both are errors in Java, but we can explain them better
+//! than javac. Loops with a constant `true` condition which are never left
+//! are reported as warnings.
+//!
+//! Reachability follows the rules of the JLS: only loop conditions are
+//! treated as constant expressions. If a condition might be a constant which
+//! we can't evaluate (e.g. a constant of another class), the method is
+//! skipped.
+
+use base::code::Span;
+use base::diag::Report;
+use check::symbols::{SymbolKind, SymbolTable};
+use syntax::ast::{self, BinOpType, ExprType, StatementType, UnaryOpType};
+use syntax::lex::Lit;
+
+/// Index of a node in the control flow graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeId(usize);
+
+/// A statement, a loop condition or the end of the method body
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub span: Span,
+    pub succs: Vec<NodeId>,
+}
+
+/// The control flow graph of one method body
+#[derive(Clone, Debug)]
+pub struct Cfg {
+    pub nodes: Vec<Node>,
+    pub entry: NodeId,
+}
+
+impl Cfg {
+    /// Returns for every node whether it can be reached from the entry.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut stack = vec![self.entry];
+        while let Some(id) = stack.pop() {
+            if reachable[id.0] {
+                continue;
+            }
+            reachable[id.0] = true;
+            stack.extend(self.nodes[id.0].succs.iter().cloned());
+        }
+        reachable
+    }
+}
+
+/// A statement which can be left by `break` (or `continue`)
+struct Target<'a> {
+    label: Option<&'a str>,
+    /// Where `continue` jumps to; `None` if this isn't a loop
+    continue_to: Option<NodeId>,
+    /// Whether an unlabeled `break` can target this (loops and `switch`)
+    breakable: bool,
+    breaks: Vec<NodeId>,
+    /// Whether the statement is left by `return`, `throw` or a jump to an
+    /// outer statement
+    left: bool,
+}
+
+/// Whether an expression is a constant expression and its value, if we know
+/// it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Constant {
+    Bool(bool),
+    /// A constant which isn't boolean or which we don't evaluate
+    Other,
+    NotConstant,
+    /// Might be a constant, but we can't tell
+    Unknown,
+}
+
+struct Builder<'a, 't> {
+    symbols: &'t SymbolTable<'a>,
+    cfg: Cfg,
+    targets: Vec<Target<'a>>,
+    /// Pairs of consecutive nodes: if the first one is reachable, but the
+    /// second one isn't, the second one is reported.
+    follows: Vec<(NodeId, NodeId)>,
+    /// Loops which never terminate
+    endless: Vec<NodeId>,
+    /// Whether a loop condition might be a constant we can't evaluate
+    uncertain: bool,
+}
+
+/// Checks the control flow of all methods in the compilation unit.
+pub fn check(ast: &ast::CompilationUnit) -> Vec<Report> {
+    let symbols = SymbolTable::build(ast);
+    let mut reports = Vec::new();
+    for ty in &ast.types {
+        check_type(&symbols, ty, &mut reports);
+    }
+    reports
+}
+
+fn check_type(symbols: &SymbolTable, ty: &ast::TypeDef, reports: &mut Vec<Report>) {
+    match *ty {
+        ast::TypeDef::NormalClass(ref c) => {
+            for member in &c.members {
+                if let ast::ClassMember::Method(ref m) = *member {
+                    reports.extend(check_method(symbols, m));
+                }
+            }
+        },
+        ast::TypeDef::NormalInterface(ref i) => {
+            for m in &i.methods {
+                reports.extend(check_method(symbols, m));
+            }
+            for ty in &i.types {
+                check_type(symbols, ty, reports);
+            }
+        },
+    }
+}
+
+fn check_method<'a>(symbols: &SymbolTable<'a>, m: &'a ast::Method) -> Vec<Report> {
+    let block = match m.block {
+        Some(ref block) => block,
+        None => return Vec::new(),
+    };
+
+    let mut builder = Builder {
+        symbols: symbols,
+        cfg: Cfg {
+            nodes: Vec::new(),
+            entry: NodeId(0),
+        },
+        targets: Vec::new(),
+        follows: Vec::new(),
+        endless: Vec::new(),
+        uncertain: false,
+    };
+    let entry = builder.node(m.name.span, &[]);
+    let out = builder.block(&block.stmts, vec![entry]);
+    let end = builder.node(m.name.span, &out);
+    if builder.uncertain {
+        return Vec::new();
+    }
+
+    let reachable = builder.cfg.reachable();
+    let mut reports = Vec::new();
+    for &(prev, id) in &builder.follows {
+        if reachable[prev.0] && !reachable[id.0] {
+            reports.push(Report::simple_error(
+                "unreachable statement",
+                builder.cfg.nodes[id.0].span,
+            ).with_note("this statement (and the following ones) can never be executed"));
+        }
+    }
+    for &id in &builder.endless {
+        if reachable[id.0] {
+            reports.push(Report::simple_warning(
+                "this loop never terminates",
+                builder.cfg.nodes[id.0].span,
+            ).with_note("the condition is always true and the loop contains no `break`, \
+                `return` or `throw`"));
+        }
+    }
+
+    let is_void = m.ret_ty.dims == 0 && m.ret_ty.name.segments.len() == 1
+        && m.ret_ty.name.segments[0].name == "void";
+    if reachable[end.0] && !is_void {
+        reports.push(Report::simple_error(
+            format!("missing return statement in method `{}`", m.name.name),
+            m.name.span,
+        ).with_note("the end of the method body can be reached without returning a value"));
+    }
+
+    reports.sort_by_key(|r| r.span.map(|s| s.lo));
+    reports
+}
+
+impl<'a, 't> Builder<'a, 't> {
+    fn node(&mut self, span: Span, preds: &[NodeId]) -> NodeId {
+        let id = NodeId(self.cfg.nodes.len());
+        self.cfg.nodes.push(Node {
+            span: span,
+            succs: Vec::new(),
+        });
+        for &pred in preds {
+            self.edge(pred, id);
+        }
+        id
+    }
+
+    fn edge(&mut self, from: NodeId, to: NodeId) {
+        self.cfg.nodes[from.0].succs.push(to);
+    }
+
+    /// The id the next node will get
+    fn next_id(&self) -> NodeId {
+        NodeId(self.cfg.nodes.len())
+    }
+
+    /// Adds the statements to the graph. `preds` are the nodes which continue
+    /// with the first statement. Returns the nodes which continue with the
+    /// statement after the block.
+    fn block(&mut self, stmts: &'a [ast::BlockStatement], preds: Vec<NodeId>) -> Vec<NodeId> {
+        let mut out = preds;
+        let mut prev = None;
+        for stmt in stmts {
+            let id = self.next_id();
+            out = self.block_stmt(stmt, out);
+            if let Some(prev) = prev {
+                self.follows.push((prev, id));
+            }
+            prev = Some(id);
+        }
+        out
+    }
+
+    fn block_stmt(&mut self, stmt: &'a ast::BlockStatement, preds: Vec<NodeId>) -> Vec<NodeId> {
+        match *stmt {
+            ast::BlockStatement::LocalVariableDecl { .. } => {
+                let span = span_of(stmt).unwrap_or_else(Span::dummy);
+                vec![self.node(span, &preds)]
+            },
+            ast::BlockStatement::Statement(ref stmt) => self.stmt(stmt, preds),
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'a ast::Statement, preds: Vec<NodeId>) -> Vec<NodeId> {
+        let n = self.node(stmt.span, &preds);
+        let label = stmt.label.as_ref().map(|l| &l.name[..]);
+        let is_loop = match stmt.stmt {
+            StatementType::While { .. }
+                | StatementType::DoWhile { .. }
+                | StatementType::For { .. } => true,
+            _ => false,
+        };
+
+        // Other labeled statements can be left with `break label;`
+        if label.is_some() && !is_loop {
+            self.targets.push(Target {
+                label: label,
+                continue_to: None,
+                breakable: false,
+                breaks: Vec::new(),
+                left: false,
+            });
+            let mut out = self.stmt_type(stmt, n, None);
+            let target = self.targets.pop().unwrap();
+            out.extend(target.breaks);
+            out
+        } else {
+            self.stmt_type(stmt, n, label)
+        }
+    }
+
+    /// Adds the parts of the statement whose node is `n` to the graph.
+    /// `label` is the label of a loop.
+    fn stmt_type(&mut self, stmt: &'a ast::Statement, n: NodeId, label: Option<&'a str>)
+        -> Vec<NodeId>
+    {
+        match stmt.stmt {
+            StatementType::Empty | StatementType::Expr(_) => vec![n],
+            StatementType::Block(ref block) => self.block(&block.stmts, vec![n]),
+            StatementType::IfThenElse { ref then_branch, ref else_branch, .. } => {
+                let mut out = self.stmt(then_branch, vec![n]);
+                match *else_branch {
+                    Some(ref else_branch) => out.extend(self.stmt(else_branch, vec![n])),
+                    None => out.push(n),
+                }
+                out
+            },
+            StatementType::While { ref cond, ref body } => {
+                let cond = self.condition(cond);
+                self.push_loop(label, n);
+                let body_out = self.loop_body(n, body, cond);
+                for id in body_out {
+                    self.edge(id, n);
+                }
+                self.finish_loop(n, n, cond)
+            },
+            StatementType::DoWhile { ref cond, ref body } => {
+                let cond_value = self.condition(cond);
+                let cond_node = self.node(cond.span, &[]);
+                self.push_loop(label, cond_node);
+                let body_out = self.stmt(body, vec![n]);
+                for id in body_out {
+                    self.edge(id, cond_node);
+                }
+                if cond_value != Some(false) {
+                    self.edge(cond_node, n);
+                }
+                self.finish_loop(n, cond_node, cond_value)
+            },
+            StatementType::For { ref init, ref cond, ref update, ref body } => {
+                let mut out = vec![n];
+                match *init {
+                    ast::ForInit::VarDecl(ref decl) => out = self.block_stmt(decl, out),
+                    ast::ForInit::Stmts(ref stmts) => for s in stmts {
+                        out = self.stmt(s, out);
+                    },
+                }
+
+                // A missing condition is the same as `true`
+                let (cond_value, cond_span) = match *cond {
+                    Some(ref cond) => (self.condition(cond), cond.span),
+                    None => (Some(true), stmt.span),
+                };
+                let head = self.node(cond_span, &out);
+                let cont = self.node(stmt.span, &[]);
+                self.push_loop(label, cont);
+                let body_out = self.loop_body(head, body, cond_value);
+                for id in body_out {
+                    self.edge(id, cont);
+                }
+
+                let mut out = vec![cont];
+                for s in update {
+                    out = self.stmt(s, out);
+                }
+                for id in out {
+                    self.edge(id, head);
+                }
+                self.finish_loop(n, head, cond_value)
+            },
+            StatementType::Switch { ref arms, .. } => {
+                self.targets.push(Target {
+                    label: label,
+                    continue_to: None,
+                    breakable: true,
+                    breaks: Vec::new(),
+                    left: false,
+                });
+
+                // Every arm holds one statement; only the first statement of
+                // a case has labels. A case is reached from the switch and by
+                // falling through from the previous case.
+                let mut out = Vec::new();
+                let mut prev = None;
+                let mut has_default = false;
+                for arm in arms {
+                    let id = self.next_id();
+                    if arm.labels.is_empty() {
+                        if let Some(prev) = prev {
+                            self.follows.push((prev, id));
+                        }
+                    } else {
+                        has_default |= arm.labels.iter().any(is_default);
+                        out.push(n);
+                    }
+                    out = self.block_stmt(&arm.block, out);
+                    prev = Some(id);
+                }
+
+                // Without `default`, no case might match
+                if !has_default {
+                    out.push(n);
+                }
+                let target = self.targets.pop().unwrap();
+                out.extend(target.breaks);
+                out
+            },
+            StatementType::Break(ref name) => {
+                let name = name.as_ref().map(|l| &l.name[..]);
+                let idx = self.targets.iter().rposition(|t| match name {
+                    Some(name) => t.label == Some(name),
+                    None => t.breakable,
+                });
+                match idx {
+                    Some(idx) => {
+                        self.targets[idx].breaks.push(n);
+                        self.leave(idx + 1);
+                    },
+                    // Invalid code, javac will complain
+                    None => self.uncertain = true,
+                }
+                Vec::new()
+            },
+            StatementType::Continue(ref name) => {
+                let name = name.as_ref().map(|l| &l.name[..]);
+                let idx = self.targets.iter().rposition(|t| {
+                    t.continue_to.is_some() && (name.is_none() || t.label == name)
+                });
+                match idx {
+                    Some(idx) => {
+                        let to = self.targets[idx].continue_to.unwrap();
+                        self.edge(n, to);
+                        self.leave(idx + 1);
+                    },
+                    None => self.uncertain = true,
+                }
+                Vec::new()
+            },
+            StatementType::Return(_) | StatementType::Throw(_) => {
+                self.leave(0);
+                Vec::new()
+            },
+        }
+    }
+
+    fn push_loop(&mut self, label: Option<&'a str>, continue_to: NodeId) {
+        self.targets.push(Target {
+            label: label,
+            continue_to: Some(continue_to),
+            breakable: true,
+            breaks: Vec::new(),
+            left: false,
+        });
+    }
+
+    /// Adds the body of a loop whose condition is checked in `head`.
+    fn loop_body(&mut self, head: NodeId, body: &'a ast::Statement, cond: Option<bool>)
+        -> Vec<NodeId>
+    {
+        let id = self.next_id();
+        let preds = if cond == Some(false) { vec![] } else { vec![head] };
+        let out = self.stmt(body, preds);
+        self.follows.push((head, id));
+        out
+    }
+
+    /// Pops the loop target and returns the nodes which continue after the
+    /// loop `n`.
+    fn finish_loop(&mut self, n: NodeId, cond: NodeId, value: Option<bool>) -> Vec<NodeId> {
+        let target = self.targets.pop().unwrap();
+        if value == Some(true) && target.breaks.is_empty() && !target.left {
+            self.endless.push(n);
+        }
+
+        let mut out = target.breaks;
+        if value != Some(true) {
+            out.push(cond);
+        }
+        out
+    }
+
+    /// Marks all targets starting at `idx` as left by a jump.
+    fn leave(&mut self, idx: usize) {
+        for target in &mut self.targets[idx..] {
+            target.left = true;
+        }
+    }
+
+    /// Returns the value of a loop condition, if it's a constant.
+    fn condition(&mut self, cond: &ast::Expr) -> Option<bool> {
+        match self.constant(cond) {
+            Constant::Bool(b) => Some(b),
+            Constant::Unknown => {
+                self.uncertain = true;
+                None
+            },
+            Constant::Other | Constant::NotConstant => None,
+        }
+    }
+
+    fn constant(&self, e: &ast::Expr) -> Constant {
+        match e.expr {
+            ExprType::Literal(Lit::Bool(b)) => Constant::Bool(b),
+            ExprType::Literal(_) => Constant::Other,
+            ExprType::Cast { ref expr, .. } => self.constant(expr),
+            ExprType::UnaryOp { op, ref expr } => match (op, self.constant(expr)) {
+                (UnaryOpType::PreIncr, _)
+                    | (UnaryOpType::PreDecr, _)
+                    | (UnaryOpType::PostIncr, _)
+                    | (UnaryOpType::PostDecr, _) => Constant::NotConstant,
+                (UnaryOpType::Not, Constant::Bool(b)) => Constant::Bool(!b),
+                (_, c) => c,
+            },
+            ExprType::BinOp { op, ref lhs, ref rhs } => {
+                match (self.constant(lhs), self.constant(rhs)) {
+                    (Constant::NotConstant, _) | (_, Constant::NotConstant) => {
+                        Constant::NotConstant
+                    },
+                    (Constant::Unknown, _) | (_, Constant::Unknown) => Constant::Unknown,
+                    (Constant::Bool(a), Constant::Bool(b)) => match op {
+                        BinOpType::LogicalAnd | BinOpType::BitwiseAnd => Constant::Bool(a && b),
+                        BinOpType::LogicalOr | BinOpType::BitwiseOr => Constant::Bool(a || b),
+                        BinOpType::BitwiseXor | BinOpType::Ne => Constant::Bool(a != b),
+                        BinOpType::Equals => Constant::Bool(a == b),
+                        _ => Constant::NotConstant,
+                    },
+                    // We don't evaluate numbers and strings, so comparisons
+                    // have an unknown value
+                    _ => match op {
+                        BinOpType::Equals
+                            | BinOpType::Ne
+                            | BinOpType::Gt
+                            | BinOpType::Lt
+                            | BinOpType::Ge
+                            | BinOpType::Le => Constant::Unknown,
+                        _ => Constant::Other,
+                    },
+                }
+            },
+            ExprType::Conditional { ref cond, ref if_branch, ref else_branch } => {
+                let branches = (self.constant(if_branch), self.constant(else_branch));
+                match (self.constant(cond), branches) {
+                    (Constant::NotConstant, _)
+                        | (_, (Constant::NotConstant, _))
+                        | (_, (_, Constant::NotConstant)) => Constant::NotConstant,
+                    (Constant::Bool(true), (c, _)) | (Constant::Bool(false), (_, c)) => c,
+                    _ => Constant::Unknown,
+                }
+            },
+            ExprType::Name(ref path) => {
+                // Constant variables are final and have a constant
+                // initializer, which we don't check. Fields of other classes
+                // might be constants as well.
+                match self.symbols.resolve(&path.segments[0]) {
+                    Some(symbol) if path.segments.len() > 1 => match symbol.kind {
+                        SymbolKind::Parameter | SymbolKind::Local => Constant::NotConstant,
+                        _ => Constant::Unknown,
+                    },
+                    Some(symbol) => match symbol.kind {
+                        SymbolKind::Parameter => Constant::NotConstant,
+                        _ if symbol.final_ => Constant::Unknown,
+                        _ => Constant::NotConstant,
+                    },
+                    None => Constant::Unknown,
+                }
+            },
+            _ => Constant::NotConstant,
+        }
+    }
+}
+
+fn is_default(label: &ast::SwitchLabel) -> bool {
+    match *label {
+        ast::SwitchLabel::Default => true,
+        _ => false,
+    }
+}
+
+/// Returns the span of the statement. Local variable declarations don't store
+/// their span, so it's computed from their parts.
+pub fn span_of(stmt: &ast::BlockStatement) -> Option<Span> {
+    match *stmt {
+        ast::BlockStatement::Statement(ref stmt) => Some(stmt.span),
+        ast::BlockStatement::LocalVariableDecl { ref ty, ref vars, .. } => {
+            let first = match ty.name.span() {
+                Some(span) => span,
+                None => return None,
+            };
+            let last = vars.last().map(|var| match var.init {
+                Some(ref init) => init.span,
+                None => var.name.span,
+            }).unwrap_or(first);
+            Some(Span::new(first.lo, last.hi))
+        },
+    }
+}
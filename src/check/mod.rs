@@ -1,3 +1,7 @@
+pub mod flow;
+pub mod symbols;
+pub mod verify;
+
 use std::io::{self, Read};
 use job::Job;
 use std::fs::File;
@@ -27,7 +31,7 @@ impl From<std::string::FromUtf8Error> for Error {
 }
 
 pub fn check_all(job: &Job) -> Result<Vec<()>, ()> {
-    for file in &job.files {
+    for file in job.checked_files() {
         msg!(Checking, "'{}'", file.display());
 
         let res = check_file(job, file);
@@ -56,6 +60,81 @@ pub fn check_all(job: &Job) -> Result<Vec<()>, ()> {
 }
 
 fn check_file(job: &Job, file_name: &Path) -> Result<(), Error> {
+    let src = try!(read_file(job, file_name));
+
+    // create filemap and parse
+    let lossy_filename = file_name.to_string_lossy().into_owned();
+    let file_map = code::FileMap::new(lossy_filename, src);
+    let (ast, errors) = syntax::parse_compilation_unit(&file_map);
+
+    // The parser doesn't support every construct yet (e.g. lambdas or
+    // generics). If `javac` compiles the file successfully, its errors are
+    // the parser's fault, so the file is only skipped. If `javac` fails, too,
+    // the errors are shown. `javac` already said why it couldn't be run.
+    let first_error = errors.iter().find(|e| e.kind == diag::ReportKind::Error);
+    let rejected = ast.is_none() || first_error.is_some();
+    if rejected && try!(verify::javac_accepts(job, file_name).map_err(|_| Error::Unknown)) {
+        let line = first_error.and_then(|e| e.span)
+            .map(|span| file_map.src()[..span.lo.0 as usize].matches('\n').count() + 1)
+            .map(|line| format!(" (line {})", line))
+            .unwrap_or_default();
+        msg!(
+            Note,
+            "'{}' contains code the internal parser doesn't support{}, but `javac` accepts it",
+            file_name.display(),
+            line,
+        );
+        msg!(None, "The file is skipped by the internal checks");
+        return Ok(());
+    }
+
+    // With `--changed-since`, only reports in changed lines are shown. That
+    // the file couldn't be parsed at all is shown in any case.
+    let mut errors = errors;
+    if let (Some(_), Some(changes)) = (ast.as_ref(), job.changes.as_ref()) {
+        changes.retain_changed_reports(&file_map, &mut errors);
+    }
+
+    let mut critical = false;
+    for e in &errors {
+        diag::print(&e, &file_map, diag::PrintOptions::default());
+
+        if e.kind == diag::ReportKind::Error {
+            critical = true;
+        }
+    }
+
+    let ast = match ast {
+        Some(ast) => ast,
+        None => return Err(Error::Unknown),
+    };
+    if critical {
+        return Err(Error::Unknown);
+    }
+
+    // Unreachable code and missing returns
+    let mut flow_reports = flow::check(&ast);
+    if let Some(ref changes) = job.changes {
+        changes.retain_changed_reports(&file_map, &mut flow_reports);
+    }
+    for e in &flow_reports {
+        diag::print(&e, &file_map, diag::PrintOptions::default());
+
+        if e.kind == diag::ReportKind::Error {
+            critical = true;
+        }
+    }
+
+    if critical {
+        Err(Error::Unknown)
+    } else {
+        Ok(())
+    }
+}
+
+/// Reads the given file and decodes it according to the encoding settings
+/// of the job.
+pub fn read_file(job: &Job, file_name: &Path) -> Result<String, Error> {
     // read file contents into buffer
     let mut file = try!(File::open(file_name));
     let mut buffer = Vec::new();
@@ -90,23 +169,5 @@ fn check_file(job: &Job, file_name: &Path) -> Result<(), Error> {
         // },
     };
 
-    // create filemap and parse
-    let lossy_filename = file_name.to_string_lossy().into_owned();
-    let file_map = code::FileMap::new(lossy_filename, src);
-    let (ast, errors) = syntax::parse_compilation_unit(&file_map);
-
-    let mut critical = false;
-    for e in &errors {
-        diag::print(&e, &file_map, diag::PrintOptions::default());
-
-        if e.kind == diag::ReportKind::Error {
-            critical = true;
-        }
-    }
-
-    if ast.is_none() || critical {
-        Err(Error::Unknown)
-    } else {
-        Ok(())
-    }
+    Ok(src)
 }
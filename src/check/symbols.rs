@@ -0,0 +1,597 @@
+//! Name resolution for one compilation unit.
+//!
+//! Collects all fields, methods, parameters and local variables into a
+//! symbol table and resolves the simple names used in method bodies to them.
+//! Names which can't be resolved (inherited members, other classes, ...) are
+//! ignored.
+
+use base::code::Span;
+use syntax::ast::{self, BinOpType, ExprType, StatementType, UnaryOpType, Visibility};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    Field,
+    Method,
+    Parameter,
+    Local,
+}
+
+/// Index of a symbol in the symbol table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SymbolId(pub usize);
+
+#[derive(Clone, Debug)]
+pub struct Symbol<'a> {
+    pub name: &'a ast::Ident,
+    pub kind: SymbolKind,
+    /// Type of a variable or return type of a method
+    pub ty: &'a ast::Type,
+    /// Visibility of fields and methods, `None` for parameters and locals
+    pub vis: Option<Visibility>,
+    pub final_: bool,
+    /// The method a parameter or local variable belongs to
+    pub method: Option<&'a ast::Method>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    /// E.g. `x += 1` or `x++`, if the value of the expression is used
+    ReadWrite,
+}
+
+/// A resolved usage of a symbol
+#[derive(Clone, Copy, Debug)]
+pub struct Reference {
+    pub symbol: SymbolId,
+    pub span: Span,
+    pub access: Access,
+}
+
+#[derive(Clone, Debug)]
+pub struct SymbolTable<'a> {
+    pub symbols: Vec<Symbol<'a>>,
+    pub references: Vec<Reference>,
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Builds the symbol table for the given compilation unit.
+    pub fn build(ast: &'a ast::CompilationUnit) -> SymbolTable<'a> {
+        let mut resolver = Resolver {
+            table: SymbolTable {
+                symbols: Vec::new(),
+                references: Vec::new(),
+            },
+            scopes: Vec::new(),
+            methods: Vec::new(),
+            method: None,
+        };
+        for ty in &ast.types {
+            resolver.type_def(ty);
+        }
+        resolver.table
+    }
+
+    /// Returns the symbol the given identifier refers to, if it was resolved.
+    pub fn resolve(&self, name: &ast::Ident) -> Option<&Symbol<'a>> {
+        self.references.iter()
+            .find(|r| r.span == name.span)
+            .map(|r| &self.symbols[r.symbol.0])
+    }
+
+    /// Whether the value of the symbol is used anywhere (for methods: whether
+    /// the method is called).
+    pub fn is_read(&self, id: SymbolId) -> bool {
+        self.references.iter()
+            .any(|r| r.symbol == id && r.access != Access::Write)
+    }
+
+    /// Whether a value is assigned to the symbol anywhere (not counting
+    /// initializers).
+    pub fn is_written(&self, id: SymbolId) -> bool {
+        self.references.iter()
+            .any(|r| r.symbol == id && r.access != Access::Read)
+    }
+}
+
+struct Resolver<'a> {
+    table: SymbolTable<'a>,
+    /// Variables visible at the current position, innermost last
+    scopes: Vec<Vec<SymbolId>>,
+    /// Methods of the enclosing classes, innermost last
+    methods: Vec<Vec<SymbolId>>,
+    /// The method whose body is currently resolved
+    method: Option<&'a ast::Method>,
+}
+
+impl<'a> Resolver<'a> {
+    fn declare(&mut self, name: &'a ast::Ident, kind: SymbolKind, ty: &'a ast::Type,
+        vis: Option<Visibility>, final_: bool) -> SymbolId
+    {
+        let id = SymbolId(self.table.symbols.len());
+        self.table.symbols.push(Symbol {
+            name: name,
+            kind: kind,
+            ty: ty,
+            vis: vis,
+            final_: final_,
+            method: match kind {
+                SymbolKind::Parameter | SymbolKind::Local => self.method,
+                _ => None,
+            },
+        });
+        if kind != SymbolKind::Method {
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.push(id);
+            }
+        }
+        id
+    }
+
+    fn type_def(&mut self, ty: &'a ast::TypeDef) {
+        match *ty {
+            ast::TypeDef::NormalClass(ref c) => self.members(&c.members),
+            ast::TypeDef::NormalInterface(ref i) => {
+                self.scopes.push(Vec::new());
+                for f in &i.constants {
+                    self.declare(&f.name, SymbolKind::Field, &f.ty, Some(f.vis), f.final_);
+                }
+                let methods = i.methods.iter()
+                    .map(|m| {
+                        self.declare(&m.name, SymbolKind::Method, &m.ret_ty, Some(m.vis), m.final_)
+                    })
+                    .collect();
+                self.methods.push(methods);
+                for m in &i.methods {
+                    self.method(m);
+                }
+                for ty in &i.types {
+                    self.type_def(ty);
+                }
+                self.methods.pop();
+                self.scopes.pop();
+            },
+        }
+    }
+
+    /// Declares and resolves the members of a class (or anonymous class).
+    fn members(&mut self, members: &'a [ast::ClassMember]) {
+        self.scopes.push(Vec::new());
+        let mut methods = Vec::new();
+        for member in members {
+            match *member {
+                ast::ClassMember::Field(ref f) => {
+                    self.declare(&f.name, SymbolKind::Field, &f.ty, Some(f.vis), f.final_);
+                },
+                ast::ClassMember::Method(ref m) => {
+                    let id = self.declare(
+                        &m.name,
+                        SymbolKind::Method,
+                        &m.ret_ty,
+                        Some(m.vis),
+                        m.final_,
+                    );
+                    methods.push(id);
+                },
+            }
+        }
+        self.methods.push(methods);
+
+        // Members of anonymous classes may belong to a method of the outer
+        // class, so remember it.
+        let outer = self.method;
+        for member in members {
+            if let ast::ClassMember::Method(ref m) = *member {
+                self.method(m);
+            }
+        }
+        self.method = outer;
+
+        self.methods.pop();
+        self.scopes.pop();
+    }
+
+    fn method(&mut self, m: &'a ast::Method) {
+        self.method = Some(m);
+        self.scopes.push(Vec::new());
+        for p in &m.params {
+            self.declare(&p.name, SymbolKind::Parameter, &p.ty, None, p.final_);
+        }
+        if let Some(ref block) = m.block {
+            self.block(block);
+        }
+        self.scopes.pop();
+    }
+
+    fn block(&mut self, block: &'a ast::Block) {
+        self.scopes.push(Vec::new());
+        for stmt in &block.stmts {
+            self.block_stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn block_stmt(&mut self, stmt: &'a ast::BlockStatement) {
+        match *stmt {
+            ast::BlockStatement::LocalVariableDecl { final_, ref ty, ref vars } => {
+                for var in vars {
+                    if let Some(ref init) = var.init {
+                        self.expr(init, true);
+                    }
+                    self.declare(&var.name, SymbolKind::Local, ty, None, final_);
+                }
+            },
+            ast::BlockStatement::Statement(ref stmt) => self.stmt(stmt),
+        }
+    }
+
+    fn stmt(&mut self, stmt: &'a ast::Statement) {
+        match stmt.stmt {
+            StatementType::Empty
+                | StatementType::Break(_)
+                | StatementType::Continue(_)
+                | StatementType::Return(None) => {},
+            StatementType::Block(ref block) => self.block(block),
+            // The value of an expression statement is discarded
+            StatementType::Expr(ref e) => self.expr(e, false),
+            StatementType::Return(Some(ref e)) | StatementType::Throw(ref e) => {
+                self.expr(e, true);
+            },
+            StatementType::IfThenElse { ref cond, ref then_branch, ref else_branch } => {
+                self.expr(cond, true);
+                self.stmt(then_branch);
+                if let Some(ref else_branch) = *else_branch {
+                    self.stmt(else_branch);
+                }
+            },
+            StatementType::While { ref cond, ref body }
+                | StatementType::DoWhile { ref cond, ref body } => {
+                self.expr(cond, true);
+                self.stmt(body);
+            },
+            StatementType::For { ref init, ref cond, ref update, ref body } => {
+                self.scopes.push(Vec::new());
+                match *init {
+                    ast::ForInit::VarDecl(ref decl) => self.block_stmt(decl),
+                    ast::ForInit::Stmts(ref stmts) => for s in stmts {
+                        self.stmt(s);
+                    },
+                }
+                if let Some(ref cond) = *cond {
+                    self.expr(cond, true);
+                }
+                for s in update {
+                    self.stmt(s);
+                }
+                self.stmt(body);
+                self.scopes.pop();
+            },
+            StatementType::Switch { ref val, ref arms, .. } => {
+                self.expr(val, true);
+                self.scopes.push(Vec::new());
+                for arm in arms {
+                    self.block_stmt(&arm.block);
+                }
+                self.scopes.pop();
+            },
+        }
+    }
+
+    /// Resolves all names in the expression. `used` says whether the value of
+    /// the expression is used.
+    fn expr(&mut self, e: &'a ast::Expr, used: bool) {
+        let update = if used { Access::ReadWrite } else { Access::Write };
+        match e.expr {
+            ExprType::BinOp { op: BinOpType::Assign, ref lhs, ref rhs } => {
+                self.target(lhs, Access::Write);
+                self.expr(rhs, true);
+            },
+            ExprType::BinOp { op, ref lhs, ref rhs } => {
+                if is_compound_assignment(op) {
+                    self.target(lhs, update);
+                } else {
+                    self.expr(lhs, true);
+                }
+                self.expr(rhs, true);
+            },
+            ExprType::UnaryOp { op, ref expr } => match op {
+                UnaryOpType::PreIncr
+                    | UnaryOpType::PreDecr
+                    | UnaryOpType::PostIncr
+                    | UnaryOpType::PostDecr => self.target(expr, update),
+                _ => self.expr(expr, true),
+            },
+            ExprType::Conditional { ref cond, ref if_branch, ref else_branch } => {
+                self.expr(cond, true);
+                self.expr(if_branch, true);
+                self.expr(else_branch, true);
+            },
+            ExprType::Name(ref path) => self.name(path, Access::Read),
+            ExprType::FieldAccess { ref root, ref path } => match *root {
+                Some(ref root) => match root.expr {
+                    ExprType::This => self.field(path, Access::Read),
+                    _ => self.expr(root, true),
+                },
+                None => {},
+            },
+            ExprType::ArrayAccess { ref obj, ref idx } => {
+                self.expr(obj, true);
+                self.expr(idx, true);
+            },
+            ExprType::MethodInvocation { ref name, ref args } => {
+                match *name {
+                    ast::MethodInvocationType::SimpleName(ref name) => self.call(name),
+                    // E.g. `list.add(x)`: the first segment is a variable
+                    ast::MethodInvocationType::SimplePath(ref path) => {
+                        self.name(path, Access::Read);
+                    },
+                    ast::MethodInvocationType::Expr(ref obj, ref name) => match obj.expr {
+                        ExprType::This => self.call(name),
+                        _ => self.expr(obj, true),
+                    },
+                }
+                for arg in args {
+                    self.expr(arg, true);
+                }
+            },
+            ExprType::ArrayInit { ref items } => {
+                for item in items {
+                    self.expr(item, true);
+                }
+            },
+            ExprType::Cast { ref expr, .. } => self.expr(expr, true),
+            ExprType::InstanceCreation { ref args, ref body, .. } => {
+                for arg in args {
+                    self.expr(arg, true);
+                }
+                if let Some(ref members) = *body {
+                    self.members(members);
+                }
+            },
+            ExprType::ArrayCreation { ref expr_dims, ref init, .. } => {
+                for e in expr_dims {
+                    self.expr(e, true);
+                }
+                if let Some(ref init) = *init {
+                    self.expr(init, true);
+                }
+            },
+            ExprType::Literal(_) | ExprType::ClassLiteral(_) | ExprType::This => {},
+        }
+    }
+
+    /// Resolves the left hand side of an assignment.
+    fn target(&mut self, e: &'a ast::Expr, access: Access) {
+        match e.expr {
+            ExprType::Name(ref path) if path.segments.len() == 1 => self.name(path, access),
+            ExprType::FieldAccess { root: Some(ref root), ref path } => match root.expr {
+                ExprType::This => self.field(path, access),
+                _ => self.expr(root, true),
+            },
+            // E.g. `arr[i] = x` or `obj.field = x`: the variable is read
+            _ => self.expr(e, true),
+        }
+    }
+
+    /// Resolves the first segment of the path to a variable.
+    fn name(&mut self, path: &ast::Path, access: Access) {
+        let first = &path.segments[0];
+        let found = self.scopes.iter().rev()
+            .flat_map(|scope| scope.iter().rev())
+            .cloned()
+            .find(|id| self.table.symbols[id.0].name.name == first.name);
+        self.reference(found, first.span, access);
+    }
+
+    /// Resolves `this.<path>` to a field.
+    fn field(&mut self, path: &ast::Path, access: Access) {
+        let first = &path.segments[0];
+        let found = self.scopes.iter().rev()
+            .flat_map(|scope| scope.iter().rev())
+            .cloned()
+            .find(|id| {
+                let symbol = &self.table.symbols[id.0];
+                symbol.kind == SymbolKind::Field && symbol.name.name == first.name
+            });
+        self.reference(found, first.span, access);
+    }
+
+    /// Resolves a call of a method of an enclosing class. All overloads with
+    /// that name are treated as called.
+    fn call(&mut self, name: &ast::Ident) {
+        let found: Vec<_> = {
+            let table = &self.table;
+            self.methods.iter().rev()
+                .map(|methods| {
+                    methods.iter()
+                        .cloned()
+                        .filter(|id| table.symbols[id.0].name.name == name.name)
+                        .collect::<Vec<_>>()
+                })
+                .find(|found| !found.is_empty())
+                .unwrap_or_else(Vec::new)
+        };
+        for id in found {
+            self.reference(Some(id), name.span, Access::Read);
+        }
+    }
+
+    fn reference(&mut self, symbol: Option<SymbolId>, span: Span, access: Access) {
+        if let Some(symbol) = symbol {
+            self.table.references.push(Reference {
+                symbol: symbol,
+                span: span,
+                access: access,
+            });
+        }
+    }
+}
+
+fn is_compound_assignment(op: BinOpType) -> bool {
+    match op {
+        BinOpType::MulAssign
+            | BinOpType::DivAssign
+            | BinOpType::ModAssign
+            | BinOpType::AddAssign
+            | BinOpType::SubAssign
+            | BinOpType::ShlAssign
+            | BinOpType::ShrAssign
+            | BinOpType::ShrUnAssign
+            | BinOpType::AndAssign
+            | BinOpType::XorAssign
+            | BinOpType::OrAssign => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use base::code::FileMap;
+    use syntax;
+    use super::*;
+
+    const SHADOWING: &'static str = "
+class A {
+    int x;
+    int y;
+
+    void f(int y) {
+        y = 1;
+        x = 2;
+        int x = 3;
+        x++;
+        this.x = x;
+    }
+
+    void g() {
+        y = 4;
+    }
+}
+";
+
+    const SCOPES: &'static str = "
+class B {
+    void f() {
+        {
+            int a = 1;
+            a++;
+        }
+        a = 2;
+        for (int i = 0; i < 3; i++) {
+            f();
+        }
+        i = 3;
+    }
+}
+";
+
+    const ACCESS: &'static str = "
+class C {
+    int n;
+    int m;
+
+    void f() {
+        n += 1;
+        m = n++;
+    }
+
+    void g() {}
+
+    void g(int a) {}
+
+    void h() {
+        g(1);
+    }
+}
+";
+
+    /// Byte offset of `s`, which has to occur exactly once in `src`
+    fn at(src: &str, s: &str) -> usize {
+        assert_eq!(src.matches(s).count(), 1, "`{}` isn't unique", s);
+        src.find(s).unwrap()
+    }
+
+    fn with_table<F: FnOnce(&SymbolTable)>(src: &str, f: F) {
+        let file_map = FileMap::new("Test.java", src);
+        let ast = syntax::parse_compilation_unit(&file_map).0.expect("test code doesn't parse");
+        f(&SymbolTable::build(&ast));
+    }
+
+    /// The kind and the offset of the declaration of the symbols the name at
+    /// offset `pos` refers to
+    fn resolved(table: &SymbolTable, pos: usize) -> Vec<(SymbolKind, usize)> {
+        table.references.iter()
+            .filter(|r| r.span.lo.0 as usize == pos)
+            .map(|r| {
+                let symbol = &table.symbols[r.symbol.0];
+                (symbol.kind, symbol.name.span.lo.0 as usize)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shadowing() {
+        let src = SHADOWING;
+        let field_x = (SymbolKind::Field, at(src, "int x;") + 4);
+        let field_y = (SymbolKind::Field, at(src, "int y;") + 4);
+        let param_y = (SymbolKind::Parameter, at(src, "int y)") + 4);
+        let local_x = (SymbolKind::Local, at(src, "int x = 3") + 4);
+
+        with_table(src, |table| {
+            assert_eq!(resolved(table, at(src, "y = 1")), vec![param_y]);
+            // The local variable isn't declared yet
+            assert_eq!(resolved(table, at(src, "x = 2")), vec![field_x]);
+            assert_eq!(resolved(table, at(src, "x++")), vec![local_x]);
+            assert_eq!(resolved(table, at(src, "this.x") + 5), vec![field_x]);
+            assert_eq!(resolved(table, at(src, "= x;") + 2), vec![local_x]);
+            assert_eq!(resolved(table, at(src, "y = 4")), vec![field_y]);
+        });
+    }
+
+    #[test]
+    fn scopes_end_with_their_block() {
+        let src = SCOPES;
+        let local_a = (SymbolKind::Local, at(src, "int a") + 4);
+        let local_i = (SymbolKind::Local, at(src, "int i") + 4);
+
+        with_table(src, |table| {
+            assert_eq!(resolved(table, at(src, "a++")), vec![local_a]);
+            assert_eq!(resolved(table, at(src, "a = 2")), vec![]);
+            assert_eq!(resolved(table, at(src, "i < 3")), vec![local_i]);
+            assert_eq!(resolved(table, at(src, "i = 3")), vec![]);
+        });
+    }
+
+    #[test]
+    fn access() {
+        let src = ACCESS;
+        with_table(src, |table| {
+            let access = |s| {
+                let pos = at(src, s);
+                table.references.iter().find(|r| r.span.lo.0 as usize == pos).unwrap().access
+            };
+            assert_eq!(access("n += 1"), Access::Write);
+            assert_eq!(access("n++"), Access::ReadWrite);
+            assert_eq!(access("m = "), Access::Write);
+
+            let id = |name| {
+                SymbolId(table.symbols.iter().position(|s| s.name.name == name).unwrap())
+            };
+            assert!(table.is_read(id("n")) && table.is_written(id("n")));
+            assert!(!table.is_read(id("m")) && table.is_written(id("m")));
+        });
+    }
+
+    #[test]
+    fn calls_refer_to_all_overloads() {
+        let src = ACCESS;
+        with_table(src, |table| {
+            let called = resolved(table, at(src, "g(1)"));
+            assert_eq!(called, vec![
+                (SymbolKind::Method, at(src, "g() {}")),
+                (SymbolKind::Method, at(src, "g(int a)")),
+            ]);
+        });
+    }
+}
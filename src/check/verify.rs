@@ -0,0 +1,319 @@
+//! Cross-checks the internal parser against `javac` (`--verify-parser`).
+//!
+//! Every file is parsed and compiled by `javac` into a temporary directory.
+//! If only one of them rejects the syntax, the file is reported together
+//! with a minimal snippet showing the same divergence. The snippet is found
+//! by removing lines from the file as long as the divergence remains.
+//!
+//! `javac` also rejects code with correct syntax, e.g. if a type is unknown.
+//! Therefore only its syntax errors count, which are recognized by their
+//! messages.
+
+use base::code::FileMap;
+use base::diag::{self, Remark, Report, ReportKind, Snippet};
+use java;
+use job::Job;
+use std::cmp;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use super::read_file;
+use syntax;
+
+/// Messages of `javac`'s syntax errors, besides the ones ending with
+/// ` expected` (e.g. `';' expected`). Details may follow after a colon, e.g.
+/// `illegal character: '#'`.
+const SYNTAX_ERRORS: &'static [&'static str] = &[
+    "illegal start of expression",
+    "illegal start of statement",
+    "illegal start of type",
+    "not a statement",
+    "reached end of file while parsing",
+    "unclosed string literal",
+    "unclosed character literal",
+    "unclosed comment",
+    "illegal character",
+    "illegal escape character",
+    "illegal line end in character literal",
+    "empty character literal",
+    "malformed floating-point literal",
+    "integer number too large",
+    "orphaned case",
+    "orphaned default",
+    "'else' without 'if'",
+    "'catch' without 'try'",
+    "'finally' without 'try'",
+    "'try' without 'catch', 'finally' or resource declarations",
+    "invalid method declaration; return type required",
+    "repeated modifier",
+    "variable declaration not allowed here",
+];
+
+/// How many times `javac` is called at most to minimize one snippet
+const MAX_REDUCE_STEPS: usize = 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Divergence {
+    /// The parser rejects what `javac` accepts
+    ParserRejects,
+    /// `javac` rejects what the parser accepts
+    JavacRejects,
+}
+
+/// What `javac` says about a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Verdict {
+    /// The file was compiled successfully
+    Accepted,
+    /// Line and message of the first syntax error
+    SyntaxError(usize, String),
+    /// `javac` failed without a syntax error, e.g. because of an unknown
+    /// type. Whether the syntax is correct is unknown.
+    Failed,
+}
+
+/// Verifies the parser with all files of the job. Fails if it diverges from
+/// `javac` for at least one file.
+pub fn verify_all(job: &Job) -> Result<(), ()> {
+    java::with_temp_dir("verify", |tmp| verify_files(job, tmp))
+}
+
+/// Whether `javac` compiles the file successfully. Fails if `javac` can't
+/// be run.
+pub fn javac_accepts(job: &Job, file: &Path) -> Result<bool, ()> {
+    let source_path = java::source_root(job, file);
+    java::with_temp_dir("verify", |tmp| javac_verdict(job, file, &source_path, tmp))
+        .map(|verdict| verdict == Verdict::Accepted)
+}
+
+fn verify_files(job: &Job, tmp: &Path) -> Result<(), ()> {
+    let mut diverging = 0;
+    for file in &job.files {
+        msg!(Checking, "'{}' with the parser and `javac`", file.display());
+        let src = match read_file(job, file) {
+            Ok(src) => src,
+            Err(e) => {
+                msg!(Error, "Could not read '{}': {:?}", file.display(), e);
+                return Err(());
+            },
+        };
+
+        let file_map = FileMap::new(file.to_string_lossy().into_owned(), src);
+        let parser_error = parse_error(&file_map);
+        let source_path = java::source_root(job, file);
+        let verdict = try!(javac_verdict(job, file, &source_path, tmp));
+        let divergence = match (parser_error, verdict) {
+            (Some(report), Verdict::Accepted) => {
+                msg!(Warning, "The parser rejects '{}', but `javac` accepts it:", file.display());
+                diag::print(&report, &file_map, diag::PrintOptions::default());
+                Divergence::ParserRejects
+            },
+            (None, Verdict::SyntaxError(line, error)) => {
+                msg!(Warning, "`javac` rejects '{}', but the parser accepts it:", file.display());
+                msg!(None, "line {}: {}", line, error);
+                Divergence::JavacRejects
+            },
+            (Some(_), Verdict::Failed) => {
+                msg!(
+                    Note,
+                    "The parser rejects '{}', but `javac` fails without a syntax error, so \
+                        it's unknown whether the syntax is correct",
+                    file.display(),
+                );
+                continue;
+            },
+            _ => continue,
+        };
+        diverging += 1;
+
+        let name = file.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+        let path = tmp.join("snippet").join(name);
+        let snippet = try!(reduce(job, &file_map, divergence, &path, &source_path));
+        msg!(Note, "Minimal snippet with the same divergence:");
+        for line in snippet.lines() {
+            println!("    {}", line);
+        }
+    }
+
+    if diverging == 0 {
+        msg!(Note, "The parser and `javac` agree on all {} file(s)", job.files.len());
+        Ok(())
+    } else {
+        msg!(Error, "The parser and `javac` disagree on {} file(s)", diverging);
+        Err(())
+    }
+}
+
+/// Returns the first syntax error the parser reports, if any.
+fn parse_error(file_map: &FileMap) -> Option<Report> {
+    let (ast, mut errors) = syntax::parse_compilation_unit(file_map);
+    match errors.iter().position(|e| e.kind == ReportKind::Error) {
+        Some(idx) => Some(errors.swap_remove(idx)),
+        None if ast.is_none() => Some(Report {
+            kind: ReportKind::Error,
+            span: None,
+            remarks: vec![Remark::error("couldn't be parsed", Snippet::None)],
+        }),
+        None => None,
+    }
+}
+
+/// Compiles the file and returns what `javac` says about it. Other classes
+/// are searched in `source_path`.
+fn javac_verdict(job: &Job, file: &Path, source_path: &Path, tmp: &Path)
+    -> Result<Verdict, ()>
+{
+    let classes = tmp.join("classes");
+    let (success, output) = try!(java::compile_quietly(file, source_path, &classes, job));
+    if success {
+        return Ok(Verdict::Accepted);
+    }
+
+    // Errors look like `Foo.java:12: error: ';' expected`. Other files are
+    // compiled, too, so their errors are skipped.
+    let prefix = format!("{}:", file.display());
+    let error = output.lines()
+        .filter(|line| line.starts_with(&prefix))
+        .filter_map(|line| {
+            let mut parts = line[prefix.len()..].splitn(2, ": error: ");
+            match (parts.next().and_then(|l| l.parse().ok()), parts.next()) {
+                (Some(line), Some(msg)) => Some((line, msg.to_string())),
+                _ => None,
+            }
+        })
+        .find(|&(_, ref msg)| is_syntax_error(msg));
+    Ok(match error {
+        Some((line, msg)) => Verdict::SyntaxError(line, msg),
+        None => Verdict::Failed,
+    })
+}
+
+/// Whether the message of a `javac` error is about the syntax
+fn is_syntax_error(msg: &str) -> bool {
+    msg.ends_with(" expected") || SYNTAX_ERRORS.iter().any(|s| {
+        msg.starts_with(s) && (msg.len() == s.len() || msg[s.len()..].starts_with(':'))
+    })
+}
+
+/// Minimizes the code of a diverging file
+struct Reducer<'a> {
+    job: &'a Job,
+    /// The original file name, since the parser checks the names of public
+    /// classes
+    name: &'a str,
+    divergence: Divergence,
+    /// Where the candidates are written to, because `javac` needs a file
+    /// with the original name
+    path: &'a Path,
+    /// Where `javac` searches the other classes of the original file
+    source_path: &'a Path,
+    /// Number of `javac` calls so far
+    steps: usize,
+}
+
+/// Removes ranges of lines from the source while the divergence remains,
+/// starting with big ranges. Only ranges with balanced brackets are tried,
+/// since removing anything else breaks the syntax for both.
+fn reduce(
+    job: &Job,
+    file_map: &FileMap,
+    divergence: Divergence,
+    path: &Path,
+    source_path: &Path,
+) -> Result<String, ()> {
+    if let Some(dir) = path.parent() {
+        if let Err(e) = fs::create_dir_all(dir) {
+            msg!(Error, "Could not create '{}': {}", dir.display(), e);
+            return Err(());
+        }
+    }
+
+    let mut reducer = Reducer {
+        job: job,
+        name: file_map.filename(),
+        divergence: divergence,
+        path: path,
+        source_path: source_path,
+        steps: 0,
+    };
+    let mut lines: Vec<&str> = file_map.src().lines().collect();
+    let mut len = lines.len();
+    // Whether something was removed since starting with the biggest ranges
+    let mut changed = false;
+    while len > 0 && reducer.steps < MAX_REDUCE_STEPS {
+        let mut start = 0;
+        let mut removed = false;
+        while start + len <= lines.len() && reducer.steps < MAX_REDUCE_STEPS {
+            let end = start + len;
+            if balanced(&lines[start..end]) {
+                let candidate: Vec<_> = lines[..start].iter()
+                    .chain(&lines[end..])
+                    .cloned()
+                    .collect();
+                if try!(reducer.diverges(candidate.join("\n"))) {
+                    lines = candidate;
+                    removed = true;
+                    continue;
+                }
+            }
+            start += 1;
+        }
+
+        changed |= removed;
+        if !removed {
+            len /= 2;
+        }
+        // Removing small ranges can make bigger ones removable
+        if len == 0 && changed {
+            len = lines.len();
+            changed = false;
+        }
+        len = cmp::min(len, lines.len());
+    }
+
+    Ok(lines.join("\n"))
+}
+
+/// Whether all brackets opened in the lines are closed in them, too.
+fn balanced(lines: &[&str]) -> bool {
+    let mut depth = 0i32;
+    for c in lines.iter().flat_map(|line| line.chars()) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {},
+        }
+        if depth < 0 {
+            return false;
+        }
+    }
+    depth == 0
+}
+
+impl<'a> Reducer<'a> {
+    /// Whether the parser and `javac` diverge on the code in the same way.
+    /// `javac` is only called if the parser already behaves as expected.
+    fn diverges(&mut self, code: String) -> Result<bool, ()> {
+        let file_map = FileMap::new(self.name, code);
+        let parser_rejects = parse_error(&file_map).is_some();
+        if parser_rejects != (self.divergence == Divergence::ParserRejects) {
+            return Ok(false);
+        }
+
+        let written = File::create(self.path)
+            .and_then(|mut f| f.write_all(file_map.src().as_bytes()));
+        if let Err(e) = written {
+            msg!(Error, "Could not write '{}': {}", self.path.display(), e);
+            return Err(());
+        }
+        self.steps += 1;
+
+        let tmp = self.path.parent().unwrap_or(Path::new("."));
+        let verdict = try!(javac_verdict(self.job, self.path, self.source_path, tmp));
+        Ok(match (self.divergence, verdict) {
+            (Divergence::ParserRejects, Verdict::Accepted) => true,
+            (Divergence::JavacRejects, Verdict::SyntaxError(..)) => true,
+            _ => false,
+        })
+    }
+}
@@ -1,3 +1,372 @@
+use analyze::custom::RustRules;
+use analyze::custom::pattern::PatternRule;
+use rustc_serialize::Decodable;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use syntax::ast::Visibility;
+use toml;
+
 pub const EXIT_GENERIC_ERROR: i32 = 100;
 pub const EXIT_NO_INPUT: i32 = 101;
 pub const EXIT_INVALID_INPUT: i32 = 102;
+
+/// Name of the configuration file. It's searched in the current working
+/// directory and all of its parents.
+pub const CONFIG_FILE_NAME: &'static str = "jswag.toml";
+
+/// The Java versions which can be selected with `java_version`
+pub const JAVA_VERSIONS: &'static [u32] = &[8, 11, 17, 21];
+
+/// Settings from the configuration file. Every setting is optional in the
+/// file and falls back to its default value.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub style: StyleConfig,
+    pub javadoc: JavadocConfig,
+    pub metrics: MetricsConfig,
+    pub duplicates: DuplicatesConfig,
+    /// The Java version of the code, if known. It's passed to `javac` and
+    /// decides which idioms are suggested.
+    pub java_version: Option<u32>,
+    /// Custom rules from the rules directory
+    pub rules: Vec<PatternRule>,
+    /// Custom rules of a program using jswag as a library
+    pub rust_rules: RustRules,
+}
+
+/// Settings of the `[style]` section. These are shared by the style pass and
+/// the formatter, so that both always agree.
+#[derive(Clone, Debug)]
+pub struct StyleConfig {
+    /// Number of spaces per indentation level
+    pub indent_width: usize,
+    /// Number of additional indentation levels of continuation lines
+    /// produced by the formatter
+    pub continuation_indent: usize,
+    /// Maximum number of consecutive blank lines kept by the formatter
+    pub max_blank_lines: usize,
+    /// Maximum number of columns per line or 0 to allow lines of any length
+    pub max_line_length: usize,
+    /// Whether trailing whitespace is reported
+    pub trailing_whitespace: bool,
+    /// Whether a missing newline at the end of the file is reported
+    pub final_newline: bool,
+    /// Order of import groups, given by package prefixes. `*` stands for all
+    /// other packages, `static` for all static imports.
+    pub import_order: Vec<String>,
+}
+
+impl Default for StyleConfig {
+    fn default() -> Self {
+        StyleConfig {
+            indent_width: 4,
+            continuation_indent: 2,
+            max_blank_lines: 1,
+            max_line_length: 100,
+            trailing_whitespace: true,
+            final_newline: true,
+            import_order: vec!["static".into(), "java".into(), "javax".into(), "*".into()],
+        }
+    }
+}
+
+/// Settings of the `[javadoc]` section
+#[derive(Clone, Debug)]
+pub struct JavadocConfig {
+    /// Types and methods that are at least this visible need to be
+    /// documented
+    pub visibility: Visibility,
+}
+
+impl Default for JavadocConfig {
+    fn default() -> Self {
+        JavadocConfig {
+            visibility: Visibility::Public,
+        }
+    }
+}
+
+/// Settings of the `[metrics]` section: thresholds of the `metrics` pass. A
+/// threshold of 0 disables the check.
+#[derive(Clone, Debug)]
+pub struct MetricsConfig {
+    pub max_cyclomatic: usize,
+    pub max_cognitive: usize,
+    pub max_nesting: usize,
+    pub max_params: usize,
+    /// Maximum length of methods in lines
+    pub max_method_length: usize,
+    /// Maximum length of classes in lines
+    pub max_class_length: usize,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            max_cyclomatic: 10,
+            max_cognitive: 15,
+            max_nesting: 4,
+            max_params: 7,
+            max_method_length: 60,
+            max_class_length: 1000,
+        }
+    }
+}
+
+/// Settings of the `[duplicates]` section
+#[derive(Clone, Debug)]
+pub struct DuplicatesConfig {
+    /// Minimum number of tokens of a reported duplicate
+    pub min_tokens: usize,
+    /// Whether sequences which only differ in names are duplicates
+    pub ignore_identifiers: bool,
+    /// Whether sequences which only differ in literals are duplicates
+    pub ignore_literals: bool,
+}
+
+impl Default for DuplicatesConfig {
+    fn default() -> Self {
+        DuplicatesConfig {
+            min_tokens: 50,
+            ignore_identifiers: false,
+            ignore_literals: false,
+        }
+    }
+}
+
+/// The file contents as they are decoded from TOML
+#[derive(RustcDecodable)]
+struct RawConfig {
+    java_version: Option<u32>,
+    style: Option<RawStyleConfig>,
+    javadoc: Option<RawJavadocConfig>,
+    metrics: Option<RawMetricsConfig>,
+    duplicates: Option<RawDuplicatesConfig>,
+}
+
+#[derive(RustcDecodable)]
+struct RawStyleConfig {
+    indent_width: Option<usize>,
+    continuation_indent: Option<usize>,
+    max_blank_lines: Option<usize>,
+    max_line_length: Option<usize>,
+    trailing_whitespace: Option<bool>,
+    final_newline: Option<bool>,
+    import_order: Option<Vec<String>>,
+}
+
+#[derive(RustcDecodable)]
+struct RawJavadocConfig {
+    visibility: Option<String>,
+}
+
+#[derive(RustcDecodable)]
+struct RawMetricsConfig {
+    max_cyclomatic: Option<usize>,
+    max_cognitive: Option<usize>,
+    max_nesting: Option<usize>,
+    max_params: Option<usize>,
+    max_method_length: Option<usize>,
+    max_class_length: Option<usize>,
+}
+
+#[derive(RustcDecodable)]
+struct RawDuplicatesConfig {
+    min_tokens: Option<usize>,
+    ignore_identifiers: Option<bool>,
+    ignore_literals: Option<bool>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// Invalid TOML syntax
+    Syntax(String),
+    /// Valid TOML, but invalid or unknown settings
+    Invalid(String),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "IO error: {}", e),
+            Error::Syntax(ref s) => write!(f, "invalid TOML: {}", s),
+            Error::Invalid(ref s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// Searches the configuration file, starting in the current working
+/// directory.
+pub fn find() -> Option<PathBuf> {
+    find_upwards(CONFIG_FILE_NAME, |p| p.is_file())
+}
+
+/// Searches `name` in the current working directory and all of its parents
+/// and returns the first path which is accepted.
+pub fn find_upwards<F: Fn(&Path) -> bool>(name: &str, accept: F) -> Option<PathBuf> {
+    let mut dir = match env::current_dir() {
+        Ok(dir) => dir,
+        Err(_) => return None,
+    };
+    loop {
+        let candidate = dir.join(name);
+        if accept(&candidate) {
+            return Some(candidate);
+        }
+        if !dir.pop() {
+            return None;
+        }
+    }
+}
+
+/// Loads the configuration file, if there is any. Otherwise the default
+/// configuration is returned.
+pub fn load() -> Result<Config, Error> {
+    match find() {
+        Some(path) => load_file(&path),
+        None => Ok(Config::default()),
+    }
+}
+
+/// Loads the given configuration file.
+pub fn load_file(path: &Path) -> Result<Config, Error> {
+    let mut src = String::new();
+    try!(File::open(path).and_then(|mut f| f.read_to_string(&mut src)));
+    let raw: RawConfig = try!(decode(&src));
+
+    let mut config = Config::default();
+    if let Some(style) = raw.style {
+        let def = StyleConfig::default();
+        config.style = StyleConfig {
+            indent_width: style.indent_width.unwrap_or(def.indent_width),
+            continuation_indent: style.continuation_indent
+                .unwrap_or(def.continuation_indent),
+            max_blank_lines: style.max_blank_lines.unwrap_or(def.max_blank_lines),
+            max_line_length: style.max_line_length.unwrap_or(def.max_line_length),
+            trailing_whitespace: style.trailing_whitespace
+                .unwrap_or(def.trailing_whitespace),
+            final_newline: style.final_newline.unwrap_or(def.final_newline),
+            import_order: style.import_order.unwrap_or(def.import_order),
+        };
+    }
+    if let Some(javadoc) = raw.javadoc {
+        if let Some(vis) = javadoc.visibility {
+            config.javadoc.visibility = match &vis[..] {
+                "public" => Visibility::Public,
+                "protected" => Visibility::Protected,
+                "package" => Visibility::Package,
+                "private" => Visibility::Private,
+                _ => return Err(Error::Invalid(format!(
+                    "invalid `javadoc.visibility` '{}' (expected 'public', 'protected', \
+                        'package' or 'private')",
+                    vis,
+                ))),
+            };
+        }
+    }
+    if let Some(metrics) = raw.metrics {
+        let def = MetricsConfig::default();
+        config.metrics = MetricsConfig {
+            max_cyclomatic: metrics.max_cyclomatic.unwrap_or(def.max_cyclomatic),
+            max_cognitive: metrics.max_cognitive.unwrap_or(def.max_cognitive),
+            max_nesting: metrics.max_nesting.unwrap_or(def.max_nesting),
+            max_params: metrics.max_params.unwrap_or(def.max_params),
+            max_method_length: metrics.max_method_length.unwrap_or(def.max_method_length),
+            max_class_length: metrics.max_class_length.unwrap_or(def.max_class_length),
+        };
+    }
+    if let Some(duplicates) = raw.duplicates {
+        let def = DuplicatesConfig::default();
+        config.duplicates = DuplicatesConfig {
+            min_tokens: duplicates.min_tokens.unwrap_or(def.min_tokens),
+            ignore_identifiers: duplicates.ignore_identifiers
+                .unwrap_or(def.ignore_identifiers),
+            ignore_literals: duplicates.ignore_literals.unwrap_or(def.ignore_literals),
+        };
+    }
+    if let Some(version) = raw.java_version {
+        config.java_version = Some(try!(check_java_version(version)));
+    }
+    if config.style.indent_width == 0 {
+        return Err(Error::Invalid("`style.indent_width` must not be 0".into()));
+    }
+    if config.duplicates.min_tokens == 0 {
+        return Err(Error::Invalid("`duplicates.min_tokens` must not be 0".into()));
+    }
+
+    Ok(config)
+}
+
+/// Returns the version if it's one of `JAVA_VERSIONS`.
+pub fn check_java_version(version: u32) -> Result<u32, Error> {
+    if JAVA_VERSIONS.contains(&version) {
+        Ok(version)
+    } else {
+        let valid: Vec<_> = JAVA_VERSIONS.iter().map(|v| v.to_string()).collect();
+        Err(Error::Invalid(format!(
+            "invalid Java version {} (expected one of: {})",
+            version,
+            valid.join(", "),
+        )))
+    }
+}
+
+/// Parses TOML and decodes it. Unknown keys are an error, since typos in
+/// setting names shouldn't be silently ignored.
+pub fn decode<T: Decodable>(src: &str) -> Result<T, Error> {
+    let mut parser = toml::Parser::new(src);
+    let table = match parser.parse() {
+        Some(table) => table,
+        None => {
+            let msgs: Vec<_> = parser.errors.iter().map(|e| {
+                let (line, col) = parser.to_linecol(e.lo);
+                format!("{} (line {}, column {})", e.desc, line + 1, col + 1)
+            }).collect();
+            return Err(Error::Syntax(msgs.join("; ")));
+        }
+    };
+
+    let mut decoder = toml::Decoder::new(toml::Value::Table(table));
+    let raw = try!(T::decode(&mut decoder).map_err(|e| {
+        Error::Invalid(e.to_string())
+    }));
+
+    if let Some(leftover) = decoder.toml {
+        let mut unknown = Vec::new();
+        collect_keys("", &leftover, &mut unknown);
+        if !unknown.is_empty() {
+            return Err(Error::Invalid(
+                format!("unknown setting(s): {}", unknown.join(", "))
+            ));
+        }
+    }
+
+    Ok(raw)
+}
+
+/// Collects the paths of all non-table values in the given value.
+fn collect_keys(prefix: &str, value: &toml::Value, out: &mut Vec<String>) {
+    match *value {
+        toml::Value::Table(ref table) => {
+            for (key, value) in table {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                collect_keys(&path, value, out);
+            }
+        },
+        _ => out.push(prefix.to_string()),
+    }
+}
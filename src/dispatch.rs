@@ -1,9 +1,36 @@
 use job::{Job, JobType};
 use java;
 use check;
+use doctor;
+use dump;
+use analyze;
+use baseline;
+use fmt;
+use grep;
+use jar;
+use lsp;
+use report;
+use similarity;
+use toolchain;
 
 
 pub fn handle(job: Job) -> Result<(), ()> {
+    let mut outcomes = Vec::new();
+    let res = handle_sub_jobs(&job, &mut outcomes);
+
+    // The report is most useful if something went wrong, so it's written in
+    // any case
+    if let Some(ref dir) = job.report {
+        msg!(Writing, "report to '{}'", dir.display());
+        if let Err(e) = report::write(&job, dir, &outcomes) {
+            msg!(Error, "Could not write report: {}", e);
+            return Err(());
+        }
+    }
+    res
+}
+
+fn handle_sub_jobs(job: &Job, outcomes: &mut Vec<java::Outcome>) -> Result<(), ()> {
     for sj in &job.sub_jobs {
         match *sj {
             JobType::Check => {
@@ -14,11 +41,67 @@ pub fn handle(job: Job) -> Result<(), ()> {
                         job.files.len()
                     );
                 }
-                let res = check::check_all(&job);
+                let res = check::check_all(job);
                 if res.is_err() {
                     return Err(());
                 }
             },
+            JobType::VerifyParser => {
+                if check::verify::verify_all(job).is_err() {
+                    return Err(());
+                }
+            },
+            JobType::Analyze { ref passes } => {
+                if job.verbose {
+                    msg!(
+                        Debug,
+                        "Starting analysis of {} file(s) [--analyze]",
+                        job.files.len()
+                    );
+                }
+                if analyze::analyze_all(job, passes).is_err() {
+                    return Err(());
+                }
+            },
+            JobType::Baseline { ref passes, ref path } => {
+                if baseline::record(job, passes, path).is_err() {
+                    return Err(());
+                }
+            },
+            JobType::Grep { ref code, ref kind, ref name } => {
+                let (code, kind, name) = (code.as_ref(), kind.as_ref(), name.as_ref());
+                let res = grep::search_all(
+                    job,
+                    code.map(|s| &s[..]),
+                    kind.map(|s| &s[..]),
+                    name.map(|s| &s[..]),
+                );
+                if res.is_err() {
+                    return Err(());
+                }
+            },
+            JobType::DumpTokens { json } => {
+                if dump::dump_tokens(job, json).is_err() {
+                    return Err(());
+                }
+            },
+            JobType::DumpAst { json } => {
+                if dump::dump_ast(job, json).is_err() {
+                    return Err(());
+                }
+            },
+            JobType::Format { check_only } => {
+                if job.verbose {
+                    msg!(
+                        Debug,
+                        "Starting to format {} file(s) [fmt]",
+                        job.checked_files().len()
+                    );
+                }
+                if fmt::format_all(job, check_only).is_err() {
+                    return Err(());
+                }
+            },
             JobType::PassThrough => {
                 if job.verbose {
                     msg!(
@@ -28,7 +111,7 @@ pub fn handle(job: Job) -> Result<(), ()> {
                     );
                 }
 
-                if java::compile_all(&job).is_err() {
+                if java::compile_all(job, outcomes).is_err() {
                     msg!(Aborting, "due to previous errors");
                     msg!(None, "run `jswag` again with `--verbose` or `-v` to \
                         obtain additional information.");
@@ -44,15 +127,46 @@ pub fn handle(job: Job) -> Result<(), ()> {
                     );
                 }
 
-                if java::run_first_main(&job).is_err() {
+                if java::run_first_main(job, outcomes).is_err() {
+                    msg!(Aborting, "due to previous errors");
+                    msg!(None, "run `jswag` again with `--verbose` or `-v` to \
+                        obtain additional information.");
+                    return Err(());
+                }
+            }
+            JobType::RunSource => {
+                if job.verbose {
+                    msg!(Debug, "Starting to run '{}' [run]", job.files[0].display());
+                }
+
+                if java::run_source(job, outcomes).is_err() {
+                    msg!(Aborting, "due to previous errors");
+                    msg!(None, "run `jswag` again with `--verbose` or `-v` to \
+                        obtain additional information.");
+                    return Err(());
+                }
+            }
+            JobType::Jar { ref file, ref resources } => {
+                let file = file.as_ref().map(|f| f.as_path());
+                let resources = resources.as_ref().map(|r| r.as_path());
+                if jar::package(job, file, resources, outcomes).is_err() {
                     msg!(Aborting, "due to previous errors");
                     msg!(None, "run `jswag` again with `--verbose` or `-v` to \
                         obtain additional information.");
                     return Err(());
                 }
+            },
+            JobType::Lsp { ref passes } => {
+                return lsp::serve(&job.config, passes);
             }
-            ref sj => {
-                msg!(Ignoring, "job '{:?}'", sj);
+            JobType::Toolchains => toolchain::print_all(),
+            JobType::Doctor { ref jdk } => {
+                return doctor::run(jdk.as_ref().map(|s| &s[..]));
+            },
+            JobType::Similarity { ref submissions, ref output } => {
+                if similarity::compare_all(job, submissions, output).is_err() {
+                    return Err(());
+                }
             }
         }
     }
@@ -0,0 +1,219 @@
+//! Diagnostics of the environment jswag runs in (`jswag doctor`).
+//!
+//! Every check prints one line with its status: `Ok` if everything is fine,
+//! `Warning` if things might not work as expected and `Error` if something
+//! is broken. Most problems reported to us are a missing or misconfigured
+//! JDK, so these checks come first.
+
+use analyze::custom::pattern;
+use config;
+use java::{JAVAC_NAME, JAVA_NAME};
+use std::env;
+use std::path::{Path, PathBuf};
+use toolchain::{self, Jdk};
+
+/// Runs all checks. `jdk` is the argument of `--jdk`, if given. Fails if at
+/// least one check found an error.
+pub fn run(jdk: Option<&str>) -> Result<(), ()> {
+    let mut doctor = Doctor { errors: 0 };
+
+    let selected = match jdk {
+        Some(jdk) => match toolchain::select(jdk) {
+            Ok(jdk) => {
+                doctor.ok(format!("JDK {} selected with `--jdk`", jdk.home.display()));
+                Some(jdk)
+            },
+            Err(e) => {
+                doctor.error(format!("Invalid `--jdk`: {}", e));
+                None
+            },
+        },
+        None => None,
+    };
+    let binary = |name: &str| match selected {
+        Some(ref jdk) => jdk.bin(name),
+        None => PathBuf::from(name),
+    };
+
+    let javac = doctor.tool(&binary(JAVAC_NAME), JAVAC_NAME);
+    let java = doctor.tool(&binary(JAVA_NAME), JAVA_NAME);
+    if javac.is_none() && selected.is_none() {
+        if let Some(jdk) = toolchain::discover().first() {
+            msg!(
+                Note,
+                "JDK {} is installed in '{}', use it with `--jdk {}`",
+                jdk.version,
+                jdk.home.display(),
+                jdk.home.display()
+            );
+        }
+    }
+    if let (Some(javac), Some(java)) = (javac.as_ref(), java.as_ref()) {
+        if toolchain::major_version(javac) != toolchain::major_version(java) {
+            doctor.warning(format!(
+                "`{}` ({}) and `{}` ({}) have different versions. Classes compiled by a newer \
+                    `{}` can't be run by an older `{}`.",
+                JAVAC_NAME, javac, JAVA_NAME, java, JAVAC_NAME, JAVA_NAME,
+            ));
+        }
+    }
+    doctor.java_home(javac.as_ref().map(|v| &v[..]));
+
+    doctor.terminal();
+    doctor.locale();
+    doctor.config();
+
+    if doctor.errors == 0 {
+        msg!(Note, "No problems found");
+        Ok(())
+    } else {
+        msg!(Error, "{} problem(s) found", doctor.errors);
+        Err(())
+    }
+}
+
+struct Doctor {
+    errors: usize,
+}
+
+impl Doctor {
+    fn ok(&self, s: String) {
+        msg!(Ok, "{}", s);
+    }
+
+    fn warning(&self, s: String) {
+        msg!(Warning, "{}", s);
+    }
+
+    fn error(&mut self, s: String) {
+        msg!(Error, "{}", s);
+        self.errors += 1;
+    }
+
+    /// Checks whether the binary can be run and returns its version.
+    fn tool(&mut self, binary: &Path, name: &str) -> Option<String> {
+        match toolchain::version_of(binary) {
+            Some(version) => {
+                self.ok(format!("`{}` {} ({})", name, version, binary.display()));
+                Some(version)
+            },
+            None => {
+                self.error(format!(
+                    "`{}` was not found. Install a JDK and add its `bin` directory to PATH, \
+                        set JAVA_HOME or select one with `--jdk` (see `jswag toolchains`).",
+                    binary.display(),
+                ));
+                None
+            },
+        }
+    }
+
+    /// Checks whether `JAVA_HOME` contains a JDK and whether it's the one
+    /// of `javac`.
+    fn java_home(&mut self, javac: Option<&str>) {
+        let home = match env::var_os("JAVA_HOME") {
+            Some(home) => PathBuf::from(home),
+            None => {
+                self.ok("JAVA_HOME isn't set, which jswag doesn't need".into());
+                return;
+            },
+        };
+
+        match Jdk::at(&home, "JAVA_HOME") {
+            Some(ref jdk) if javac.is_some() && javac != Some(&jdk.version[..]) => {
+                self.warning(format!(
+                    "JAVA_HOME ({}) contains JDK {}, but `{}` is {}. Other tools will use \
+                        a different JDK than jswag.",
+                    home.display(),
+                    jdk.version,
+                    JAVAC_NAME,
+                    javac.unwrap_or(""),
+                ));
+            },
+            Some(jdk) => {
+                self.ok(format!("JAVA_HOME ({}) contains JDK {}", home.display(), jdk.version));
+            },
+            None => {
+                self.warning(format!(
+                    "JAVA_HOME ({}) doesn't contain a JDK (no 'bin/{}'). Set it to the \
+                        directory containing 'bin'.",
+                    home.display(),
+                    JAVAC_NAME,
+                ));
+            },
+        }
+    }
+
+    /// Checks whether the terminal supports the colors of the output.
+    fn terminal(&self) {
+        match env::var("TERM") {
+            Ok(ref term) if term != "dumb" => {
+                self.ok(format!("The terminal supports colors (TERM={})", term));
+            },
+            _ => {
+                self.warning(
+                    "The terminal might not support colors (TERM isn't set or 'dumb'), \
+                        so the output might contain escape codes".into()
+                );
+            },
+        }
+    }
+
+    /// Checks whether the locale uses UTF-8, which jswag reads files as and
+    /// which `javac` uses for files by default.
+    fn locale(&self) {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+            .filter_map(|var| env::var(var).ok().map(|value| (var, value)))
+            .find(|&(_, ref value)| !value.is_empty());
+        match locale {
+            Some((var, ref value)) if is_utf8(value) => {
+                self.ok(format!("The locale uses UTF-8 ({}={})", var, value));
+            },
+            Some((var, value)) => {
+                self.warning(format!(
+                    "The locale doesn't use UTF-8 ({}={}). jswag reads files as UTF-8, \
+                        but `{}` might read them with another encoding.",
+                    var,
+                    value,
+                    JAVAC_NAME,
+                ));
+            },
+            None => {
+                self.warning(format!(
+                    "No locale is set (LC_ALL, LC_CTYPE or LANG). jswag reads files as \
+                        UTF-8, but `{}` might read them as ASCII.",
+                    JAVAC_NAME,
+                ));
+            },
+        }
+    }
+
+    /// Checks whether the configuration file and the rule files are valid.
+    fn config(&mut self) {
+        match config::find() {
+            Some(path) => match config::load_file(&path) {
+                Ok(_) => self.ok(format!("The configuration file '{}' is valid", path.display())),
+                Err(e) => self.error(format!(
+                    "Invalid configuration file '{}': {}",
+                    path.display(),
+                    e,
+                )),
+            },
+            None => self.ok(format!(
+                "No configuration file (`{}`), the defaults are used",
+                config::CONFIG_FILE_NAME,
+            )),
+        }
+
+        match pattern::load() {
+            Ok(ref rules) if rules.is_empty() => {},
+            Ok(rules) => self.ok(format!("{} custom rule(s) loaded", rules.len())),
+            Err(e) => self.error(format!("Invalid rule file {}", e)),
+        }
+    }
+}
+
+fn is_utf8(locale: &str) -> bool {
+    let lower = locale.to_lowercase();
+    lower.contains("utf-8") || lower.contains("utf8")
+}
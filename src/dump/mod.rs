@@ -0,0 +1,540 @@
+//! Debugging output of the internal parser: the tokens (`jswag dump-tokens`)
+//! and the AST (`jswag dump-ast`) of files, as text or as JSON.
+//!
+//! The AST is parsed like in `check`, so the dump shows exactly what the
+//! checks and analysis passes work with. It's printed as a tree of nodes,
+//! each with its kind, some details (names, operators, types, ...) and the
+//! position of its span, if it has one.
+
+use base::code::{FileMap, Span};
+use base::diag;
+use check;
+use job::Job;
+use rustc_serialize::json::Json;
+use std::collections::BTreeMap;
+use std::path::Path;
+use syntax::{self, ast, lex};
+use syntax::ast::{ExprType, StatementType};
+
+/// A node of the printed tree
+struct Node {
+    kind: &'static str,
+    detail: String,
+    span: Option<Span>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new<S: Into<String>>(kind: &'static str, detail: S, span: Option<Span>) -> Node {
+        Node {
+            kind: kind,
+            detail: detail.into(),
+            span: span,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Prints the tokens of all files of the job (without whitespace). A file
+/// with a lexer error is reported and skipped. With `json`, one array with
+/// an object for every file is printed.
+pub fn dump_tokens(job: &Job, json: bool) -> Result<(), ()> {
+    let mut failed = false;
+    let mut json_files = Vec::new();
+    'files: for file in &job.files {
+        let file_map = match read(job, file) {
+            Ok(file_map) => file_map,
+            Err(()) => {
+                failed = true;
+                continue;
+            },
+        };
+        let src = file_map.src();
+
+        let mut tokens = Vec::new();
+        for res in lex::Tokenizer::new(&file_map) {
+            match res {
+                Ok(ts) => {
+                    if ts.tok != lex::Token::Whitespace {
+                        tokens.push(ts);
+                    }
+                },
+                Err(e) => {
+                    diag::print(&e.report, &file_map, diag::PrintOptions::default());
+                    msg!(Error, "'{}' couldn't be tokenized", file.display());
+                    failed = true;
+                    continue 'files;
+                },
+            }
+        }
+
+        if json {
+            let tokens = tokens.iter().map(|ts| {
+                let mut obj = BTreeMap::new();
+                obj.insert("token".to_string(), Json::String(format!("{:?}", ts.tok)));
+                obj.insert("text".to_string(), Json::String(src[ts.span.into_range()].into()));
+                obj.insert("span".to_string(), span_json(src, Some(ts.span)));
+                Json::Object(obj)
+            }).collect();
+            json_files.push(file_json(file, "tokens", Json::Array(tokens)));
+        } else {
+            for ts in &tokens {
+                let (line, col) = line_col(src, ts.span.lo.0 as usize);
+                println!(
+                    "{:>5}:{:<4} {:<30} {}",
+                    line,
+                    col,
+                    format!("{:?}", ts.tok),
+                    &src[ts.span.into_range()],
+                );
+            }
+        }
+    }
+
+    if json {
+        println!("{}", Json::Array(json_files).pretty());
+    }
+    if failed {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// Parses all files of the job and prints their ASTs. Parser errors are
+/// printed, too. With `json`, one array with an object for every file is
+/// printed.
+pub fn dump_ast(job: &Job, json: bool) -> Result<(), ()> {
+    let mut failed = false;
+    let mut json_files = Vec::new();
+    for file in &job.files {
+        let file_map = match read(job, file) {
+            Ok(file_map) => file_map,
+            Err(()) => {
+                failed = true;
+                continue;
+            },
+        };
+        let (ast, errors) = syntax::parse_compilation_unit(&file_map);
+        for e in &errors {
+            diag::print(e, &file_map, diag::PrintOptions::default());
+        }
+
+        let ast = match ast {
+            Some(ast) => ast,
+            None => {
+                msg!(Error, "'{}' couldn't be parsed", file.display());
+                failed = true;
+                continue;
+            },
+        };
+        let dumper = Dumper { src: file_map.src() };
+        let tree = dumper.compilation_unit(&ast);
+        if json {
+            json_files.push(file_json(file, "ast", dumper.json(&tree)));
+        } else {
+            dumper.print(&tree, 0);
+        }
+    }
+
+    if json {
+        println!("{}", Json::Array(json_files).pretty());
+    }
+    if failed {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// The JSON object of one file: its name and the dump under `key`
+fn file_json(file: &Path, key: &str, dump: Json) -> Json {
+    let mut obj = BTreeMap::new();
+    obj.insert("file".to_string(), Json::String(file.to_string_lossy().into_owned()));
+    obj.insert(key.to_string(), dump);
+    Json::Object(obj)
+}
+
+fn read(job: &Job, file: &Path) -> Result<FileMap, ()> {
+    match check::read_file(job, file) {
+        Ok(src) => Ok(FileMap::new(file.to_string_lossy().into_owned(), src)),
+        Err(e) => {
+            msg!(Error, "Could not read '{}': {:?}", file.display(), e);
+            Err(())
+        },
+    }
+}
+
+/// Line and column (both starting at 1) of a byte position
+fn line_col(src: &str, pos: usize) -> (usize, usize) {
+    let pos = ::std::cmp::min(pos, src.len());
+    let line_start = src[..pos].rfind('\n').map(|p| p + 1).unwrap_or(0);
+    (src[..pos].matches('\n').count() + 1, src[line_start..pos].chars().count() + 1)
+}
+
+fn span_json(src: &str, span: Option<Span>) -> Json {
+    let span = match span {
+        Some(span) => span,
+        None => return Json::Null,
+    };
+    let mut obj = BTreeMap::new();
+    for &(key, pos) in &[("start", span.lo.0), ("end", span.hi.0)] {
+        let (line, col) = line_col(src, pos as usize);
+        let mut loc = BTreeMap::new();
+        loc.insert("line".to_string(), Json::U64(line as u64));
+        loc.insert("column".to_string(), Json::U64(col as u64));
+        obj.insert(key.to_string(), Json::Object(loc));
+    }
+    Json::Object(obj)
+}
+
+struct Dumper<'a> {
+    src: &'a str,
+}
+
+impl<'a> Dumper<'a> {
+    fn print(&self, node: &Node, depth: usize) {
+        let pos = match node.span {
+            Some(span) => {
+                let (line, col) = line_col(self.src, span.lo.0 as usize);
+                format!("  @ {}:{}", line, col)
+            },
+            None => String::new(),
+        };
+        let sep = if node.detail.is_empty() { "" } else { " " };
+        println!(
+            "{:indent$}{}{}{}{}",
+            "",
+            node.kind,
+            sep,
+            node.detail,
+            pos,
+            indent = depth * 2,
+        );
+        for child in &node.children {
+            self.print(child, depth + 1);
+        }
+    }
+
+    fn json(&self, node: &Node) -> Json {
+        let mut obj = BTreeMap::new();
+        obj.insert("kind".to_string(), Json::String(node.kind.into()));
+        obj.insert("detail".to_string(), Json::String(node.detail.clone()));
+        obj.insert("span".to_string(), span_json(self.src, node.span));
+        obj.insert(
+            "children".to_string(),
+            Json::Array(node.children.iter().map(|c| self.json(c)).collect()),
+        );
+        Json::Object(obj)
+    }
+
+    fn text(&self, span: Span) -> &'a str {
+        &self.src[span.into_range()]
+    }
+
+    fn compilation_unit(&self, ast: &ast::CompilationUnit) -> Node {
+        let mut node = Node::new("CompilationUnit", "", None);
+        if let Some(ref package) = ast.package {
+            node.children.push(Node::new("Package", path(package), package.span()));
+        }
+        for import in &ast.imports {
+            let (p, detail) = match *import {
+                ast::Import::SingleType(ref p) => (p, path(p)),
+                ast::Import::TypeOnDemand(ref p) => (p, format!("{}.*", path(p))),
+                ast::Import::SingleStatic(ref p) => (p, format!("static {}", path(p))),
+                ast::Import::StaticOnDemand(ref p) => (p, format!("static {}.*", path(p))),
+            };
+            node.children.push(Node::new("Import", detail, p.span()));
+        }
+        for ty in &ast.types {
+            node.children.push(self.type_def(ty));
+        }
+        node
+    }
+
+    fn type_def(&self, ty: &ast::TypeDef) -> Node {
+        match *ty {
+            ast::TypeDef::NormalClass(ref class) => {
+                let detail = format!("{}{}", vis(class.vis), class.name.name);
+                let mut node = Node::new("Class", detail, Some(class.name.span));
+                for member in &class.members {
+                    node.children.push(self.member(member));
+                }
+                node
+            },
+            ast::TypeDef::NormalInterface(ref interface) => {
+                let detail = format!("{}{}", vis(interface.vis), interface.name.name);
+                let mut node = Node::new("Interface", detail, Some(interface.name.span));
+                for ty in &interface.extends {
+                    node.children.push(Node::new("Extends", ty_name(ty), ty.name.span()));
+                }
+                node.children.extend(interface.constants.iter().map(|f| self.field(f)));
+                node.children.extend(interface.methods.iter().map(|m| self.method(m)));
+                node.children.extend(interface.types.iter().map(|t| self.type_def(t)));
+                node
+            },
+        }
+    }
+
+    fn member(&self, member: &ast::ClassMember) -> Node {
+        match *member {
+            ast::ClassMember::Method(ref method) => self.method(method),
+            ast::ClassMember::Field(ref field) => self.field(field),
+        }
+    }
+
+    fn field(&self, f: &ast::Field) -> Node {
+        let detail = format!(
+            "{}{}{}{} {}",
+            vis(f.vis),
+            if f.static_ { "static " } else { "" },
+            if f.final_ { "final " } else { "" },
+            ty_name(&f.ty),
+            f.name.name,
+        );
+        Node::new("Field", detail, Some(f.name.span))
+    }
+
+    fn method(&self, m: &ast::Method) -> Node {
+        let modifiers = [
+            (m.static_, "static "),
+            (m.abstract_, "abstract "),
+            (m.final_, "final "),
+            (m.synchronized, "synchronized "),
+            (m.native, "native "),
+            (m.default, "default "),
+        ];
+        let mut detail = vis(m.vis).to_string();
+        for &(_, s) in modifiers.iter().filter(|&&(set, _)| set) {
+            detail.push_str(s);
+        }
+        detail.push_str(&format!("{} {}", ty_name(&m.ret_ty), m.name.name));
+
+        let mut node = Node::new("Method", detail, Some(m.name.span));
+        for param in &m.params {
+            let detail = format!(
+                "{}{} {}",
+                if param.final_ { "final " } else { "" },
+                ty_name(&param.ty),
+                param.name.name,
+            );
+            node.children.push(Node::new("Parameter", detail, Some(param.name.span)));
+        }
+        for ty in &m.throws {
+            node.children.push(Node::new("Throws", ty_name(ty), ty.name.span()));
+        }
+        if let Some(ref block) = m.block {
+            node.children.push(self.block(block, None));
+        }
+        node
+    }
+
+    fn block(&self, block: &ast::Block, span: Option<Span>) -> Node {
+        let mut node = Node::new("Block", "", span);
+        node.children.extend(block.stmts.iter().map(|s| self.block_stmt(s)));
+        node
+    }
+
+    fn block_stmt(&self, stmt: &ast::BlockStatement) -> Node {
+        match *stmt {
+            ast::BlockStatement::LocalVariableDecl { final_, ref ty, ref vars } => {
+                let detail = format!("{}{}", if final_ { "final " } else { "" }, ty_name(ty));
+                let mut node = Node::new("LocalVariableDecl", detail, ty.name.span());
+                for var in vars {
+                    node.children.push(self.var(var));
+                }
+                node
+            },
+            ast::BlockStatement::Statement(ref stmt) => self.stmt(stmt),
+        }
+    }
+
+    fn var(&self, var: &ast::VariableDeclarator) -> Node {
+        let detail = format!("{}{}", var.name.name, dims(var.dims));
+        let mut node = Node::new("Variable", detail, Some(var.name.span));
+        if let Some(ref init) = var.init {
+            node.children.push(self.expr(init));
+        }
+        node
+    }
+
+    fn stmt(&self, stmt: &ast::Statement) -> Node {
+        let span = Some(stmt.span);
+        let mut node = match stmt.stmt {
+            StatementType::Empty => Node::new("Empty", "", span),
+            StatementType::Block(ref block) => self.block(block, span),
+            StatementType::Expr(ref e) => {
+                let mut node = Node::new("ExprStatement", "", span);
+                node.children.push(self.expr(e));
+                node
+            },
+            StatementType::IfThenElse { ref cond, ref then_branch, ref else_branch } => {
+                let mut node = Node::new("If", "", span);
+                node.children.push(self.expr(cond));
+                node.children.push(self.stmt(then_branch));
+                if let Some(ref else_branch) = *else_branch {
+                    node.children.push(self.stmt(else_branch));
+                }
+                node
+            },
+            StatementType::While { ref cond, ref body } => {
+                let mut node = Node::new("While", "", span);
+                node.children.push(self.expr(cond));
+                node.children.push(self.stmt(body));
+                node
+            },
+            StatementType::DoWhile { ref cond, ref body } => {
+                let mut node = Node::new("DoWhile", "", span);
+                node.children.push(self.stmt(body));
+                node.children.push(self.expr(cond));
+                node
+            },
+            StatementType::For { ref init, ref cond, ref update, ref body } => {
+                let mut node = Node::new("For", "", span);
+                let mut init_node = Node::new("ForInit", "", None);
+                match *init {
+                    ast::ForInit::VarDecl(ref decl) => {
+                        init_node.children.push(self.block_stmt(decl));
+                    },
+                    ast::ForInit::Stmts(ref stmts) => {
+                        init_node.children.extend(stmts.iter().map(|s| self.stmt(s)));
+                    },
+                }
+                node.children.push(init_node);
+                if let Some(ref cond) = *cond {
+                    node.children.push(self.expr(cond));
+                }
+                let mut update_node = Node::new("ForUpdate", "", None);
+                update_node.children.extend(update.iter().map(|s| self.stmt(s)));
+                node.children.push(update_node);
+                node.children.push(self.stmt(body));
+                node
+            },
+            StatementType::Switch { ref val, ref arms, ref empty_arms } => {
+                let mut node = Node::new("Switch", "", span);
+                node.children.push(self.expr(val));
+                for arm in arms {
+                    let mut arm_node = Node::new("Arm", self.labels(&arm.labels), None);
+                    arm_node.children.push(self.block_stmt(&arm.block));
+                    node.children.push(arm_node);
+                }
+                if !empty_arms.is_empty() {
+                    node.children.push(Node::new("EmptyArm", self.labels(empty_arms), None));
+                }
+                node
+            },
+            StatementType::Break(ref label) => {
+                Node::new("Break", label.as_ref().map(|l| &l.name[..]).unwrap_or(""), span)
+            },
+            StatementType::Continue(ref label) => {
+                Node::new("Continue", label.as_ref().map(|l| &l.name[..]).unwrap_or(""), span)
+            },
+            StatementType::Return(ref e) => {
+                let mut node = Node::new("Return", "", span);
+                node.children.extend(e.iter().map(|e| self.expr(e)));
+                node
+            },
+            StatementType::Throw(ref e) => {
+                let mut node = Node::new("Throw", "", span);
+                node.children.push(self.expr(e));
+                node
+            },
+        };
+
+        if let Some(ref label) = stmt.label {
+            node.detail = format!("{}: {}", label.name, node.detail).trim_right().to_string();
+        }
+        node
+    }
+
+    fn labels(&self, labels: &[ast::SwitchLabel]) -> String {
+        let labels: Vec<_> = labels.iter().map(|label| match *label {
+            ast::SwitchLabel::Expr(ref e) => format!("case {}", self.text(e.span)),
+            ast::SwitchLabel::Name(ref ident) => format!("case {}", ident.name),
+            ast::SwitchLabel::Default => "default".to_string(),
+        }).collect();
+        labels.join(", ")
+    }
+
+    fn expr(&self, e: &ast::Expr) -> Node {
+        let span = Some(e.span);
+        let (kind, detail, children) = match e.expr {
+            ExprType::Conditional { ref cond, ref if_branch, ref else_branch } => {
+                ("Conditional", String::new(), vec![&**cond, &**if_branch, &**else_branch])
+            },
+            ExprType::BinOp { op, ref lhs, ref rhs } => {
+                ("BinOp", format!("{:?}", op), vec![&**lhs, &**rhs])
+            },
+            ExprType::UnaryOp { op, ref expr } => ("UnaryOp", format!("{:?}", op), vec![&**expr]),
+            ExprType::Literal(_) => ("Literal", self.text(e.span).to_string(), vec![]),
+            ExprType::ClassLiteral(ref ty) => ("ClassLiteral", ty_name(ty), vec![]),
+            ExprType::Name(ref p) => ("Name", path(p), vec![]),
+            ExprType::This => ("This", String::new(), vec![]),
+            ExprType::FieldAccess { ref root, path: ref p } => {
+                ("FieldAccess", path(p), root.iter().map(|r| &**r).collect())
+            },
+            ExprType::ArrayAccess { ref obj, ref idx } => {
+                ("ArrayAccess", String::new(), vec![&**obj, &**idx])
+            },
+            ExprType::MethodInvocation { ref name, ref args } => {
+                let (name, mut children) = match *name {
+                    ast::MethodInvocationType::SimpleName(ref ident) => {
+                        (ident.name.clone(), vec![])
+                    },
+                    ast::MethodInvocationType::SimplePath(ref p) => (path(p), vec![]),
+                    ast::MethodInvocationType::Expr(ref obj, ref ident) => {
+                        (ident.name.clone(), vec![&**obj])
+                    },
+                };
+                children.extend(args);
+                ("MethodInvocation", name, children)
+            },
+            ExprType::ArrayInit { ref items } => {
+                ("ArrayInit", String::new(), items.iter().collect())
+            },
+            ExprType::Cast { ref ty, ref expr } => ("Cast", ty_name(ty), vec![&**expr]),
+            ExprType::InstanceCreation { ref name, ref args, ref body } => {
+                let mut node = Node::new("InstanceCreation", path(name), span);
+                node.children.extend(args.iter().map(|a| self.expr(a)));
+                if let Some(ref members) = *body {
+                    let mut body_node = Node::new("AnonymousClass", "", None);
+                    body_node.children.extend(members.iter().map(|m| self.member(m)));
+                    node.children.push(body_node);
+                }
+                return node;
+            },
+            ExprType::ArrayCreation { ref ty, ref expr_dims, empty_dims, ref init } => {
+                let detail = format!("{}{}", ty_name(ty), dims(empty_dims));
+                let mut children: Vec<_> = expr_dims.iter().collect();
+                children.extend(init.iter().map(|i| &**i));
+                ("ArrayCreation", detail, children)
+            },
+        };
+
+        let mut node = Node::new(kind, detail, span);
+        node.children.extend(children.into_iter().map(|c| self.expr(c)));
+        node
+    }
+}
+
+fn path(p: &ast::Path) -> String {
+    let segments: Vec<_> = p.segments.iter().map(|s| &s.name[..]).collect();
+    segments.join(".")
+}
+
+fn ty_name(ty: &ast::Type) -> String {
+    format!("{}{}", path(&ty.name), dims(ty.dims))
+}
+
+fn dims(dims: ast::Dims) -> String {
+    (0..dims).map(|_| "[]").collect()
+}
+
+fn vis(vis: ast::Visibility) -> &'static str {
+    match vis {
+        ast::Visibility::Public => "public ",
+        ast::Visibility::Protected => "protected ",
+        ast::Visibility::Package => "",
+        ast::Visibility::Private => "private ",
+    }
+}
@@ -0,0 +1,175 @@
+//! Line based unified diffs, as printed by `diff -u`.
+//!
+//! The diff itself is computed with Myers' algorithm ("An O(ND) Difference
+//! Algorithm and Its Variations"), which is fast if both texts are mostly
+//! equal -- exactly the case for fixes.
+
+/// Number of unchanged lines shown around each change
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum OpKind {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// One line of the diff with the line indices in the old and new text
+/// before this operation.
+#[derive(Clone, Copy, Debug)]
+struct Op {
+    kind: OpKind,
+    old: usize,
+    new: usize,
+}
+
+/// Returns a unified diff between the two texts or an empty string if they
+/// are equal.
+pub fn unified(old: &str, new: &str, file_name: &str) -> String {
+    let old_lines = lines(old);
+    let new_lines = lines(new);
+    let ops = diff(&old_lines, &new_lines);
+
+    let mut out = String::new();
+    if ops.iter().all(|op| op.kind == OpKind::Equal) {
+        return out;
+    }
+    out.push_str(&format!("--- a/{}\n+++ b/{}\n", file_name, file_name));
+
+    let mut i = 0;
+    while i < ops.len() {
+        if ops[i].kind == OpKind::Equal {
+            i += 1;
+            continue;
+        }
+
+        // Extend the hunk as long as the next change is near enough to share
+        // the context lines.
+        let start = i - ::std::cmp::min(i, CONTEXT);
+        let mut end = i + 1;
+        let mut j = end;
+        while j < ops.len() && j - end <= 2 * CONTEXT {
+            if ops[j].kind != OpKind::Equal {
+                end = j + 1;
+            }
+            j += 1;
+        }
+        let end = ::std::cmp::min(end + CONTEXT, ops.len());
+        let hunk = &ops[start..end];
+
+        let old_len = hunk.iter().filter(|op| op.kind != OpKind::Insert).count();
+        let new_len = hunk.iter().filter(|op| op.kind != OpKind::Delete).count();
+        // Empty ranges are denoted by the line before them
+        let old_start = hunk[0].old + if old_len > 0 { 1 } else { 0 };
+        let new_start = hunk[0].new + if new_len > 0 { 1 } else { 0 };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start,
+            old_len,
+            new_start,
+            new_len,
+        ));
+
+        for op in hunk {
+            let (prefix, line) = match op.kind {
+                OpKind::Equal => (' ', old_lines[op.old]),
+                OpKind::Delete => ('-', old_lines[op.old]),
+                OpKind::Insert => ('+', new_lines[op.new]),
+            };
+            out.push(prefix);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+
+        i = end;
+    }
+
+    out
+}
+
+/// Splits the text into lines, keeping the line breaks.
+fn lines(s: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        if c == '\n' {
+            out.push(&s[start..i + 1]);
+            start = i + 1;
+        }
+    }
+    if start < s.len() {
+        out.push(&s[start..]);
+    }
+    out
+}
+
+/// Computes the shortest edit script between both line lists.
+fn diff(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = n + m;
+    let offset = max + 1;
+    let idx = |k: isize| (k + offset) as usize;
+
+    // `v[k]` holds the furthest x reached on diagonal `k`. We store a copy of
+    // `v` for each step, to be able to backtrack the path afterwards.
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    let mut trace = Vec::new();
+    'outer: for d in 0..max + 1 {
+        trace.push(v.clone());
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && v[idx(k - 1)] < v[idx(k + 1)]) {
+                v[idx(k + 1)]
+            } else {
+                v[idx(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx(k)] = x;
+
+            if x >= n && y >= m {
+                break 'outer;
+            }
+            k += 2;
+        }
+    }
+
+    // Walk backwards through the stored states
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[idx(k - 1)] < v[idx(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[idx(prev_k)];
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push(Op { kind: OpKind::Equal, old: x as usize, new: y as usize });
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push(Op { kind: OpKind::Insert, old: x as usize, new: prev_y as usize });
+            } else {
+                ops.push(Op { kind: OpKind::Delete, old: prev_x as usize, new: y as usize });
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+
+    ops.reverse();
+    ops
+}
@@ -0,0 +1,120 @@
+//! Automatic fixing of findings with machine-applicable edits.
+//!
+//! Edits are applied in rounds: after applying all non-overlapping edits,
+//! the file is parsed and analyzed again, since skipped or new findings may
+//! be fixable now. This is repeated until no edits are left or the maximum
+//! number of rounds is reached.
+
+pub mod diff;
+
+use analyze::{self, Edit};
+use base::code::FileMap;
+use check;
+use job::{AnalyzePass, FixMode, Job};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use syntax;
+
+/// Maximum number of rounds, to not loop forever if two fixes undo each
+/// other.
+const MAX_ROUNDS: usize = 10;
+
+/// Fixes all findings of the given passes in the file. Findings that can't
+/// be fixed are printed. Fails if the file couldn't be read or written or if
+/// one of the remaining findings is an error.
+pub fn fix_file(
+    job: &Job,
+    passes: &VecDeque<AnalyzePass>,
+    file: &Path,
+    mode: FixMode,
+) -> Result<(), ()> {
+    // Errors while reading the file were already reported by the `Check` job
+    let orig = try!(check::read_file(job, file).map_err(|_| ()));
+    let file_name = file.to_string_lossy().into_owned();
+
+    let mut src = orig.clone();
+    let mut num_edits = 0;
+    for _ in 0..MAX_ROUNDS {
+        let file_map = FileMap::new(file_name.clone(), src.clone());
+        let ast = match syntax::parse_compilation_unit(&file_map) {
+            (Some(ast), _) => ast,
+            (None, _) => break,
+        };
+
+        let mut edits: Vec<_> = analyze::analyze_file(passes, &job.config, &file_map, &ast)
+            .into_iter()
+            .flat_map(|f| f.fix.into_iter())
+            .collect();
+        if edits.is_empty() {
+            break;
+        }
+
+        let (new_src, applied) = apply(&src, &mut edits);
+        src = new_src;
+        num_edits += applied;
+    }
+
+    // Report all findings left in the fixed code. The file map has to be
+    // parsed again, since printing reports needs its line information.
+    let file_map = FileMap::new(file_name.clone(), src.clone());
+    let failed = match syntax::parse_compilation_unit(&file_map) {
+        (Some(ast), _) => {
+            let remaining = analyze::analyze_file(passes, &job.config, &file_map, &ast);
+            analyze::print_findings(&remaining, &file_map)
+        },
+        (None, _) => {
+            msg!(Error, "Fixing '{}' would break the code, not changing it", file.display());
+            return Err(());
+        },
+    };
+
+    if src != orig {
+        match mode {
+            FixMode::Apply => {
+                msg!(Fixing, "'{}' ({} edit(s))", file.display(), num_edits);
+                let res = File::create(file).and_then(|mut f| f.write_all(src.as_bytes()));
+                if let Err(e) = res {
+                    msg!(Error, "Could not write '{}': {}", file.display(), e);
+                    return Err(());
+                }
+            },
+            FixMode::DryRun => {
+                print!("{}", diff::unified(&orig, &src, &file_name));
+            },
+        }
+    }
+
+    if failed {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// Applies the given edits to the source code. Edits overlapping a previous
+/// edit are skipped. Returns the new source code and the number of edits
+/// that were applied.
+pub fn apply(src: &str, edits: &mut Vec<Edit>) -> (String, usize) {
+    edits.sort_by_key(|e| (e.span.lo, e.span.hi));
+    edits.dedup();
+
+    let mut out = String::with_capacity(src.len());
+    let mut pos = 0;
+    let mut applied = 0;
+    for edit in edits.iter() {
+        let range = edit.span.into_range();
+        if range.start < pos {
+            continue;
+        }
+
+        out.push_str(&src[pos..range.start]);
+        out.push_str(&edit.with);
+        pos = range.end;
+        applied += 1;
+    }
+    out.push_str(&src[pos..]);
+
+    (out, applied)
+}
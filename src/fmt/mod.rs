@@ -0,0 +1,353 @@
+//! Formatter that rewrites Java source files in the style checked by the
+//! `style` pass.
+//!
+//! The AST of `xswag-syntax-java` doesn't contain everything that's in the
+//! source (e.g. initializers of fields), so pretty printing it would lose
+//! code. Instead, the formatter rewrites the whitespace between the tokens of
+//! a file that was parsed successfully: line breaks are inserted at
+//! statement and block boundaries, line breaks within a statement are kept
+//! and indented as continuation lines. Long lines are not wrapped.
+//!
+//! The indentation is computed by the same `Indenter` the `indentation` rule
+//! uses and both read the same `StyleConfig`, so formatted code never
+//! violates the style rules.
+
+use analyze;
+use analyze::style::braces;
+use analyze::style::indent::Indenter;
+use base::code::FileMap;
+use check;
+use config::StyleConfig;
+use fix::diff;
+use job::Job;
+use std::fs::File;
+use std::io::Write;
+use syntax;
+use syntax::lex::{Keyword, Token};
+
+/// Kinds of blocks delimited by braces
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum BraceKind {
+    /// Normal block of a declaration or statement
+    Block,
+    /// Body of a `do ... while` loop
+    DoBlock,
+    /// Array initializer, e.g. `{1, 2, 3}`
+    Init,
+}
+
+/// What to put between two tokens
+enum Sep {
+    Nothing,
+    Space,
+    Newline {
+        blank_lines: usize,
+    },
+}
+
+/// Formats all files of the job in-place. If `check_only` is set, no files
+/// are changed. Instead, a diff is printed for every file that isn't
+/// formatted correctly and the job fails.
+pub fn format_all(job: &Job, check_only: bool) -> Result<(), ()> {
+    let mut unformatted = 0;
+    let mut failed = false;
+    for file in job.checked_files() {
+        // Errors while reading and parsing were already reported by the
+        // `Check` job, which always runs before this one.
+        let src = match check::read_file(job, file) {
+            Ok(src) => src,
+            Err(_) => continue,
+        };
+        let file_name = file.to_string_lossy().into_owned();
+        let file_map = FileMap::new(file_name.clone(), src);
+        if syntax::parse_compilation_unit(&file_map).0.is_none() {
+            continue;
+        }
+
+        let formatted = format(&job.config.style, &file_map);
+        if formatted == file_map.src() {
+            continue;
+        }
+
+        if check_only {
+            print!("{}", diff::unified(file_map.src(), &formatted, &file_name));
+            unformatted += 1;
+        } else {
+            msg!(Writing, "'{}'", file.display());
+            let res = File::create(file).and_then(|mut f| f.write_all(formatted.as_bytes()));
+            if let Err(e) = res {
+                msg!(Error, "Could not write '{}': {}", file.display(), e);
+                failed = true;
+            }
+        }
+    }
+
+    if unformatted > 0 {
+        msg!(Error, "{} file(s) are not formatted correctly", unformatted);
+        msg!(Note, "run `jswag fmt` without `--check-format` to format them");
+        failed = true;
+    }
+
+    if failed {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the formatted source code of the given file. The file has to be
+/// parsed without errors before.
+pub fn format(config: &StyleConfig, file_map: &FileMap) -> String {
+    let src = file_map.src();
+    let tokens = analyze::tokenize(file_map);
+
+    let mut out = String::with_capacity(src.len());
+    let mut indenter = Indenter::new();
+    let mut braces = Vec::new();
+    let mut indent = 0;
+
+    // Number of line breaks in the whitespace before the current token
+    let mut newlines = 0;
+    // The previous token that isn't whitespace and its text
+    let mut prev: Option<&Token> = None;
+    let mut prev_text = "";
+    // The previous real token and whether it was an unary operator
+    let mut prev_real: Option<(&Token, bool)> = None;
+    // The kind of the block closed by the previous token, if it's `}`
+    let mut closed = None;
+
+    for ts in &tokens {
+        let tok = &ts.tok;
+        let text = &src[ts.span.into_range()];
+        if *tok == Token::Whitespace {
+            newlines += text.matches('\n').count();
+            continue;
+        }
+
+        let unary = is_unary(tok, prev_real);
+        let top = braces.last().cloned();
+        let label_colon = indenter.is_label_line() && !indenter.in_parens();
+
+        // Kind of the block, if this token opens one
+        let kind = if *tok == Token::BraceOp {
+            let prev_tok = prev_real.map(|p| p.0);
+            Some(if braces::opens_initializer(prev_tok, top == Some(BraceKind::Init)) {
+                BraceKind::Init
+            } else if prev_tok == Some(&Token::KeyW(Keyword::Do)) {
+                BraceKind::DoBlock
+            } else {
+                BraceKind::Block
+            })
+        } else {
+            None
+        };
+
+        let blank_lines = if newlines > 1 && *tok != Token::BraceCl {
+            ::std::cmp::min(newlines - 1, config.max_blank_lines)
+        } else {
+            0
+        };
+        let keep_break = if newlines > 0 {
+            Sep::Newline { blank_lines: blank_lines }
+        } else {
+            Sep::Nothing
+        };
+
+        let sep = match prev {
+            // The first token starts the first line
+            None => Sep::Newline { blank_lines: 0 },
+
+            // Comments keep their position relative to the code: either at
+            // the end of a line or on their own line.
+            Some(&Token::Comment) if is_line_comment(prev_text) => {
+                Sep::Newline { blank_lines: blank_lines }
+            },
+            Some(&Token::Comment) if newlines == 0 => Sep::Space,
+            Some(&Token::Comment) => Sep::Newline { blank_lines: blank_lines },
+            Some(_) if *tok == Token::Comment && newlines == 0 => Sep::Space,
+            Some(_) if *tok == Token::Comment => Sep::Newline { blank_lines: blank_lines },
+
+            // Closing braces
+            Some(p) if *tok == Token::BraceCl => match top {
+                Some(BraceKind::Init) => keep_break,
+                _ if *p == Token::BraceOp => Sep::Nothing,
+                _ => Sep::Newline { blank_lines: 0 },
+            },
+
+            // Opening braces
+            Some(&Token::BraceOp) => match top {
+                Some(BraceKind::Init) => keep_break,
+                _ => Sep::Newline { blank_lines: 0 },
+            },
+            Some(&Token::BraceCl) if closed != Some(BraceKind::Init) => match *tok {
+                Token::KeyW(Keyword::Else)
+                    | Token::KeyW(Keyword::Catch)
+                    | Token::KeyW(Keyword::Finally) => Sep::Space,
+                Token::KeyW(Keyword::While) if closed == Some(BraceKind::DoBlock) => {
+                    Sep::Space
+                },
+                Token::Semi | Token::Comma | Token::ParenCl | Token::Dot => Sep::Nothing,
+                _ => Sep::Newline { blank_lines: blank_lines },
+            },
+
+            // Ends of statements and switch labels
+            Some(&Token::Semi) if !indenter.in_parens() => {
+                Sep::Newline { blank_lines: blank_lines }
+            },
+            Some(&Token::Colon) if label_colon => Sep::Newline { blank_lines: 0 },
+
+            // The brace of a block belongs in the same line as the
+            // declaration or statement it belongs to
+            Some(p) if kind == Some(BraceKind::Block) || kind == Some(BraceKind::DoBlock) => {
+                if braces::introduces_block(p) {
+                    Sep::Space
+                } else {
+                    Sep::Newline { blank_lines: blank_lines }
+                }
+            },
+            Some(_) if kind == Some(BraceKind::Init) => Sep::Space,
+
+            // Line breaks within a statement are kept
+            Some(_) if newlines > 0 => Sep::Newline { blank_lines: 0 },
+
+            Some(p) => {
+                let prev_unary = prev_real.map(|p| p.1).unwrap_or(false);
+                if needs_space(p, prev_unary, tok, unary, label_colon) {
+                    Sep::Space
+                } else {
+                    Sep::Nothing
+                }
+            },
+        };
+
+        match sep {
+            Sep::Nothing => {},
+            Sep::Space => out.push(' '),
+            Sep::Newline { blank_lines } => {
+                if prev.is_some() {
+                    for _ in 0..blank_lines + 1 {
+                        out.push('\n');
+                    }
+                }
+
+                let expected = indenter.line_start(tok);
+                let level = if expected.continuation {
+                    let level = expected.level + config.continuation_indent;
+                    indenter.set_line_level(level);
+                    level
+                } else {
+                    expected.level
+                };
+                indent = level * config.indent_width;
+                for _ in 0..indent {
+                    out.push(' ');
+                }
+            },
+        }
+
+        if *tok == Token::Comment {
+            push_comment(&mut out, text, indent);
+        } else {
+            out.push_str(text);
+        }
+
+        indenter.token(tok);
+        closed = None;
+        match *tok {
+            Token::BraceOp => braces.push(kind.unwrap_or(BraceKind::Block)),
+            Token::BraceCl => closed = braces.pop(),
+            _ => {},
+        }
+
+        newlines = 0;
+        prev = Some(tok);
+        prev_text = text;
+        if tok.is_real() {
+            prev_real = Some((tok, unary));
+        }
+    }
+
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+/// Whether the text of a comment token is a line comment (`// ...`)
+fn is_line_comment(comment: &str) -> bool {
+    comment.starts_with("//")
+}
+
+/// Appends the comment without trailing whitespace. Lines of block comments
+/// starting with `*` are aligned to the indentation of the comment.
+fn push_comment(out: &mut String, text: &str, indent: usize) {
+    for (i, line) in text.lines().enumerate() {
+        if i > 0 {
+            out.push('\n');
+            let trimmed = line.trim_left();
+            if trimmed.starts_with('*') {
+                for _ in 0..indent + 1 {
+                    out.push(' ');
+                }
+                out.push_str(trimmed.trim_right());
+                continue;
+            }
+        }
+        out.push_str(line.trim_right());
+    }
+}
+
+/// Determines whether the token is an unary operator (or a prefix increment
+/// or decrement), based on the previous real token.
+fn is_unary(tok: &Token, prev: Option<(&Token, bool)>) -> bool {
+    match *tok {
+        Token::Bang | Token::Tilde => true,
+        Token::Plus | Token::Minus | Token::PlusPlus | Token::MinusMinus => {
+            !ends_operand(prev)
+        },
+        _ => false,
+    }
+}
+
+/// Whether the token ends an operand (like an identifier or a closing
+/// parenthesis). Binary operators can only follow such tokens.
+fn ends_operand(tok: Option<(&Token, bool)>) -> bool {
+    match tok {
+        Some((&Token::Ident(_), _))
+            | Some((&Token::Literal(_), _))
+            | Some((&Token::ParenCl, _))
+            | Some((&Token::BracketCl, _))
+            | Some((&Token::KeyW(Keyword::This), _))
+            | Some((&Token::KeyW(Keyword::Super), _)) => true,
+        // Postfix increments and decrements
+        Some((&Token::PlusPlus, unary)) | Some((&Token::MinusMinus, unary)) => !unary,
+        _ => false,
+    }
+}
+
+/// Whether there has to be a space between two tokens in the same line.
+fn needs_space(prev: &Token, prev_unary: bool, tok: &Token, unary: bool, label: bool)
+    -> bool
+{
+    match *tok {
+        Token::Comma | Token::Semi | Token::ParenCl | Token::BracketOp | Token::BracketCl
+            | Token::Dot | Token::DotDotDot | Token::ColonSep => return false,
+        Token::Colon if label => return false,
+        Token::PlusPlus | Token::MinusMinus if !unary => return false,
+        Token::ParenOp => match *prev {
+            Token::Ident(_)
+                | Token::KeyW(Keyword::This)
+                | Token::KeyW(Keyword::Super)
+                | Token::ParenOp => return false,
+            _ => return true,
+        },
+        _ => {},
+    }
+
+    match *prev {
+        Token::ParenOp | Token::BracketOp | Token::Dot | Token::At | Token::ColonSep => false,
+        Token::Bang | Token::Tilde => false,
+        Token::Plus | Token::Minus | Token::PlusPlus | Token::MinusMinus => !prev_unary,
+        _ => true,
+    }
+}
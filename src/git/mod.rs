@@ -0,0 +1,174 @@
+//! Changes in a git repository, for `--changed-since <rev>`.
+//!
+//! The changed lines are read from the output of `git diff` against the
+//! given revision, which includes uncommitted changes. Untracked files count
+//! as changed entirely.
+
+use analyze::Finding;
+use base::code::{FileMap, Span};
+use base::diag::Report;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const GIT_NAME: &'static str = "git";
+
+/// The changed files and lines since a revision
+#[derive(Clone, Debug)]
+pub struct Changes {
+    /// Changed lines (1-based, end exclusive) of every changed file, by
+    /// canonical path. `None` if the whole file is new.
+    files: HashMap<PathBuf, Option<Vec<(usize, usize)>>>,
+}
+
+impl Changes {
+    /// Asks `git` for all changes since `rev` in the current directory.
+    pub fn since(rev: &str) -> io::Result<Changes> {
+        let mut changes = Changes {
+            files: HashMap::new(),
+        };
+
+        let diff = try!(git(&[
+            "diff",
+            "--relative",
+            "--unified=0",
+            "--no-color",
+            "--no-ext-diff",
+            "--src-prefix=a/",
+            "--dst-prefix=b/",
+            rev,
+            "--",
+        ]));
+        let mut current = None;
+        for line in diff.lines() {
+            if line.starts_with("+++ ") {
+                // Deleted files have no new version
+                current = match line[4..].trim_right() {
+                    "/dev/null" => None,
+                    path => canonical(&path[2..]),
+                };
+                if let Some(ref path) = current {
+                    changes.files.insert(path.clone(), Some(Vec::new()));
+                }
+            } else if line.starts_with("@@ ") {
+                if let (Some(path), Some(lines)) = (current.as_ref(), hunk_lines(line)) {
+                    if let Some(&mut Some(ref mut ranges)) = changes.files.get_mut(path) {
+                        ranges.push(lines);
+                    }
+                }
+            }
+        }
+
+        let untracked = try!(git(&["ls-files", "--others", "--exclude-standard"]));
+        for path in untracked.lines().filter_map(canonical) {
+            changes.files.insert(path, None);
+        }
+        Ok(changes)
+    }
+
+    pub fn contains_file(&self, path: &Path) -> bool {
+        canonical(path).map(|p| self.files.contains_key(&p)).unwrap_or(false)
+    }
+
+    /// Removes all findings which don't start in a changed line. Findings
+    /// without a span are kept.
+    pub fn retain_changed(&self, file_map: &FileMap, findings: &mut Vec<Finding>) {
+        findings.retain(|finding| self.is_changed(file_map, finding.report.span));
+    }
+
+    /// Like `retain_changed`, but for reports which aren't findings, e.g.
+    /// errors of the parser.
+    pub fn retain_changed_reports(&self, file_map: &FileMap, reports: &mut Vec<Report>) {
+        reports.retain(|report| self.is_changed(file_map, report.span));
+    }
+
+    /// Whether the span starts in a changed line of the file. Without a
+    /// span, only the file has to be changed.
+    fn is_changed(&self, file_map: &FileMap, span: Option<Span>) -> bool {
+        let ranges = match canonical(file_map.filename()).and_then(|p| self.files.get(&p)) {
+            Some(&Some(ref ranges)) => ranges,
+            Some(&None) => return true,
+            None => return false,
+        };
+
+        match span {
+            Some(span) => {
+                let src = file_map.src();
+                let pos = ::std::cmp::min(span.lo.0 as usize, src.len());
+                let line = src[..pos].matches('\n').count() + 1;
+                ranges.iter().any(|&(lo, hi)| lo <= line && line < hi)
+            },
+            None => true,
+        }
+    }
+}
+
+/// Runs `git` with the given arguments and returns its stdout.
+fn git(args: &[&str]) -> io::Result<String> {
+    // Paths with special characters are quoted otherwise
+    let out = try!(Command::new(GIT_NAME)
+        .args(&["-c", "core.quotePath=false"])
+        .args(args)
+        .output());
+    if !out.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            String::from_utf8_lossy(&out.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+/// Parses the new line range of a hunk header like `@@ -3,2 +4,5 @@`.
+/// Returns `None` if lines were only removed.
+fn hunk_lines(header: &str) -> Option<(usize, usize)> {
+    let new = match header.split_whitespace().nth(2) {
+        Some(s) if s.starts_with('+') => &s[1..],
+        _ => return None,
+    };
+    let mut parts = new.splitn(2, ',');
+    let start = parts.next().and_then(|s| s.parse::<usize>().ok());
+    let count = match parts.next() {
+        Some(s) => s.parse::<usize>().ok(),
+        None => Some(1),
+    };
+    match (start, count) {
+        (Some(start), Some(count)) if count > 0 => Some((start, start + count)),
+        _ => None,
+    }
+}
+
+fn canonical<P: AsRef<Path>>(path: P) -> Option<PathBuf> {
+    fs::canonicalize(path).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::hunk_lines;
+
+    #[test]
+    fn hunk_with_count() {
+        assert_eq!(hunk_lines("@@ -3,2 +4,5 @@"), Some((4, 9)));
+        assert_eq!(hunk_lines("@@ -3,2 +4,5 @@ class Foo {"), Some((4, 9)));
+    }
+
+    #[test]
+    fn hunk_without_count() {
+        assert_eq!(hunk_lines("@@ -3 +4 @@"), Some((4, 5)));
+        assert_eq!(hunk_lines("@@ -3,0 +4 @@"), Some((4, 5)));
+    }
+
+    #[test]
+    fn pure_deletion() {
+        assert_eq!(hunk_lines("@@ -3,2 +2,0 @@"), None);
+        assert_eq!(hunk_lines("@@ -1 +0,0 @@"), None);
+    }
+
+    #[test]
+    fn invalid_header() {
+        assert_eq!(hunk_lines("@@ -3,2 @@"), None);
+        assert_eq!(hunk_lines("@@ -3,2 +x,1 @@"), None);
+    }
+}
@@ -0,0 +1,358 @@
+//! Structural search in Java sources (`jswag grep`).
+//!
+//! There are two kinds of queries:
+//!
+//! - A code pattern like `catch ($T $e) {}`, which is matched against the
+//!   tokens of the files, so whitespace and comments don't matter.
+//!   `$name` matches one or more tokens with balanced brackets, but no `;`
+//!   outside of brackets. If the same name appears multiple times, all of
+//!   them have to match the same code. `$_` matches anything without
+//!   binding and `...` matches any balanced code, even none at all.
+//! - The kind of a node and a pattern for its name (`--kind method --name
+//!   'get*'`), which are matched like custom rules do (see
+//!   `analyze::custom::pattern`). This requires parsing the files.
+
+use analyze::{self, custom};
+use analyze::custom::pattern::{self, PatternRule};
+use base::code::{FileMap, Span};
+use check;
+use job::Job;
+use std::collections::HashMap;
+use syntax;
+use syntax::lex::{Token, TokenSpan};
+use term_painter::Color::*;
+use term_painter::ToStyle;
+
+/// One element of a code pattern
+#[derive(Debug)]
+enum Piece {
+    /// A token which has to appear literally
+    Token(String),
+    /// `$name`
+    Var(String),
+    /// `...`
+    Ellipsis,
+}
+
+/// Searches all files of the job and prints the matches. Either `code` or
+/// `kind` and `name` are given.
+pub fn search_all(job: &Job, code: Option<&str>, kind: Option<&str>, name: Option<&str>)
+    -> Result<(), ()>
+{
+    let pieces = match code {
+        Some(code) => match compile(code) {
+            Some(pieces) => pieces,
+            None => {
+                msg!(Error, "Invalid code pattern '{}'", code);
+                return Err(());
+            },
+        },
+        None => Vec::new(),
+    };
+    let kinds = match kind {
+        Some(kind) => vec![kind],
+        None => pattern::KINDS.to_vec(),
+    };
+    let mut rules = Vec::new();
+    for kind in kinds {
+        match PatternRule::matcher(kind, name.unwrap_or("*")) {
+            Some(rule) => rules.push(rule),
+            None => {
+                msg!(Error, "Invalid kind '{}'. Valid kinds: {}", kind, pattern::KINDS.join(", "));
+                return Err(());
+            },
+        }
+    }
+
+    let mut count = 0;
+    for file in &job.files {
+        let src = match check::read_file(job, file) {
+            Ok(src) => src,
+            Err(_) => {
+                msg!(Ignoring, "'{}' (can't be read)", file.display());
+                continue;
+            },
+        };
+        let file_map = FileMap::new(file.to_string_lossy().into_owned(), src);
+
+        let mut spans = if code.is_some() {
+            search_code(&pieces, &file_map)
+        } else {
+            let ast = match syntax::parse_compilation_unit(&file_map) {
+                (Some(ast), _) => ast,
+                (None, _) => {
+                    msg!(Ignoring, "'{}' (can't be parsed)", file.display());
+                    continue;
+                },
+            };
+            let rules: Vec<_> = rules.iter().map(|r| r as &custom::Rule).collect();
+            custom::check(&rules, &file_map, &ast).into_iter()
+                .filter_map(|finding| finding.report.span)
+                .collect()
+        };
+        spans.sort_by_key(|span| span.lo);
+
+        for span in &spans {
+            print_match(&file_map, *span);
+        }
+        count += spans.len();
+    }
+
+    msg!(Note, "{} match(es)", count);
+    Ok(())
+}
+
+/// Converts the code pattern into pieces. Returns `None` if it can't be
+/// tokenized or is empty.
+fn compile(code: &str) -> Option<Vec<Piece>> {
+    let file_map = FileMap::new("<pattern>", code);
+    let tokens = analyze::tokenize(&file_map);
+    let lexed = tokens.last().map(|ts| ts.span.hi.0 as usize).unwrap_or(0);
+    if lexed < code.trim_right().len() {
+        return None;
+    }
+
+    let pieces: Vec<_> = tokens.iter()
+        .filter(|ts| ts.tok.is_real())
+        .map(|ts| {
+            let text = &code[ts.span.into_range()];
+            match ts.tok {
+                Token::Ident(_) if text.starts_with('$') && text.len() > 1 => {
+                    Piece::Var(text[1..].to_string())
+                },
+                Token::DotDotDot => Piece::Ellipsis,
+                _ => Piece::Token(text.to_string()),
+            }
+        })
+        .collect();
+
+    if pieces.is_empty() {
+        None
+    } else {
+        Some(pieces)
+    }
+}
+
+/// Returns the spans of all non-overlapping matches of the code pattern.
+fn search_code(pieces: &[Piece], file_map: &FileMap) -> Vec<Span> {
+    let src = file_map.src();
+    let tokens: Vec<_> = analyze::tokenize(file_map)
+        .into_iter()
+        .filter(|ts| ts.tok.is_real())
+        .collect();
+    let matcher = Matcher {
+        src: src,
+        tokens: &tokens,
+    };
+
+    let mut spans = Vec::new();
+    let mut pos = 0;
+    while pos < tokens.len() {
+        match matcher.match_at(pieces, pos, &mut HashMap::new()) {
+            Some(end) if end > pos => {
+                spans.push(Span::new(tokens[pos].span.lo, tokens[end - 1].span.hi));
+                pos = end;
+            },
+            _ => pos += 1,
+        }
+    }
+    spans
+}
+
+struct Matcher<'a> {
+    src: &'a str,
+    tokens: &'a [TokenSpan],
+}
+
+impl<'a> Matcher<'a> {
+    fn text(&self, idx: usize) -> &'a str {
+        &self.src[self.tokens[idx].span.into_range()]
+    }
+
+    /// Matches the pieces against the tokens starting at `pos` and returns
+    /// the index after the last matched token. Variables are bound to token
+    /// ranges in `binds`; shorter bindings are tried first.
+    fn match_at<'p>(
+        &self,
+        pieces: &'p [Piece],
+        pos: usize,
+        binds: &mut HashMap<&'p str, (usize, usize)>,
+    ) -> Option<usize> {
+        let (first, rest) = match pieces.split_first() {
+            Some(split) => split,
+            None => return Some(pos),
+        };
+
+        match *first {
+            Piece::Token(ref text) => {
+                if pos < self.tokens.len() && self.text(pos) == text {
+                    self.match_at(rest, pos + 1, binds)
+                } else {
+                    None
+                }
+            },
+            Piece::Var(ref name) if binds.contains_key(&name[..]) => {
+                let (lo, hi) = binds[&name[..]];
+                let len = hi - lo;
+                if pos + len > self.tokens.len() {
+                    return None;
+                }
+                if (0..len).all(|i| self.text(lo + i) == self.text(pos + i)) {
+                    self.match_at(rest, pos + len, binds)
+                } else {
+                    None
+                }
+            },
+            Piece::Var(ref name) => {
+                for end in self.balanced_ends(pos, false) {
+                    if end == pos {
+                        continue;
+                    }
+                    if name != "_" {
+                        binds.insert(name, (pos, end));
+                    }
+                    if let Some(end) = self.match_at(rest, end, binds) {
+                        return Some(end);
+                    }
+                    binds.remove(&name[..]);
+                }
+                None
+            },
+            Piece::Ellipsis => {
+                self.balanced_ends(pos, true)
+                    .into_iter()
+                    .filter_map(|end| self.match_at(rest, end, binds))
+                    .next()
+            },
+        }
+    }
+
+    /// All indices `end` (starting with `pos` itself) for which the tokens
+    /// from `pos` to `end` have balanced brackets. Unless `semicolons` is
+    /// set, they don't contain a `;` outside of brackets.
+    fn balanced_ends(&self, pos: usize, semicolons: bool) -> Vec<usize> {
+        let mut ends = vec![pos];
+        let mut depth = 0;
+        for idx in pos..self.tokens.len() {
+            match self.tokens[idx].tok {
+                Token::ParenOp | Token::BracketOp | Token::BraceOp => depth += 1,
+                Token::ParenCl | Token::BracketCl | Token::BraceCl => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                },
+                Token::Semi if depth == 0 && !semicolons => break,
+                _ => {},
+            }
+            if depth == 0 {
+                ends.push(idx + 1);
+            }
+        }
+        ends
+    }
+}
+
+/// Prints the location and the code of a match, e.g.:
+///
+/// ```text
+/// Foo.java:12:9
+///   12 |         } catch (Exception e) {}
+///      |           ^^^^^^^^^^^^^^^^^^^^^^
+/// ```
+///
+/// Matches spanning multiple lines are printed without markers.
+fn print_match(file_map: &FileMap, span: Span) {
+    let src = file_map.src();
+    let (lo, hi) = (span.lo.0 as usize, span.hi.0 as usize);
+    let line_start = src[..lo].rfind('\n').map(|p| p + 1).unwrap_or(0);
+    let first_line = src[..lo].matches('\n').count() + 1;
+    let col = src[line_start..lo].chars().count() + 1;
+    println!("{}", Magenta.bold().paint(format!("{}:{}:{}", file_map.filename(), first_line, col)));
+
+    let line_end = src[hi..].find('\n').map(|p| hi + p).unwrap_or(src.len());
+    let lines: Vec<_> = src[line_start..line_end].lines().collect();
+    for (i, line) in lines.iter().enumerate() {
+        println!(
+            "{:>4} {} {}",
+            Magenta.bold().paint(first_line + i),
+            Magenta.bold().paint("|"),
+            line.replace('\t', "    "),
+        );
+    }
+
+    if lines.len() == 1 {
+        let before = src[line_start..lo].replace('\t', "    ").chars().count();
+        let len = ::std::cmp::max(1, src[lo..hi].replace('\t', "    ").chars().count());
+        println!(
+            "     {} {:2$}{3}",
+            Magenta.bold().paint("|"),
+            "",
+            before,
+            Yellow.paint(::std::iter::repeat('^').take(len).collect::<String>()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use base::code::FileMap;
+    use super::*;
+
+    /// The code of all matches of the pattern in `src`
+    fn matches(pattern: &str, src: &str) -> Vec<String> {
+        let pieces = compile(pattern).expect("pattern doesn't compile");
+        let file_map = FileMap::new("Test.java", src);
+        search_code(&pieces, &file_map)
+            .into_iter()
+            .map(|span| src[span.into_range()].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn compile_pieces() {
+        let pieces = compile("catch ($T $_) { ... }").unwrap();
+        let kinds: Vec<_> = pieces.iter().map(|p| match *p {
+            Piece::Token(ref t) => format!("token {}", t),
+            Piece::Var(ref v) => format!("var {}", v),
+            Piece::Ellipsis => "ellipsis".to_string(),
+        }).collect();
+        assert_eq!(kinds, vec![
+            "token catch", "token (", "var T", "var _", "token )",
+            "token {", "ellipsis", "token }",
+        ]);
+
+        assert!(compile("").is_none());
+        assert!(compile("  ").is_none());
+    }
+
+    #[test]
+    fn variables_match_the_same_code_again() {
+        let src = "x = x + 1; y = x + 1; a.b = a.b + 1;";
+        assert_eq!(matches("$v = $v + 1;", src), vec!["x = x + 1;", "a.b = a.b + 1;"]);
+    }
+
+    #[test]
+    fn underscore_does_not_bind() {
+        let src = "x = x + 1; y = x + 1;";
+        assert_eq!(matches("$_ = $_ + 1;", src), vec!["x = x + 1;", "y = x + 1;"]);
+    }
+
+    #[test]
+    fn variables_stop_at_semicolons() {
+        let src = "a(); b(); c(x, y);";
+        assert_eq!(matches("$f(x, $y)", src), vec!["c(x, y)"]);
+    }
+
+    #[test]
+    fn ellipsis_matches_balanced_code() {
+        let src = "f(); f(a, g(b)); g(c);";
+        assert_eq!(matches("f(...)", src), vec!["f()", "f(a, g(b))"]);
+
+        let src = "catch (E e) { a(); b(); } catch (F f) {}";
+        assert_eq!(
+            matches("catch ($T $e) { ... }", src),
+            vec!["catch (E e) { a(); b(); }", "catch (F f) {}"],
+        );
+    }
+}
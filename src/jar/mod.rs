@@ -0,0 +1,53 @@
+//! Packaging of the files into an executable JAR (`jswag jar`).
+//!
+//! The files are compiled into a temporary directory, so no class files are
+//! left next to the sources. The `Main-Class` of the manifest is the class
+//! `jswag run` runs: the one given by `--main-class` or else the first one
+//! with a `main` method.
+
+use java::{self, Outcome};
+use job::Job;
+use std::path::{Path, PathBuf};
+
+/// Compiles the files of the job and packages them together with the files
+/// in `resources` into `jar_file`. Without `jar_file`, the JAR is named
+/// after the main class.
+pub fn package(
+    job: &Job,
+    jar_file: Option<&Path>,
+    resources: Option<&Path>,
+    outcomes: &mut Vec<Outcome>,
+) -> Result<(), ()> {
+    let main = match java::find_main_class(job) {
+        Some(main) => main,
+        None => {
+            msg!(
+                Error,
+                "None of the files contains a class with a \
+                    `public static void main(String[] args)` method"
+            );
+            msg!(Note, "Give the main class with `--main-class`");
+            return Err(());
+        },
+    };
+    if let Some(dir) = resources {
+        if !dir.is_dir() {
+            msg!(Error, "The resource directory '{}' doesn't exist", dir.display());
+            return Err(());
+        }
+    }
+
+    let jar_file = match jar_file {
+        Some(file) => file.to_path_buf(),
+        None => {
+            let simple_name = main.name.rsplit('.').next().unwrap_or(&main.name);
+            PathBuf::from(format!("{}.jar", simple_name))
+        },
+    };
+
+    java::with_temp_dir("jar", |tmp| {
+        try!(java::compile_into(job, tmp, outcomes));
+        msg!(Writing, "'{}' with main class `{}`", jar_file.display(), main.name);
+        java::jar(&jar_file, &main.name, tmp, resources, job, outcomes)
+    })
+}
@@ -1,52 +1,236 @@
-use std::process::{Command, ExitStatus};
-use std::path::Path;
-use std::io;
-use super::{JAVAC_NAME, JAVA_NAME};
+use std::process::{Command, ExitStatus, Stdio};
+use std::path::{Path, PathBuf};
+use std::io::{self, Read, Write};
+use std::thread::{self, JoinHandle};
+use super::{JAVAC_NAME, JAVA_NAME, JAR_NAME, Outcome, binary, parent_dir};
 use job::Job;
 
 
 /// Calls `javac` with the given file
-pub fn compile(file: &Path, job: &Job) -> Result<(), Error> {
+pub fn compile(file: &Path, job: &Job, outcomes: &mut Vec<Outcome>)
+    -> Result<(), Error>
+{
+    let javac = binary(job, JAVAC_NAME);
+    let mut cmd = Command::new(&javac);
+    let mut desc = javac.display().to_string();
+    for arg in version_args(job) {
+        desc.push_str(&format!(" {}", arg));
+        cmd.arg(arg);
+    }
+    cmd.arg(file);
+    desc.push_str(&format!(" {}", file.display()));
+
     // Print what we are about to do
     if job.verbose {
-        msg!(Running, "`{} {}`", JAVAC_NAME, file.display());
+        msg!(Running, "`{}`", desc);
     }
+    execute(cmd, desc, job, outcomes)
+}
 
-    // Spawn new child process
-    let child = Command::new(JAVAC_NAME)
-                        .arg(file)
-                        .spawn();
-    let mut child = try!(child);
+/// Calls `javac` with the given file without printing anything, writing the
+/// class files into `out_dir`. Other classes are searched in `source_path`.
+/// Returns whether `javac` succeeded and everything it wrote to stdout and
+/// stderr.
+pub fn compile_quietly(file: &Path, source_path: &Path, out_dir: &Path, job: &Job)
+    -> Result<(bool, String), Error>
+{
+    let javac = binary(job, JAVAC_NAME);
+    let mut cmd = Command::new(&javac);
+    cmd.args(&version_args(job));
 
-    let status = try!(child.wait());
+    // Messages in English, so that callers can look at them
+    let out = try!(cmd
+        .arg("-J-Duser.language=en")
+        .arg("-nowarn")
+        .arg("-implicit:none")
+        .arg("-d")
+        .arg(out_dir)
+        .arg("-sourcepath")
+        .arg(source_path)
+        .arg(file)
+        .stdin(Stdio::null())
+        .output());
 
-    // Stop processing, if javac failed to compile.
-    if !status.success() {
-        return Err(Error::JavacFailure(status));
+    let mut output = String::from_utf8_lossy(&out.stdout).into_owned();
+    output.push_str(&String::from_utf8_lossy(&out.stderr));
+    Ok((out.status.success(), output))
+}
+
+/// Calls `javac` with all given files at once, writing the class files into
+/// `out_dir`. Other classes the files use are searched in `source_path`.
+pub fn compile_into(
+    files: &[PathBuf],
+    source_path: Option<&Path>,
+    out_dir: &Path,
+    job: &Job,
+    outcomes: &mut Vec<Outcome>,
+) -> Result<(), Error> {
+    let javac = binary(job, JAVAC_NAME);
+    let mut cmd = Command::new(&javac);
+    let mut desc = javac.display().to_string();
+    for arg in version_args(job) {
+        desc.push_str(&format!(" {}", arg));
+        cmd.arg(arg);
+    }
+    if let Some(path) = source_path {
+        cmd.arg("-sourcepath").arg(path);
+        desc.push_str(&format!(" -sourcepath {}", path.display()));
+    }
+    cmd.arg("-d").arg(out_dir).args(files);
+    desc.push_str(&format!(" -d {}", out_dir.display()));
+    for file in files {
+        desc.push_str(&format!(" {}", file.display()));
     }
 
-    Ok(())
+    // Print what we are about to do
+    if job.verbose {
+        msg!(Running, "`{}`", desc);
+    }
+    execute(cmd, desc, job, outcomes)
+}
+
+/// Calls `jar` to create an executable JAR with the class files in
+/// `classes` and the files in `resources`. The short options are used,
+/// since the long ones only exist since Java 9.
+pub fn jar(
+    jar_file: &Path,
+    main_class: &str,
+    classes: &Path,
+    resources: Option<&Path>,
+    job: &Job,
+    outcomes: &mut Vec<Outcome>,
+) -> Result<(), Error> {
+    let jar = binary(job, JAR_NAME);
+    let mut cmd = Command::new(&jar);
+    cmd.arg("cfe").arg(jar_file).arg(main_class).arg("-C").arg(classes).arg(".");
+    let mut desc = format!(
+        "{} cfe {} {} -C {} .",
+        jar.display(),
+        jar_file.display(),
+        main_class,
+        classes.display()
+    );
+    if let Some(dir) = resources {
+        cmd.arg("-C").arg(dir).arg(".");
+        desc.push_str(&format!(" -C {} .", dir.display()));
+    }
+
+    // Print what we are about to do
+    if job.verbose {
+        msg!(Running, "`{}`", desc);
+    }
+    execute(cmd, desc, job, outcomes)
 }
 
 /// Calls `java` with the given file
-pub fn run<P: AsRef<Path>>(class: &str, path: P, job: &Job)
+pub fn run<P: AsRef<Path>>(class: &str, path: P, job: &Job, outcomes: &mut Vec<Outcome>)
     -> Result<(), Error>
 {
+    let java = binary(job, JAVA_NAME);
+    let desc = format!("{} {}", java.display(), class);
+
     // Print what we are about to do
     if job.verbose {
-        msg!(Running, "`{} {}`", JAVA_NAME, class);
+        msg!(Running, "`{}`", desc);
     }
 
-    // Spawn new child process
-    let child = Command::new(JAVA_NAME)
-                        .arg(class)
-                        .current_dir(path)
-                        .spawn();
-    let mut child = try!(child);
+    let mut cmd = Command::new(&java);
+    cmd.arg(class).current_dir(path);
+    execute(cmd, desc, job, outcomes)
+}
 
-    let status = try!(child.wait());
+/// Calls `java` with the given class, whose class files are in
+/// `class_path`, in the directory `dir`
+pub fn run_in(class: &str, class_path: &Path, dir: &Path, job: &Job, outcomes: &mut Vec<Outcome>)
+    -> Result<(), Error>
+{
+    let java = binary(job, JAVA_NAME);
+    let desc = format!("{} -cp {} {}", java.display(), class_path.display(), class);
 
-    // Stop processing, if javac failed to compile.
+    // Print what we are about to do
+    if job.verbose {
+        msg!(Running, "`{}`", desc);
+    }
+
+    let mut cmd = Command::new(&java);
+    cmd.arg("-cp").arg(class_path).arg(class).current_dir(dir);
+    execute(cmd, desc, job, outcomes)
+}
+
+/// Calls `java` with the given source file, which is compiled in memory
+/// (source-file mode, Java 11+). Like `run`, it runs in the directory of the
+/// file.
+pub fn run_source(file: &Path, job: &Job, outcomes: &mut Vec<Outcome>)
+    -> Result<(), Error>
+{
+    let java = binary(job, JAVA_NAME);
+    let mut cmd = Command::new(&java);
+    let mut desc = java.display().to_string();
+    if let Some(version) = job.config.java_version {
+        cmd.arg("--source").arg(version.to_string());
+        desc.push_str(&format!(" --source {}", version));
+    }
+    let name = file.file_name().map(Path::new).unwrap_or(file);
+    cmd.arg(name).current_dir(parent_dir(file));
+    desc.push_str(&format!(" {}", name.display()));
+
+    // Print what we are about to do
+    if job.verbose {
+        msg!(Running, "`{}`", desc);
+    }
+    execute(cmd, desc, job, outcomes)
+}
+
+/// The options of `javac` selecting the Java version of the code, if one is
+/// set. `--release` only exists since JDK 9, so older ones get `-source` and
+/// `-target` instead.
+fn version_args(job: &Job) -> Vec<String> {
+    let version = match job.config.java_version {
+        Some(version) => version.to_string(),
+        None => return vec![],
+    };
+    match job.jdk_major {
+        Some(major) if major < 9 => {
+            vec!["-source".into(), version.clone(), "-target".into(), version]
+        },
+        _ => vec!["--release".into(), version],
+    }
+}
+
+/// Spawns the command and waits for it to exit.
+///
+/// If a report is written, the output is captured to show it in the report
+/// (stdout first, then stderr). It's still printed while the command runs,
+/// so that interactive programs show their prompts.
+fn execute(mut cmd: Command, desc: String, job: &Job, outcomes: &mut Vec<Outcome>)
+    -> Result<(), Error>
+{
+    let status = if job.report.is_some() {
+        let mut child = try!(cmd
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn());
+        let stdout = tee(child.stdout.take(), io::stdout());
+        let stderr = tee(child.stderr.take(), io::stderr());
+        let status = try!(child.wait());
+        let stdout = try!(join(stdout));
+        let stderr = try!(join(stderr));
+
+        let mut output = String::from_utf8_lossy(&stdout).into_owned();
+        output.push_str(&String::from_utf8_lossy(&stderr));
+        outcomes.push(Outcome {
+            command: desc,
+            status: status,
+            output: output,
+        });
+        status
+    } else {
+        let mut child = try!(cmd.spawn());
+        try!(child.wait())
+    };
+
+    // Stop processing, if the command failed.
     if !status.success() {
         return Err(Error::JavacFailure(status));
     }
@@ -54,6 +238,37 @@ pub fn run<P: AsRef<Path>>(class: &str, path: P, job: &Job)
     Ok(())
 }
 
+/// Copies everything from `from` to `to` in a new thread and returns it at
+/// the end. Every chunk is written as soon as it was read, even without a
+/// line break.
+fn tee<R, W>(from: Option<R>, mut to: W) -> JoinHandle<io::Result<Vec<u8>>>
+    where R: Read + Send + 'static,
+          W: Write + Send + 'static
+{
+    thread::spawn(move || {
+        let mut out = Vec::new();
+        if let Some(mut from) = from {
+            let mut buf = [0; 4096];
+            loop {
+                let len = try!(from.read(&mut buf));
+                if len == 0 {
+                    break;
+                }
+                try!(to.write_all(&buf[..len]));
+                try!(to.flush());
+                out.extend_from_slice(&buf[..len]);
+            }
+        }
+        Ok(out)
+    })
+}
+
+fn join(handle: JoinHandle<io::Result<Vec<u8>>>) -> io::Result<Vec<u8>> {
+    handle.join().unwrap_or_else(|_| {
+        Err(io::Error::new(io::ErrorKind::Other, "copying the output failed"))
+    })
+}
+
 #[derive(Debug)]
 pub enum Error {
     JavaBinaryNotFound,
@@ -4,87 +4,422 @@
 /// module contains functions for pretty printing the output of `inner`.
 
 mod inner;
+use analyze;
+use base::code::FileMap;
+use check;
+use std::cmp;
 use job::Job;
-use std::path::Path;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitStatus};
 use self::inner::Error;
+use syntax;
+use syntax::ast::{ClassMember, CompilationUnit, Method, TypeDef, Visibility};
+use syntax::lex::{Keyword, Token};
 
-const JAVAC_NAME: &'static str = "javac";
-const JAVA_NAME: &'static str = "java";
+pub const JAVAC_NAME: &'static str = "javac";
+pub const JAVA_NAME: &'static str = "java";
+pub const JAR_NAME: &'static str = "jar";
 
-/// Calls `javac` with the given files
-pub fn compile_all(job: &Job) -> Result<(), ()> {
+/// The binary `name` (`javac` or `java`) of the JDK selected with `--jdk`
+/// or the one in PATH.
+fn binary(job: &Job, name: &str) -> PathBuf {
+    match job.jdk {
+        Some(ref jdk) => jdk.bin(name),
+        None => PathBuf::from(name),
+    }
+}
+
+/// One call of `javac` or `java`, recorded for the report of the run
+pub struct Outcome {
+    /// The command line, e.g. `javac Foo.java`
+    pub command: String,
+    pub status: ExitStatus,
+    /// Everything the command wrote to stdout and stderr
+    pub output: String,
+}
+
+/// Calls `javac` with the given files. If the job writes a report, the
+/// calls are recorded in `outcomes`; the same goes for `run_first_main`.
+pub fn compile_all(job: &Job, outcomes: &mut Vec<Outcome>) -> Result<(), ()> {
     for file in &job.files {
-        try!(compile(file, job));
+        try!(compile(file, job, outcomes));
     }
     Ok(())
 }
-pub fn compile(file: &Path, job: &Job) -> Result<(), ()> {
+pub fn compile(file: &Path, job: &Job, outcomes: &mut Vec<Outcome>) -> Result<(), ()> {
     msg!(Compiling, "'{}'", file.display());
-    inner::compile(file, job).map_err(|e| {
-        match e {
-            Error::JavaBinaryNotFound => {
-                msg!(
-                    Error,
-                    "`{1}` was not found on the system. Make sure that `{1}` \
-                        is installed and in your PATH. Aborting.",
-                    JAVAC_NAME
-                );
-            },
-            Error::JavacFailure(status) => {
-                msg!(
-                    Error,
-                    "`{}` exited with a non-success status ({}). Aborting.",
-                    JAVAC_NAME,
-                    status
-                );
-            },
-            Error::Io(e) => {
-                msg!(
-                    Error,
-                    "an IO error occured while executing `{}`: {}. Aborting.",
-                    JAVAC_NAME,
-                    e
-                );
+    inner::compile(file, job, outcomes).map_err(|e| print_error(e, JAVAC_NAME))
+}
+
+/// Calls `javac` with the given file to find out whether it accepts it,
+/// without printing its output. See `inner::compile_quietly`.
+pub fn compile_quietly(file: &Path, source_path: &Path, out_dir: &Path, job: &Job)
+    -> Result<(bool, String), ()>
+{
+    inner::compile_quietly(file, source_path, out_dir, job)
+        .map_err(|e| print_error(e, JAVAC_NAME))
+}
+
+/// The directory containing the directories of the packages, for the
+/// package the file declares. `javac` searches other classes in it.
+pub fn source_root(job: &Job, file: &Path) -> PathBuf {
+    let package = match check::read_file(job, file) {
+        Ok(src) => {
+            let file_map = FileMap::new(file.to_string_lossy().into_owned(), src);
+            package_in(&real_tokens(&file_map))
+        },
+        Err(_) => vec![],
+    };
+    package_root(file, package.len())
+}
+
+/// Calls `javac` with all files at once, writing the class files into
+/// `out_dir`.
+pub fn compile_into(job: &Job, out_dir: &Path, outcomes: &mut Vec<Outcome>) -> Result<(), ()> {
+    msg!(Compiling, "{} file(s) into '{}'", job.files.len(), out_dir.display());
+    inner::compile_into(&job.files, None, out_dir, job, outcomes)
+        .map_err(|e| print_error(e, JAVAC_NAME))
+}
+
+/// Calls `jar` to package the class files in `classes` and everything in
+/// `resources` into an executable JAR.
+pub fn jar(
+    jar_file: &Path,
+    main_class: &str,
+    classes: &Path,
+    resources: Option<&Path>,
+    job: &Job,
+    outcomes: &mut Vec<Outcome>,
+) -> Result<(), ()> {
+    inner::jar(jar_file, main_class, classes, resources, job, outcomes)
+        .map_err(|e| print_error(e, JAR_NAME))
+}
+
+/// Runs the class of the files containing a `main` method. If none is
+/// found, the class named like the first file is run.
+pub fn run_first_main(job: &Job, outcomes: &mut Vec<Outcome>) -> Result<(), ()> {
+    let main = main_class_or_first(job);
+    inner::run(&main.name, &main.root, job, outcomes).map_err(|e| print_error(e, JAVA_NAME))
+}
+
+/// Runs the first file without leaving class files behind. Since Java 11,
+/// `java` compiles a single source file in memory, as long as it doesn't
+/// use other source files. Otherwise the file is compiled into a temporary
+/// directory, together with the classes it uses, and run from there.
+pub fn run_source(job: &Job, outcomes: &mut Vec<Outcome>) -> Result<(), ()> {
+    let file = &job.files[0];
+    let source_mode = job.jdk_major.map(|major| major >= 11).unwrap_or(false);
+    if source_mode && !uses_other_sources(job, file) {
+        return inner::run_source(file, job, outcomes).map_err(|e| print_error(e, JAVA_NAME));
+    }
+
+    if job.verbose {
+        msg!(Debug, "Can't run '{}' as source file, compiling it first", file.display());
+    }
+    let main = main_class_or_first(job);
+    let source_path = source_root(job, file);
+    with_temp_dir("run", |tmp| {
+        msg!(Compiling, "'{}' into '{}'", file.display(), tmp.display());
+        try!(inner::compile_into(&job.files, Some(&source_path), tmp, job, outcomes)
+            .map_err(|e| print_error(e, JAVAC_NAME)));
+        inner::run_in(&main.name, tmp, parent_dir(file), job, outcomes)
+            .map_err(|e| print_error(e, JAVA_NAME))
+    })
+}
+
+/// Whether the file uses classes of other source files, which `java`
+/// doesn't compile in source-file mode: classes of files in the same
+/// directory or imports outside of the JDK.
+fn uses_other_sources(job: &Job, file: &Path) -> bool {
+    let tokens = match check::read_file(job, file) {
+        Ok(src) => real_tokens(&FileMap::new(file.to_string_lossy().into_owned(), src)),
+        Err(_) => return false,
+    };
+
+    let imports_other = tokens.iter().enumerate().any(|(i, tok)| {
+        let static_ = Token::KeyW(Keyword::Static);
+        *tok == Token::KeyW(Keyword::Import) &&
+            match tokens[i + 1..].iter().find(|t| **t != static_) {
+                Some(&Token::Ident(ref first)) => first != "java" && first != "javax",
+                _ => false,
             }
-        };
+    });
+    let siblings: Vec<String> = match fs::read_dir(parent_dir(file)) {
+        Ok(entries) => entries.filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|e| e == "java").unwrap_or(false))
+            .filter(|p| p.file_name() != file.file_name())
+            .filter_map(|p| p.file_stem().and_then(|s| s.to_str()).map(|s| s.to_string()))
+            .collect(),
+        Err(_) => vec![],
+    };
+
+    imports_other || tokens.iter().any(|t| match *t {
+        Token::Ident(ref name) => siblings.contains(name),
+        _ => false,
     })
 }
 
-pub fn run_first_main(job: &Job) -> Result<(), ()> {
-    // TODO: find the first class where `main` is defined
-    let p = Path::new(&job.files[0]);
-    let class = p.file_name().and_then(|s| s.to_str()).unwrap();
-    let class = strip_file_ending(class);
-    let parent = p.parent().unwrap();
-
-    inner::run(class, parent, job).map_err(|e| {
-        match e {
-            Error::JavaBinaryNotFound => {
-                msg!(
-                    Error,
-                    "`{1}` was not found on the system. Make sure that `{1}` \
-                        is installed and in your PATH. Aborting.",
-                    JAVA_NAME
-                );
+/// The class found by `find_main_class` or the class named like the first
+/// file
+fn main_class_or_first(job: &Job) -> MainClass {
+    find_main_class(job).unwrap_or_else(|| {
+        let file = &job.files[0];
+        let class = file.file_name().and_then(|s| s.to_str()).unwrap();
+        given_main_class(job, strip_file_ending(class))
+    })
+}
+
+/// A class with a `main` method
+pub struct MainClass {
+    /// The fully qualified name, e.g. `foo.bar.Main`
+    pub name: String,
+    /// The directory containing the directories of the packages, which is
+    /// the class path to run the class compiled next to its source
+    pub root: PathBuf,
+}
+
+/// Returns the class given with `--main-class` or else the first class with
+/// a `public static void main(String[])` method in the files of the job, in
+/// the order they were given. Files the parser rejects (e.g. because of
+/// lambdas) are searched on their tokens.
+pub fn find_main_class(job: &Job) -> Option<MainClass> {
+    if let Some(ref name) = job.main_class {
+        return Some(given_main_class(job, name));
+    }
+    job.files.iter().filter_map(|file| main_class_in(job, file)).next()
+}
+
+/// The class given by its fully qualified name. Its root is found from the
+/// file named after the class.
+fn given_main_class(job: &Job, name: &str) -> MainClass {
+    let simple_name = name.rsplit('.').next().unwrap_or(name);
+    let file = job.files.iter()
+        .find(|f| f.file_stem().and_then(|s| s.to_str()) == Some(simple_name))
+        .unwrap_or(&job.files[0]);
+    MainClass {
+        name: name.to_string(),
+        root: package_root(file, name.matches('.').count()),
+    }
+}
+
+fn main_class_in(job: &Job, file: &Path) -> Option<MainClass> {
+    let src = match check::read_file(job, file) {
+        Ok(src) => src,
+        Err(_) => return None,
+    };
+    let file_map = FileMap::new(file.to_string_lossy().into_owned(), src);
+    let found = match syntax::parse_compilation_unit(&file_map).0 {
+        Some(ast) => main_class_in_ast(&ast),
+        None => main_class_in_tokens(&file_map),
+    };
+
+    found.map(|(package, class)| {
+        let mut name = package.join(".");
+        if !name.is_empty() {
+            name.push('.');
+        }
+        name.push_str(&class);
+        MainClass {
+            name: name,
+            root: package_root(file, package.len()),
+        }
+    })
+}
+
+/// The package and the name of the first class with a `main` method
+fn main_class_in_ast(ast: &CompilationUnit) -> Option<(Vec<String>, String)> {
+    let class = ast.types.iter()
+        .filter_map(|ty| match *ty {
+            TypeDef::NormalClass(ref c) => Some(c),
+            _ => None,
+        })
+        .find(|c| c.members.iter().any(|m| match *m {
+            ClassMember::Method(ref m) => is_main(m),
+            _ => false,
+        }));
+
+    class.map(|class| {
+        let package = ast.package.iter()
+            .flat_map(|p| p.segments.iter().map(|s| s.name.clone()))
+            .collect();
+        (package, class.name.name.clone())
+    })
+}
+
+/// The parser doesn't know varargs parameters yet, so methods with
+/// `String... args` are only found by `main_class_in_tokens`.
+fn is_main(m: &Method) -> bool {
+    let is_void = m.ret_ty.dims == 0 && m.ret_ty.name.segments.len() == 1 &&
+        m.ret_ty.name.segments[0].name == "void";
+    let string_array = m.params.len() == 1 && m.params[0].ty.dims == 1 &&
+        m.params[0].ty.name.segments.last().map(|s| &s.name[..]) == Some("String");
+
+    m.name.name == "main" && m.vis == Visibility::Public && m.static_ && is_void && string_array
+}
+
+/// Like `main_class_in_ast`, but searches the tokens of the file: the first
+/// top level class containing a `main` method.
+fn main_class_in_tokens(file_map: &FileMap) -> Option<(Vec<String>, String)> {
+    let tokens = real_tokens(file_map);
+    let package = package_in(&tokens);
+    let mut class = None;
+    let mut depth = 0;
+    for (i, tok) in tokens.iter().enumerate() {
+        match *tok {
+            Token::BraceOp => depth += 1,
+            Token::BraceCl => depth = cmp::max(depth, 1) - 1,
+            Token::KeyW(Keyword::Class) if depth == 0 => {
+                class = match tokens.get(i + 1) {
+                    Some(&Token::Ident(ref name)) => Some(name.clone()),
+                    _ => None,
+                };
             },
-            Error::JavacFailure(status) => {
-                msg!(
-                    Error,
-                    "`{}` exited with a non-success status ({}). Aborting.",
-                    JAVA_NAME,
-                    status
-                );
+            Token::Ident(ref name) if name == "main" && depth == 1 => {
+                if class.is_some() && is_main_tokens(&tokens[..i], &tokens[i + 1..]) {
+                    return class.map(|class| (package, class));
+                }
             },
-            Error::Io(e) => {
-                msg!(
-                    Error,
-                    "an IO error occured while executing `{}`: {}. Aborting.",
-                    JAVA_NAME,
-                    e
-                );
-            }
+            _ => {},
+        }
+    }
+    None
+}
+
+/// The tokens of the file without whitespace and comments
+fn real_tokens(file_map: &FileMap) -> Vec<Token> {
+    analyze::tokenize(file_map).into_iter()
+        .map(|ts| ts.tok)
+        .filter(|tok| tok.is_real())
+        .collect()
+}
+
+/// The segments of the package declared at the beginning of the tokens,
+/// empty for the default package
+fn package_in(tokens: &[Token]) -> Vec<String> {
+    if tokens.first() != Some(&Token::KeyW(Keyword::Package)) {
+        return vec![];
+    }
+    tokens[1..].iter()
+        .take_while(|t| **t != Token::Semi)
+        .filter_map(|t| match *t {
+            Token::Ident(ref segment) => Some(segment.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether the tokens before and after an identifier `main` declare the
+/// main method: `public static void main(String[] args)`, also with
+/// `String args[]` or `String... args`.
+fn is_main_tokens(before: &[Token], after: &[Token]) -> bool {
+    let modifiers: Vec<_> = before.iter()
+        .rev()
+        .take_while(|t| match **t {
+            Token::Semi | Token::BraceOp | Token::BraceCl => false,
+            _ => true,
+        })
+        .collect();
+    let header = before.last() == Some(&Token::KeyW(Keyword::Void)) &&
+        modifiers.contains(&&Token::KeyW(Keyword::Public)) &&
+        modifiers.contains(&&Token::KeyW(Keyword::Static));
+    if !header || after.first() != Some(&Token::ParenOp) {
+        return false;
+    }
+
+    // The parameter without `final`, e.g. `String [ ] args`. `String` may be
+    // qualified.
+    let param: Vec<_> = after[1..].iter()
+        .take_while(|t| **t != Token::ParenCl)
+        .filter(|t| **t != Token::KeyW(Keyword::Final))
+        .collect();
+    let string = match param.iter().rposition(|t| **t == Token::Ident("String".into())) {
+        Some(pos) => pos,
+        None => return false,
+    };
+    let qualified = param[..string].iter().all(|t| match **t {
+        Token::Ident(_) | Token::Dot => true,
+        _ => false,
+    });
+    let shape: String = param[string + 1..].iter().map(|t| match **t {
+        Token::BracketOp => '[',
+        Token::BracketCl => ']',
+        Token::DotDotDot => '.',
+        Token::Ident(_) => 'i',
+        _ => '?',
+    }).collect();
+
+    qualified && (shape == "[]i" || shape == "i[]" || shape == ".i")
+}
+
+/// The directory containing the directories of the packages, if the file
+/// is in a package with the given number of segments: every package is one
+/// directory above the file.
+fn package_root(file: &Path, segments: usize) -> PathBuf {
+    let mut root = parent_dir(file).to_path_buf();
+    for _ in 0..segments {
+        root = match root.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => root.join(".."),
         };
-    })
+    }
+    root
+}
+
+/// Calls `f` with a new temporary directory `jswag-<purpose>-<pid>`, which
+/// is removed afterwards.
+pub fn with_temp_dir<T, F>(purpose: &str, f: F) -> Result<T, ()>
+    where F: FnOnce(&Path) -> Result<T, ()>
+{
+    let tmp = env::temp_dir().join(format!("jswag-{}-{}", purpose, process::id()));
+    if let Err(e) = fs::create_dir_all(&tmp) {
+        msg!(Error, "Could not create '{}': {}", tmp.display(), e);
+        return Err(());
+    }
+
+    let res = f(&tmp);
+    let _ = fs::remove_dir_all(&tmp);
+    res
+}
+
+/// The directory of the file, `.` for files in the current directory
+fn parent_dir(file: &Path) -> &Path {
+    match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+fn print_error(e: Error, name: &str) {
+    match e {
+        Error::JavaBinaryNotFound => {
+            msg!(
+                Error,
+                "`{1}` was not found on the system. Make sure that `{1}` \
+                    is installed and in your PATH. Aborting.",
+                name
+            );
+            msg!(Note, "run `jswag doctor` to check your setup");
+        },
+        Error::JavacFailure(status) => {
+            msg!(
+                Error,
+                "`{}` exited with a non-success status ({}). Aborting.",
+                name,
+                status
+            );
+        },
+        Error::Io(e) => {
+            msg!(
+                Error,
+                "an IO error occured while executing `{}`: {}. Aborting.",
+                name,
+                e
+            );
+        }
+    };
 }
 
 fn strip_file_ending(file: &str) -> &str {
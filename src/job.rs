@@ -1,8 +1,14 @@
+use analyze::custom::pattern;
 use args::{Args, Encoding};
+use baseline;
+use config::{self, Config};
+use git::Changes;
+use java::JAVAC_NAME;
 use std::collections::VecDeque;
 use std::io;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use toolchain::{self, Jdk};
 
 /// A job description to be executed.
 ///
@@ -17,15 +23,204 @@ pub struct Job {
     pub verbose: bool,
     pub lossy_decoding: bool,
     pub encoding: Encoding,
+    /// Whether findings of the analysis passes should be fixed
+    pub fix: Option<FixMode>,
+    /// Where to write the metrics of all files, if at all
+    pub metrics_report: Option<MetricsReport>,
+    /// The directory to write the HTML report of the run into, if at all
+    pub report: Option<PathBuf>,
+    /// The baseline file whose findings are hidden, if any
+    pub baseline: Option<PathBuf>,
+    /// Changes since the revision given by `--changed-since`. Only findings
+    /// in changed lines are shown.
+    pub changes: Option<Changes>,
+    /// The JDK selected with `--jdk`. Without one, `javac` and `java` are
+    /// taken from PATH.
+    pub jdk: Option<Jdk>,
+    /// The major version of the JDK, if it's known. Without `--jdk`, it's
+    /// only asked from `javac` if it's needed: if the Java version of the
+    /// code is set or files are run.
+    pub jdk_major: Option<u32>,
+    /// The main class given with `--main-class`, e.g. `foo.Main`
+    pub main_class: Option<String>,
+    /// Settings from the configuration file
+    pub config: Config,
 }
 
 impl Job {
+    /// The files to check and analyze. With `--changed-since`, these are
+    /// only the changed files, while compiling and running uses all files.
+    pub fn checked_files(&self) -> Vec<&Path> {
+        self.files.iter()
+            .filter(|f| self.changes.as_ref().map(|c| c.contains_file(f)).unwrap_or(true))
+            .map(|f| f.as_path())
+            .collect()
+    }
+
+    /// A job without sub jobs and files, which only takes the general flags
+    /// from `args`. The other fields are set with struct update syntax.
+    fn empty(args: &Args, config: Config) -> Job {
+        Job {
+            sub_jobs: VecDeque::new(),
+            files: vec![],
+            verbose: args.flag_verbose,
+            lossy_decoding: args.flag_lossy_decoding,
+            encoding: args.flag_encoding,
+            fix: None,
+            metrics_report: None,
+            report: None,
+            baseline: None,
+            changes: None,
+            jdk: None,
+            jdk_major: None,
+            main_class: None,
+            config: config,
+        }
+    }
+
     pub fn from_args(mut args: Args) -> Option<Self> {
-        let no_cmd = !(args.cmd_build || args.cmd_run || args.cmd_raw);
+        // The doctor checks the configuration and the JDK itself, so they
+        // mustn't stop it
+        if args.cmd_doctor {
+            let mut sub_jobs = VecDeque::new();
+            sub_jobs.push_back(JobType::Doctor { jdk: args.flag_jdk.clone() });
+            return Some(Job {
+                sub_jobs: sub_jobs,
+                ..Job::empty(&args, Config::default())
+            });
+        }
+
+        // Listing the JDKs doesn't need the configuration
+        if args.cmd_toolchains {
+            let mut sub_jobs = VecDeque::new();
+            sub_jobs.push_back(JobType::Toolchains);
+            return Some(Job {
+                sub_jobs: sub_jobs,
+                ..Job::empty(&args, Config::default())
+            });
+        }
+
+        // The language server keeps running with the defaults, since the
+        // editor can't show why it stopped. It mustn't print to stdout.
+        let mut config = match config::load() {
+            Ok(c) => c,
+            Err(e) if args.cmd_lsp => {
+                warn!("invalid configuration file, using the defaults: {}", e);
+                Config::default()
+            },
+            Err(e) => {
+                msg!(Error, "Invalid configuration file `{}`: {}", config::CONFIG_FILE_NAME, e);
+                return None;
+            }
+        };
+        if let Some(ref version) = args.flag_java_version {
+            match version.parse().ok().and_then(|v| config::check_java_version(v).ok()) {
+                Some(v) => config.java_version = Some(v),
+                None => {
+                    let valid: Vec<_> = config::JAVA_VERSIONS.iter()
+                        .map(|v| v.to_string())
+                        .collect();
+                    println!(
+                        "Invalid Java version '{}'. Valid values: {}",
+                        version,
+                        valid.join(", "),
+                    );
+                    return None;
+                },
+            }
+        }
+        config.rules = match pattern::load() {
+            Ok(rules) => rules,
+            Err(e) if args.cmd_lsp => {
+                warn!("invalid rule file, ignoring all rules: {}", e);
+                Vec::new()
+            },
+            Err(e) => {
+                msg!(Error, "Invalid rule file {}", e);
+                return None;
+            }
+        };
+
+        // The language server gets its files from the editor, so we don't
+        // need to look at the file list at all.
+        if args.cmd_lsp {
+            args.flag_analyze.push("style".into());
+            let passes = match Self::parse_passes(&args.flag_analyze) {
+                Some(p) => p,
+                None => return None,
+            };
+
+            let mut sub_jobs = VecDeque::new();
+            sub_jobs.push_back(JobType::Lsp { passes: passes });
+            return Some(Job {
+                sub_jobs: sub_jobs,
+                ..Job::empty(&args, config)
+            });
+        }
+
+        // Submissions are directories, not a list of files
+        if args.cmd_similarity {
+            let mut sub_jobs = VecDeque::new();
+            sub_jobs.push_back(JobType::Similarity {
+                submissions: args.arg_dir.iter().map(PathBuf::from).collect(),
+                output: PathBuf::from(&args.flag_output),
+            });
+            return Some(Job {
+                sub_jobs: sub_jobs,
+                ..Job::empty(&args, config)
+            });
+        }
+
+        let metrics_report = match args.flag_metrics_report {
+            Some(ref s) => match MetricsReport::parse(s) {
+                Some(report) => {
+                    args.flag_analyze.push("metrics".into());
+                    Some(report)
+                },
+                None => {
+                    println!("Invalid metrics report '{}'. Valid values: 'table', 'csv', \
+                        'table=<file>' or 'csv=<file>'", s);
+                    return None;
+                },
+            },
+            None => None,
+        };
+
+        let report = match args.flag_report {
+            Some(ref s) => match parse_report(s) {
+                Some(dir) => Some(dir),
+                None => {
+                    println!("Invalid report '{}'. Valid values: 'html=<dir>'", s);
+                    return None;
+                },
+            },
+            None => None,
+        };
+
+        // Searching by kind or name doesn't need a pattern, so the first
+        // argument is a file
+        let grep_code = if args.flag_kind.is_some() || args.flag_name.is_some() {
+            if let Some(file) = args.arg_pattern.take() {
+                args.arg_file.insert(0, file);
+            }
+            None
+        } else {
+            args.arg_pattern.take()
+        };
+        if args.cmd_grep && grep_code.is_none() && args.flag_kind.is_none() &&
+            args.flag_name.is_none()
+        {
+            println!("`grep` needs a code pattern, `--kind` or `--name`");
+            return None;
+        }
+
+        let no_cmd = !(args.cmd_build || args.cmd_run || args.cmd_fix || args.cmd_fmt ||
+            args.cmd_baseline || args.cmd_grep || args.cmd_dump_tokens || args.cmd_dump_ast ||
+            args.cmd_jar || args.cmd_raw);
         if !no_cmd && args.arg_file.is_empty() {
             args.arg_file.push(".".into());
         }
-        let files = match Self::fold_files(args.arg_file) {
+        let files = match Self::fold_files(args.arg_file.clone()) {
             Err(e) => {
                 msg!(Error, "An IO error occured while analysing file list: {}", e);
                 return None;
@@ -37,42 +232,104 @@ impl Job {
             Ok(f) => f,
         };
 
+        let jdk = match args.flag_jdk {
+            Some(ref jdk) => match toolchain::select(jdk) {
+                Ok(jdk) => Some(jdk),
+                Err(e) => {
+                    msg!(Error, "Invalid JDK: {}", e);
+                    msg!(Note, "Run `jswag toolchains` to list the installed JDKs");
+                    return None;
+                },
+            },
+            None => None,
+        };
+        // Asking `javac` starts a process, so it's done once here
+        let jdk_major = match jdk {
+            Some(ref jdk) => jdk.major(),
+            None if config.java_version.is_some() || args.cmd_run => {
+                toolchain::version_of(Path::new(JAVAC_NAME))
+                    .and_then(|v| toolchain::major_version(&v))
+            },
+            None => None,
+        };
+
+        let changes = match args.flag_changed_since {
+            Some(ref rev) => match Changes::since(rev) {
+                Ok(changes) => Some(changes),
+                Err(e) => {
+                    msg!(Error, "Could not get the changes since '{}' from git: {}", rev, e);
+                    return None;
+                },
+            },
+            None => None,
+        };
+
         let mut out = Job {
-            sub_jobs: VecDeque::new(),
             files: files,
-            verbose: args.flag_verbose,
-            lossy_decoding: args.flag_lossy_decoding,
-            encoding: args.flag_encoding,
-            // encoding: Encoding::Utf8,
+            fix: if args.flag_dry_run {
+                Some(FixMode::DryRun)
+            } else if args.flag_fix || args.cmd_fix {
+                Some(FixMode::Apply)
+            } else {
+                None
+            },
+            metrics_report: metrics_report,
+            report: report,
+            baseline: if args.cmd_baseline {
+                None
+            } else {
+                args.flag_baseline.as_ref().map(PathBuf::from)
+            },
+            changes: changes,
+            jdk: jdk,
+            jdk_major: jdk_major,
+            main_class: args.flag_main_class.clone(),
+            ..Job::empty(&args, config)
         };
 
+        // Whether `--check` can be trusted is interesting before checking
+        if args.flag_verify_parser {
+            out.sub_jobs.push_back(JobType::VerifyParser);
+        }
+
         // Matching flag, implying flags or implying commands
-        if args.flag_check || !args.arg_analyze.is_empty() ||
-            args.cmd_run || args.cmd_build || no_cmd
+        if args.flag_check || !args.flag_analyze.is_empty() || args.cmd_run ||
+            args.cmd_build || args.cmd_jar || args.cmd_fix || args.cmd_fmt || args.cmd_baseline ||
+            no_cmd
         {
             out.sub_jobs.push_back(JobType::Check);
         }
-        // Matching argument or implying commands
-        if !args.arg_analyze.is_empty() || args.cmd_run || args.cmd_build || no_cmd {
-            if args.cmd_run || args.cmd_build {
-                args.arg_analyze.push("style".into());
-            }
-
-            let passes: VecDeque<_> = args.arg_analyze.iter().filter_map(|name| {
-                match &name[..] {
-                    "style" => Some(AnalyzePass::Style),
-                    _ => {
-                        println!("Invalid analysis pass '{}'", name);
-                        None
-                    }
-                }
-            }).collect();
+        if args.cmd_fmt {
+            out.sub_jobs.push_back(JobType::Format {
+                check_only: args.flag_check_format,
+            });
+        }
+        // The baseline is recorded instead of printing the findings
+        if args.cmd_baseline {
+            args.flag_analyze.push("style".into());
+            let passes = match Self::parse_passes(&args.flag_analyze) {
+                Some(p) => p,
+                None => return None,
+            };
 
-            // There was at least one invalid pass name
-            if passes.len() != args.arg_analyze.len() {
-                return None;
+            let path = args.flag_baseline.clone()
+                .unwrap_or_else(|| baseline::BASELINE_FILE_NAME.into());
+            out.sub_jobs.push_back(JobType::Baseline {
+                passes: passes,
+                path: PathBuf::from(path),
+            });
+        } else if !args.flag_analyze.is_empty() || args.cmd_run || args.cmd_build ||
+            args.cmd_fix || no_cmd
+        {
+            if args.cmd_run || args.cmd_build || args.cmd_fix {
+                args.flag_analyze.push("style".into());
             }
 
+            let passes = match Self::parse_passes(&args.flag_analyze) {
+                Some(p) => p,
+                None => return None,
+            };
+
             out.sub_jobs.push_back(
                 JobType::Analyze {
                     passes: passes,
@@ -80,10 +337,42 @@ impl Job {
             );
         }
 
-        if args.flag_pass_through || args.cmd_run || args.cmd_build {
+        if out.fix.is_some() && args.flag_analyze.is_empty() {
+            println!("In order to `--fix`, at least one analysis pass needs to be given");
+            return None;
+        }
+
+        if args.cmd_grep {
+            out.sub_jobs.push_back(JobType::Grep {
+                code: grep_code,
+                kind: args.flag_kind.clone(),
+                name: args.flag_name.clone(),
+            });
+        }
+
+        if args.cmd_dump_tokens {
+            out.sub_jobs.push_back(JobType::DumpTokens { json: args.flag_json });
+        }
+        if args.cmd_dump_ast {
+            out.sub_jobs.push_back(JobType::DumpAst { json: args.flag_json });
+        }
+
+        if args.cmd_jar {
+            out.sub_jobs.push_back(JobType::Jar {
+                file: args.flag_jar_file.as_ref().map(PathBuf::from),
+                resources: args.flag_resources.as_ref().map(PathBuf::from),
+            });
+        }
+        // A single file is run without writing class files next to it, unless
+        // the class to run is given
+        let run_source = args.cmd_run && out.files.len() == 1 && !args.flag_class_files &&
+            !args.flag_pass_through && !args.flag_run && out.main_class.is_none();
+        if args.flag_pass_through || (args.cmd_run && !run_source) || args.cmd_build {
             out.sub_jobs.push_back(JobType::PassThrough);
         }
-        if args.flag_run || args.cmd_run {
+        if run_source {
+            out.sub_jobs.push_back(JobType::RunSource);
+        } else if args.flag_run || args.cmd_run {
             if out.sub_jobs.iter().find(|&sj| sj == &JobType::PassThrough).is_none() {
                 println!("In order to `--run`, `--pass-through` needs to be set");
                 return None;
@@ -91,9 +380,48 @@ impl Job {
             out.sub_jobs.push_back(JobType::Run);
         }
 
+        if let Some(rev) = args.flag_changed_since {
+            if out.checked_files().is_empty() {
+                msg!(Note, "No files changed since '{}', nothing to check", rev);
+            }
+        }
+
         Some(out)
     }
 
+    /// Converts the names of analysis passes into `AnalyzePass`es, ignoring
+    /// duplicates. Returns `None` if at least one name is invalid.
+    fn parse_passes(names: &[String]) -> Option<VecDeque<AnalyzePass>> {
+        let mut passes = VecDeque::new();
+        let mut valid = true;
+        for name in names {
+            let pass = match &name[..] {
+                "style" => AnalyzePass::Style,
+                "javadoc" => AnalyzePass::Javadoc,
+                "metrics" => AnalyzePass::Metrics,
+                "bugs" => AnalyzePass::Bugs,
+                "unused" => AnalyzePass::Unused,
+                "duplicates" => AnalyzePass::Duplicates,
+                "idioms" => AnalyzePass::Idioms,
+                _ => {
+                    println!("Invalid analysis pass '{}'", name);
+                    valid = false;
+                    continue;
+                }
+            };
+
+            if !passes.contains(&pass) {
+                passes.push_back(pass);
+            }
+        }
+
+        if valid {
+            Some(passes)
+        } else {
+            None
+        }
+    }
+
     pub fn fold_files(files: Vec<String>) -> io::Result<Vec<PathBuf>> {
         let mut new = Vec::new();
         for file in files {
@@ -119,6 +447,16 @@ impl Job {
     }
 }
 
+/// Parses a report description like `html=report/` and returns the
+/// directory.
+fn parse_report(s: &str) -> Option<PathBuf> {
+    let mut parts = s.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some("html"), Some(dir)) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => None,
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum JobType {
     /// Checks input files for language errors with internal tools
@@ -127,13 +465,115 @@ pub enum JobType {
     Analyze {
         passes: VecDeque<AnalyzePass>,
     },
+    /// Compares the internal parser with `javac`
+    VerifyParser,
     /// Forwards files to `javac` to compile them into byte code
     PassThrough,
     /// Runs `java` to execute the files
     Run,
+    /// Runs the single file without writing class files next to it
+    RunSource,
+    /// Compiles the files into an executable JAR `file` (named after the
+    /// main class by default), including the files in `resources`
+    Jar {
+        file: Option<PathBuf>,
+        resources: Option<PathBuf>,
+    },
+    /// Records the findings of the passes in the baseline file `path`
+    Baseline {
+        passes: VecDeque<AnalyzePass>,
+        path: PathBuf,
+    },
+    /// Searches the files for a code pattern or for nodes by kind and name
+    Grep {
+        code: Option<String>,
+        kind: Option<String>,
+        name: Option<String>,
+    },
+    /// Prints the tokens of the files
+    DumpTokens {
+        json: bool,
+    },
+    /// Prints the AST of the files
+    DumpAst {
+        json: bool,
+    },
+    /// Formats the files in-place or only checks whether they are formatted
+    Format {
+        check_only: bool,
+    },
+    /// Runs a language server which checks and analyzes documents sent by
+    /// an editor
+    Lsp {
+        passes: VecDeque<AnalyzePass>,
+    },
+    /// Lists the installed JDKs
+    Toolchains,
+    /// Checks the environment, with the JDK given by `--jdk`
+    Doctor {
+        jdk: Option<String>,
+    },
+    /// Compares submissions (directories) with each other and writes a
+    /// report into `output`
+    Similarity {
+        submissions: Vec<PathBuf>,
+        output: PathBuf,
+    },
+}
+
+/// Specifies what to do with fixable findings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FixMode {
+    /// Change the files in-place
+    Apply,
+    /// Only print a diff of all changes
+    DryRun,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum AnalyzePass {
     Style,
+    /// Checks presence and tags of Javadoc comments
+    Javadoc,
+    /// Measures complexity and size of methods and classes
+    Metrics,
+    /// Searches common bug patterns
+    Bugs,
+    /// Finds variables and private members which are never used
+    Unused,
+    /// Finds repeated code across all files
+    Duplicates,
+    /// Suggests language features of the selected Java version
+    Idioms,
+}
+
+/// Output format of the metrics report
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetricsFormat {
+    Table,
+    Csv,
+}
+
+/// Where and how to write the metrics report
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetricsReport {
+    pub format: MetricsFormat,
+    /// The file to write to or `None` for stdout
+    pub path: Option<PathBuf>,
+}
+
+impl MetricsReport {
+    /// Parses a report description like `csv=metrics.csv`.
+    fn parse(s: &str) -> Option<MetricsReport> {
+        let mut parts = s.splitn(2, '=');
+        let format = match parts.next() {
+            Some("table") => MetricsFormat::Table,
+            Some("csv") => MetricsFormat::Csv,
+            _ => return None,
+        };
+        Some(MetricsReport {
+            format: format,
+            path: parts.next().map(PathBuf::from),
+        })
+    }
 }
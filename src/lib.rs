@@ -0,0 +1,117 @@
+//! jswag: a build tool and checker for small Java projects.
+//!
+//! The command line interface lives in `run`. Besides that, the library only
+//! exports what's needed to write project-specific rules outside of jswag:
+//! the `Rule` trait with its helpers in `custom`, the `Finding`s they
+//! produce and the file map, diagnostics and AST types they work with. A
+//! program with its own rules calls `run_with_rules` instead of `run`.
+
+extern crate docopt;
+extern crate env_logger;
+#[macro_use]
+extern crate log;
+extern crate rustc_serialize;
+extern crate xswag_base as base;
+extern crate xswag_syntax_java as syntax;
+extern crate term_painter;
+extern crate toml;
+extern crate unicode_width;
+
+use docopt::Docopt;
+use term_painter::{Attr, Color, ToStyle};
+
+#[macro_use]
+mod ui;
+
+mod analyze;
+mod args;
+mod baseline;
+mod check;
+mod config;
+mod dispatch;
+mod doctor;
+mod dump;
+mod fix;
+mod fmt;
+mod git;
+mod grep;
+mod jar;
+mod java;
+mod job;
+mod lsp;
+mod report;
+mod similarity;
+mod toolchain;
+
+pub use analyze::Finding;
+pub use analyze::custom;
+pub use base::{code, diag};
+pub use syntax::ast;
+
+use analyze::custom::{Rule, RustRules};
+use job::Job;
+use std::rc::Rc;
+
+/// Runs jswag with the command line arguments of this process. Exits the
+/// process with an error code if anything failed.
+pub fn run() {
+    run_with_rules(Vec::new())
+}
+
+/// Like `run`, but the given rules run together with the analysis passes,
+/// like the rules in `.jswag/rules`.
+pub fn run_with_rules(rules: Vec<Box<Rule>>) {
+    use args::Args;
+
+    env_logger::init().unwrap();
+
+    // If there are no command line parameters, we print a nice message without
+    // telling docopt.
+    if std::env::args().count() == 1 {
+        println!(
+            "{} Run `{}` to show the usage message or `{}` to show the \
+                version of jswag",
+            Color::Red.bold().paint("No arguments given!"),
+            Attr::Bold.paint("jswag --help"),
+            Attr::Bold.paint("jswag (-V | --version)"),
+        );
+        std::process::exit(config::EXIT_NO_INPUT);
+    }
+
+    // Parse command line arguments with docopt and exit if anything went
+    // wrong.
+    let args: Args = Docopt::new(args::USAGE)
+        .and_then(|d| d.decode())
+        .unwrap_or_else(|e| {
+            if e.fatal() {
+                println!("{}", e);
+                std::process::exit(config::EXIT_INVALID_INPUT);
+            } else {
+                println!("{}", e);
+                std::process::exit(0)
+            }
+        });
+
+
+    // If the `--version` flag was set, we do nothing but print the version.
+    if args.flag_version {
+        println!("jswag v{}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+
+    // Check validity of args and compose them into a job object
+    let mut job = match Job::from_args(args) {
+        None => {
+            msg!(Error,"Abort due to invalid input (check CLI parameters)...");
+            std::process::exit(config::EXIT_INVALID_INPUT);
+        },
+        Some(j) => j,
+    };
+    job.config.rust_rules = RustRules(Rc::new(rules));
+
+    // execute the job
+    let res = dispatch::handle(job);
+    if res.is_err() {
+        std::process::exit(config::EXIT_GENERIC_ERROR);
+    }
+}
@@ -0,0 +1,250 @@
+//! A minimal language server (LSP) over stdin and stdout.
+//!
+//! The server keeps no state about documents: clients have to send the full
+//! text on every change (`TextDocumentSyncKind.Full`). Each time a document
+//! is opened or changed, it's parsed and analyzed and all resulting reports
+//! are published as diagnostics.
+//!
+//! Note that we must not print anything to stdout here (e.g. with `msg!`),
+//! since it would corrupt the message stream. Logging goes to stderr.
+
+mod transport;
+
+use analyze::{self, Finding};
+use base::code::{BytePos, FileMap, Span};
+use base::diag::{RemarkKind, ReportKind};
+use config::Config;
+use job::AnalyzePass;
+use rustc_serialize::json::{Json, Object};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use syntax;
+
+/// JSON-RPC error code for unknown methods
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// LSP `DiagnosticSeverity` values
+const SEVERITY_ERROR: u64 = 1;
+const SEVERITY_WARNING: u64 = 2;
+
+/// Runs the server until the client sends `exit` or closes the input stream.
+pub fn serve(config: &Config, passes: &VecDeque<AnalyzePass>) -> Result<(), ()> {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+
+    let mut shutdown = false;
+    loop {
+        let msg = match transport::read_message(&mut input) {
+            Ok(Some(msg)) => msg,
+            Ok(None) => break,
+            Err(e) => {
+                error!("failed to read LSP message: {}", e);
+                return Err(());
+            }
+        };
+
+        let method = msg.find("method").and_then(|m| m.as_string()).unwrap_or("");
+        let id = msg.find("id").cloned();
+        let params = msg.find("params");
+        debug!("received LSP message '{}'", method);
+
+        let res = match method {
+            "initialize" => {
+                let caps = object(vec![("textDocumentSync", Json::U64(1))]);
+                let result = object(vec![("capabilities", caps)]);
+                respond(&mut output, id, result)
+            }
+            "shutdown" => {
+                shutdown = true;
+                respond(&mut output, id, Json::Null)
+            }
+            "exit" => break,
+            "textDocument/didOpen" => {
+                let doc = params.and_then(|p| p.find("textDocument"));
+                let uri = doc.and_then(|d| d.find("uri")).and_then(|u| u.as_string());
+                let text = doc.and_then(|d| d.find("text")).and_then(|t| t.as_string());
+                match (uri, text) {
+                    (Some(uri), Some(text)) => {
+                        let diags = diagnose(uri, text, config, passes);
+                        publish(&mut output, uri, diags)
+                    }
+                    _ => Ok(()),
+                }
+            }
+            "textDocument/didChange" => {
+                let uri = params
+                    .and_then(|p| p.find_path(&["textDocument", "uri"]))
+                    .and_then(|u| u.as_string());
+                // With full sync the last change contains the whole document
+                let text = params
+                    .and_then(|p| p.find("contentChanges"))
+                    .and_then(|c| c.as_array())
+                    .and_then(|c| c.last())
+                    .and_then(|c| c.find("text"))
+                    .and_then(|t| t.as_string());
+                match (uri, text) {
+                    (Some(uri), Some(text)) => {
+                        let diags = diagnose(uri, text, config, passes);
+                        publish(&mut output, uri, diags)
+                    }
+                    _ => Ok(()),
+                }
+            }
+            "textDocument/didClose" => {
+                let uri = params
+                    .and_then(|p| p.find_path(&["textDocument", "uri"]))
+                    .and_then(|u| u.as_string());
+                match uri {
+                    Some(uri) => publish(&mut output, uri, vec![]),
+                    None => Ok(()),
+                }
+            }
+            // Unknown requests need an answer, unknown notifications are
+            // ignored.
+            _ => match id {
+                Some(id) => respond_error(&mut output, id, METHOD_NOT_FOUND, "method not found"),
+                None => Ok(()),
+            },
+        };
+
+        if let Err(e) = res {
+            error!("failed to write LSP message: {}", e);
+            return Err(());
+        }
+    }
+
+    // According to the spec, the server has to exit with an error code if it
+    // wasn't shut down properly before.
+    if shutdown {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+/// Parses and analyzes the given document and returns all reports as LSP
+/// diagnostics.
+fn diagnose(uri: &str, text: &str, config: &Config, passes: &VecDeque<AnalyzePass>)
+    -> Vec<Json>
+{
+    // The parser compares the name of public types with the filename, so we
+    // only pass the last part of the URI.
+    let file_name = uri.rsplit('/').next().unwrap_or(uri);
+    let file_map = FileMap::new(file_name, text);
+    let (ast, reports) = syntax::parse_compilation_unit(&file_map);
+    let mut diags: Vec<_> = reports.into_iter()
+        .map(|rep| diagnostic(&Finding::new("syntax", rep), file_map.src()))
+        .collect();
+    if let Some(ref ast) = ast {
+        let findings = analyze::analyze_file(passes, config, &file_map, ast);
+        diags.extend(findings.iter().map(|f| diagnostic(f, file_map.src())));
+    }
+
+    diags
+}
+
+/// Converts a finding into an LSP diagnostic.
+fn diagnostic(finding: &Finding, src: &str) -> Json {
+    let rep = &finding.report;
+    // Use the main span or the first span of a remark if the report has none
+    let span = rep.span.or_else(|| {
+        rep.remarks.iter().filter_map(|rem| rem.snippet.span()).next()
+    });
+    let span = match span {
+        Some(span) if !span.is_dummy() => span,
+        _ => Span::empty_at(BytePos(0)),
+    };
+
+    let severity = match rep.kind {
+        ReportKind::Error => SEVERITY_ERROR,
+        ReportKind::Warning => SEVERITY_WARNING,
+    };
+
+    // The first remark is the main message, all others are appended
+    let mut message = String::new();
+    for (i, rem) in rep.remarks.iter().enumerate() {
+        if i > 0 {
+            message.push('\n');
+            message.push_str(match rem.kind {
+                RemarkKind::Error => "error: ",
+                RemarkKind::Warning => "warning: ",
+                RemarkKind::Note => "note: ",
+            });
+        }
+        message.push_str(&rem.desc);
+    }
+
+    object(vec![
+        ("range", object(vec![
+            ("start", position(src, span.lo)),
+            ("end", position(src, span.hi)),
+        ])),
+        ("severity", Json::U64(severity)),
+        ("code", Json::String(finding.rule.to_string())),
+        ("source", Json::String("jswag".into())),
+        ("message", Json::String(message)),
+    ])
+}
+
+/// Converts a byte position into an LSP position. LSP counts characters in
+/// UTF-16 code units.
+fn position(src: &str, pos: BytePos) -> Json {
+    let mut offset = ::std::cmp::min(pos.0 as usize, src.len());
+    while !src.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    let before = &src[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let character: usize = before[line_start..].chars().map(|c| c.len_utf16()).sum();
+
+    object(vec![
+        ("line", Json::U64(line as u64)),
+        ("character", Json::U64(character as u64)),
+    ])
+}
+
+fn publish<W: Write>(output: &mut W, uri: &str, diags: Vec<Json>) -> io::Result<()> {
+    let params = object(vec![
+        ("uri", Json::String(uri.into())),
+        ("diagnostics", Json::Array(diags)),
+    ]);
+    transport::write_message(output, &object(vec![
+        ("jsonrpc", Json::String("2.0".into())),
+        ("method", Json::String("textDocument/publishDiagnostics".into())),
+        ("params", params),
+    ]))
+}
+
+fn respond<W: Write>(output: &mut W, id: Option<Json>, result: Json) -> io::Result<()> {
+    transport::write_message(output, &object(vec![
+        ("jsonrpc", Json::String("2.0".into())),
+        ("id", id.unwrap_or(Json::Null)),
+        ("result", result),
+    ]))
+}
+
+fn respond_error<W: Write>(output: &mut W, id: Json, code: i64, msg: &str)
+    -> io::Result<()>
+{
+    let error = object(vec![
+        ("code", Json::I64(code)),
+        ("message", Json::String(msg.into())),
+    ]);
+    transport::write_message(output, &object(vec![
+        ("jsonrpc", Json::String("2.0".into())),
+        ("id", id),
+        ("error", error),
+    ]))
+}
+
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    let mut obj = Object::new();
+    for (key, value) in fields {
+        obj.insert(key.into(), value);
+    }
+    Json::Object(obj)
+}
@@ -0,0 +1,57 @@
+//! Reading and writing of LSP messages.
+//!
+//! Every message consists of a header part and a JSON body. The only header
+//! we care about is `Content-Length`, which specifies the length of the body
+//! in bytes.
+
+use rustc_serialize::json::Json;
+use std::io::{self, BufRead, Write};
+
+/// Reads the next message. Returns `Ok(None)` if the input stream was closed.
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Json>> {
+    let mut content_len = None;
+    loop {
+        let mut line = String::new();
+        if try!(input.read_line(&mut line)) == 0 {
+            return Ok(None);
+        }
+
+        // An empty line terminates the header part. Some clients send
+        // additional empty lines between messages, which we skip.
+        let line = line.trim();
+        if line.is_empty() {
+            if content_len.is_some() {
+                break;
+            }
+            continue;
+        }
+
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        if name.to_lowercase() == "content-length" {
+            content_len = Some(try!(value.parse::<usize>().map_err(|_| {
+                invalid_data(format!("invalid Content-Length '{}'", value))
+            })));
+        }
+    }
+
+    // We can unwrap, because we only leave the loop with a length
+    let mut body = vec![0; content_len.unwrap()];
+    try!(input.read_exact(&mut body));
+    let body = try!(String::from_utf8(body).map_err(|e| invalid_data(e)));
+
+    Json::from_str(&body).map(Some).map_err(|e| invalid_data(e))
+}
+
+/// Writes the given message including the header part and flushes the
+/// output stream.
+pub fn write_message<W: Write>(output: &mut W, msg: &Json) -> io::Result<()> {
+    let body = msg.to_string();
+    try!(write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body));
+    output.flush()
+}
+
+fn invalid_data<E: ToString>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
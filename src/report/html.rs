@@ -0,0 +1,314 @@
+//! The report as static HTML pages: an index of all files with the number
+//! of errors and warnings and the output of `javac` and `java`, and one page
+//! per file with its diagnostics, metrics and highlighted source.
+
+use analyze;
+use base::code::FileMap;
+use base::diag::{RemarkKind, ReportKind, Snippet};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::iter;
+use std::path::Path;
+use java::Outcome;
+use super::FileReport;
+use syntax::lex::Token;
+
+const STYLE: &'static str = "
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }
+td.num { text-align: right; }
+pre { background: #f8f8f8; padding: 0.5em; overflow-x: auto; }
+.ln { color: #999; }
+.kw { color: #708; font-weight: bold; }
+.lit { color: #164; }
+.com { color: #888; font-style: italic; }
+.error { color: #c00; font-weight: bold; }
+.warning { color: #b60; font-weight: bold; }
+.note { color: #05a; }
+.ok { color: #080; }
+";
+
+/// A remark shown below a line of code
+struct Annotation {
+    /// Byte range within the line which is marked, if any
+    range: Option<(usize, usize)>,
+    class: &'static str,
+    text: String,
+}
+
+/// Writes `index.html` and the pages of all readable files into `dir`.
+pub fn write(dir: &Path, files: &[FileReport], outcomes: &[Outcome]) -> io::Result<()> {
+    try!(fs::create_dir_all(dir));
+
+    let mut index = String::new();
+    index.push_str("<h1>jswag report</h1>\n<h2>Files</h2>\n<table>\n");
+    index.push_str("<tr><th>File</th><th>Errors</th><th>Warnings</th></tr>\n");
+    for (idx, file) in files.iter().enumerate() {
+        let name = escape(&file.path.display().to_string());
+        let cells = match file.src {
+            Some(ref src) => {
+                let page = format!("file-{}.html", idx + 1);
+                try!(write_page(&dir.join(&page), &file_page(file, src)));
+                format!(
+                    "<td><a href=\"{}\">{}</a></td><td class=\"num\">{}</td>\
+                        <td class=\"num\">{}</td>",
+                    page,
+                    name,
+                    count(file, ReportKind::Error),
+                    count(file, ReportKind::Warning),
+                )
+            },
+            None => format!("<td>{}</td><td colspan=\"2\">could not be read</td>", name),
+        };
+        index.push_str(&format!("<tr>{}</tr>\n", cells));
+    }
+    index.push_str("</table>\n<h2>Compiling and running</h2>\n");
+
+    if outcomes.is_empty() {
+        index.push_str("<p>Neither <code>javac</code> nor <code>java</code> was run.</p>\n");
+    }
+    for outcome in outcomes {
+        let status = if outcome.status.success() {
+            "<span class=\"ok\">succeeded</span>".to_string()
+        } else {
+            format!("<span class=\"error\">failed ({})</span>", outcome.status)
+        };
+        index.push_str(&format!(
+            "<h3><code>{}</code> {}</h3>\n<pre>{}</pre>\n",
+            escape(&outcome.command),
+            status,
+            escape(&outcome.output),
+        ));
+    }
+
+    write_page(&dir.join("index.html"), &index)
+}
+
+fn write_page(path: &Path, body: &str) -> io::Result<()> {
+    let mut file = try!(File::create(path));
+    write!(
+        file,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+            <title>jswag report</title>\n<style>{}</style>\n</head>\n\
+            <body>\n{}</body>\n</html>\n",
+        STYLE,
+        body,
+    )
+}
+
+fn count(file: &FileReport, kind: ReportKind) -> usize {
+    file.diagnostics.iter().filter(|d| d.report.kind == kind).count()
+}
+
+/// The page of one file
+fn file_page(file: &FileReport, src: &str) -> String {
+    let starts = line_starts(src);
+    let mut out = format!(
+        "<h1>{}</h1>\n<p><a href=\"index.html\">Back to the index</a></p>\n",
+        escape(&file.path.display().to_string()),
+    );
+
+    if !file.diagnostics.is_empty() {
+        out.push_str("<h2>Diagnostics</h2>\n<table>\n");
+        out.push_str("<tr><th>Line</th><th>Kind</th><th>Rule</th><th>Message</th></tr>\n");
+        for d in &file.diagnostics {
+            let line = match d.report.span {
+                Some(span) => {
+                    format!("<a href=\"#L{0}\">{0}</a>", line_of(&starts, span.lo.0) + 1)
+                },
+                None => String::new(),
+            };
+            let (class, kind) = match d.report.kind {
+                ReportKind::Error => ("error", "error"),
+                ReportKind::Warning => ("warning", "warning"),
+            };
+            out.push_str(&format!(
+                "<tr><td class=\"num\">{}</td><td class=\"{}\">{}</td><td>{}</td>\
+                    <td>{}</td></tr>\n",
+                line,
+                class,
+                kind,
+                escape(d.rule.as_ref().map(|r| &r[..]).unwrap_or("")),
+                escape(d.report.remarks.first().map(|r| &r.desc[..]).unwrap_or("")),
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+
+    if !file.metrics.is_empty() {
+        out.push_str("<h2>Metrics</h2>\n<table>\n<tr><th>Class</th><th>Method</th>\
+            <th>Cyclomatic</th><th>Cognitive</th><th>Nesting</th><th>Params</th>\
+            <th>Lines</th></tr>\n");
+        for class in &file.metrics {
+            out.push_str(&format!(
+                "<tr><td>{}</td><td></td><td></td><td></td><td></td><td></td>\
+                    <td class=\"num\">{}</td></tr>\n",
+                escape(&class.name),
+                class.length,
+            ));
+            for m in &class.methods {
+                out.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td class=\"num\">{}</td>\
+                        <td class=\"num\">{}</td><td class=\"num\">{}</td>\
+                        <td class=\"num\">{}</td><td class=\"num\">{}</td></tr>\n",
+                    escape(&class.name),
+                    escape(&m.name),
+                    m.cyclomatic,
+                    m.cognitive,
+                    m.nesting,
+                    m.params,
+                    m.length,
+                ));
+            }
+        }
+        out.push_str("</table>\n");
+    }
+
+    out.push_str("<h2>Source</h2>\n");
+    out.push_str(&source(file, src, &starts));
+    out
+}
+
+/// The highlighted source with line numbers. Every remark with a span is
+/// shown below the line in which its span starts, remarks without a span
+/// below the previous remark of the same report.
+fn source(file: &FileReport, src: &str, starts: &[usize]) -> String {
+    let line_end = |line: usize| {
+        let mut end = starts.get(line + 1).cloned().unwrap_or(src.len());
+        if src[..end].ends_with('\n') {
+            end -= 1;
+        }
+        if src[..end].ends_with('\r') {
+            end -= 1;
+        }
+        end
+    };
+
+    let mut annotations: Vec<Vec<Annotation>> = starts.iter().map(|_| Vec::new()).collect();
+    for d in &file.diagnostics {
+        let mut last_line = None;
+        for (i, remark) in d.report.remarks.iter().enumerate() {
+            let class = match remark.kind {
+                RemarkKind::Error => "error",
+                RemarkKind::Warning => "warning",
+                RemarkKind::Note => "note",
+            };
+            let mut text = format!("{}: {}", class, remark.desc);
+            if let (0, Some(ref rule)) = (i, d.rule.as_ref()) {
+                text.push_str(&format!(" [{}]", rule));
+            }
+
+            let span = match remark.snippet {
+                Snippet::Orig(span) | Snippet::Replace { span, .. } => Some(span),
+                Snippet::None => None,
+            };
+            let (line, range) = match (span, last_line) {
+                (Some(span), _) => {
+                    let line = line_of(starts, span.lo.0);
+                    let lo = span.lo.0 as usize;
+                    let hi = ::std::cmp::min(span.hi.0 as usize, line_end(line));
+                    (line, Some((lo - starts[line], ::std::cmp::max(lo, hi) - starts[line])))
+                },
+                (None, Some(line)) => (line, None),
+                // Shown in the table of diagnostics only
+                (None, None) => continue,
+            };
+            last_line = Some(line);
+            annotations[line].push(Annotation {
+                range: range,
+                class: class,
+                text: text,
+            });
+        }
+    }
+
+    let classes = highlighting(file, src);
+    let width = starts.len().to_string().len();
+    let mut out = String::from("<pre>");
+    for (idx, &start) in starts.iter().enumerate() {
+        let end = line_end(idx);
+        out.push_str(&format!("<span class=\"ln\" id=\"L{0}\">{0:>1$} </span>", idx + 1, width));
+        out.push_str(&highlighted(src, &classes, start, end));
+        out.push('\n');
+
+        for a in &annotations[idx] {
+            out.push_str(&format!("<span class=\"{}\">{:2$} ", a.class, "", width));
+            if let Some((lo, hi)) = a.range {
+                // Tabs are kept, so that the markers line up with the code
+                let line = &src[start..end];
+                let indent: String = line[..lo].chars()
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                let len = ::std::cmp::max(1, line[lo..hi].chars().count());
+                out.push_str(&indent);
+                out.extend(iter::repeat('^').take(len));
+                out.push(' ');
+            }
+            out.push_str(&escape(&a.text));
+            out.push_str("</span>\n");
+        }
+    }
+    out.push_str("</pre>\n");
+    out
+}
+
+/// Returns the CSS class of every byte of the source.
+fn highlighting(file: &FileReport, src: &str) -> Vec<&'static str> {
+    let mut classes = vec![""; src.len()];
+    let file_map = FileMap::new(file.path.to_string_lossy().into_owned(), src);
+    for ts in analyze::tokenize(&file_map) {
+        let class = match ts.tok {
+            Token::KeyW(_) => "kw",
+            Token::Literal(_) => "lit",
+            Token::Comment => "com",
+            _ => continue,
+        };
+        for c in &mut classes[ts.span.into_range()] {
+            *c = class;
+        }
+    }
+    classes
+}
+
+/// The source from `lo` to `hi` with spans for highlighted tokens.
+fn highlighted(src: &str, classes: &[&'static str], lo: usize, hi: usize) -> String {
+    let mut out = String::new();
+    let mut pos = lo;
+    while pos < hi {
+        let class = classes[pos];
+        let end = (pos..hi).find(|&i| classes[i] != class).unwrap_or(hi);
+        let text = escape(&src[pos..end]);
+        if class.is_empty() {
+            out.push_str(&text);
+        } else {
+            out.push_str(&format!("<span class=\"{}\">{}</span>", class, text));
+        }
+        pos = end;
+    }
+    out
+}
+
+/// Byte positions of all line beginnings, without the empty line after a
+/// trailing newline
+fn line_starts(src: &str) -> Vec<usize> {
+    Some(0).into_iter()
+        .chain(src.match_indices('\n').map(|(pos, _)| pos + 1))
+        .filter(|&start| start == 0 || start < src.len())
+        .collect()
+}
+
+/// Index of the line which contains the position
+fn line_of(starts: &[usize], pos: u32) -> usize {
+    match starts.binary_search(&(pos as usize)) {
+        Ok(line) => line,
+        Err(next) => next - 1,
+    }
+}
+
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
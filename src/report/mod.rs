@@ -0,0 +1,106 @@
+//! The report of a whole run (`--report html=<dir>`).
+//!
+//! The other jobs print their diagnostics as they go, so after all of them
+//! are done, every file is checked and analyzed again to collect them.
+//! Together with the metrics and the recorded calls of `javac` and `java`,
+//! they are written as static HTML pages. Findings hidden by the baseline or
+//! by `--changed-since` are left out, like in the output of the analysis.
+
+pub mod html;
+
+use analyze::{self, metrics};
+use analyze::metrics::ClassMetrics;
+use base::code::FileMap;
+use base::diag::Report;
+use baseline::Baseline;
+use check::{self, flow};
+use java::Outcome;
+use job::{Job, JobType};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A report of the check or of an analysis pass
+pub struct Diagnostic {
+    /// Name of the rule for findings of analysis passes
+    pub rule: Option<Cow<'static, str>>,
+    pub report: Report,
+}
+
+/// Everything the report shows about one file
+pub struct FileReport {
+    pub path: PathBuf,
+    /// `None` if the file couldn't be read
+    pub src: Option<String>,
+    /// All diagnostics, ordered by position
+    pub diagnostics: Vec<Diagnostic>,
+    pub metrics: Vec<ClassMetrics>,
+}
+
+/// Collects the results of all files of the job and writes the report into
+/// `dir`.
+pub fn write(job: &Job, dir: &Path, outcomes: &[Outcome]) -> io::Result<()> {
+    let passes = job.sub_jobs.iter()
+        .filter_map(|sj| match *sj {
+            JobType::Analyze { ref passes } => Some(passes.clone()),
+            _ => None,
+        })
+        .next()
+        .unwrap_or_else(VecDeque::new);
+    let mut baseline = match job.baseline {
+        Some(ref path) => Some(try!(Baseline::load(path))),
+        None => None,
+    };
+
+    let mut files = Vec::new();
+    // Readable files and their index in `files`
+    let mut file_maps = Vec::new();
+    let mut readable_idx = Vec::new();
+    for path in &job.files {
+        let src = check::read_file(job, path).ok();
+        if let Some(ref src) = src {
+            file_maps.push(FileMap::new(path.to_string_lossy().into_owned(), src.clone()));
+            readable_idx.push(files.len());
+        }
+        files.push(FileReport {
+            path: path.clone(),
+            src: src,
+            diagnostics: Vec::new(),
+            metrics: Vec::new(),
+        });
+    }
+
+    let analyses = analyze::analyze_files(&passes, &job.config, file_maps);
+    for (analysis, idx) in analyses.into_iter().zip(readable_idx) {
+        let file = &mut files[idx];
+        file.diagnostics.extend(analysis.parse_errors.into_iter().map(|report| Diagnostic {
+            rule: None,
+            report: report,
+        }));
+        if let Some(ref ast) = analysis.ast {
+            file.diagnostics.extend(flow::check(ast).into_iter().map(|report| Diagnostic {
+                rule: None,
+                report: report,
+            }));
+            file.metrics = metrics::measure(&analysis.file_map, ast);
+        }
+
+        let mut findings = analysis.findings;
+        if let Some(ref changes) = job.changes {
+            changes.retain_changed(&analysis.file_map, &mut findings);
+        }
+        if let Some(ref mut baseline) = baseline {
+            baseline.retain_new(&analysis.file_map, &mut findings);
+        }
+        file.diagnostics.extend(findings.into_iter().map(|finding| Diagnostic {
+            rule: Some(finding.rule),
+            report: finding.report,
+        }));
+    }
+
+    for file in &mut files {
+        file.diagnostics.sort_by_key(|d| d.report.span.map(|span| span.lo));
+    }
+    html::write(dir, &files, outcomes)
+}
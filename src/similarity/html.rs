@@ -0,0 +1,160 @@
+//! The HTML report of `jswag similarity`: a ranking of all pairs of
+//! submissions and one page per pair, showing both submissions side by side
+//! with matching regions highlighted.
+
+use base::code::Span;
+use report::html::escape;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use super::{Comparison, SourceFile, Submission};
+
+/// Number of distinct highlighting colors
+const COLORS: usize = 6;
+
+const STYLE: &'static str = "
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }
+.sides { display: flex; }
+.side { flex: 1; min-width: 0; margin-right: 1em; }
+pre { background: #f8f8f8; padding: 0.5em; overflow-x: auto; }
+.m0 { background: #fbb; } .m1 { background: #bfb; } .m2 { background: #bbf; }
+.m3 { background: #ffb; } .m4 { background: #fbf; } .m5 { background: #bff; }
+";
+
+/// Writes `index.html` and the pages of all pairs with matches into `dir`.
+pub fn write(dir: &Path, submissions: &[Submission], comparisons: &[Comparison])
+    -> io::Result<()>
+{
+    try!(fs::create_dir_all(dir));
+
+    let mut index = String::new();
+    index.push_str("<h1>Similarity report</h1>\n<table>\n");
+    index.push_str("<tr><th>#</th><th>Similarity</th><th>Submission</th>\
+        <th>Submission</th><th>Matches</th></tr>\n");
+    for (rank, c) in comparisons.iter().enumerate() {
+        let matches = if c.tiles.is_empty() {
+            "none".to_string()
+        } else {
+            let page = format!("pair-{}.html", rank + 1);
+            try!(write_page(&dir.join(&page), &pair(submissions, c)));
+            format!("<a href=\"{}\">{} region(s)</a>", page, c.tiles.len())
+        };
+        index.push_str(&format!(
+            "<tr><td>{}</td><td>{:.1}%</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            rank + 1,
+            c.similarity * 100.0,
+            escape(&submissions[c.a].path.display().to_string()),
+            escape(&submissions[c.b].path.display().to_string()),
+            matches,
+        ));
+    }
+    index.push_str("</table>\n");
+
+    write_page(&dir.join("index.html"), &index)
+}
+
+fn write_page(path: &Path, body: &str) -> io::Result<()> {
+    let mut file = try!(File::create(path));
+    write!(
+        file,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+            <title>Similarity report</title>\n<style>{}</style>\n</head>\n\
+            <body>\n{}</body>\n</html>\n",
+        STYLE,
+        body,
+    )
+}
+
+/// The page comparing two submissions.
+fn pair(submissions: &[Submission], c: &Comparison) -> String {
+    let (a, b) = (&submissions[c.a], &submissions[c.b]);
+    let mut out = format!(
+        "<h1>{} vs. {}: {:.1}%</h1>\n<p><a href=\"index.html\">Back to the ranking</a></p>\n",
+        escape(&a.path.display().to_string()),
+        escape(&b.path.display().to_string()),
+        c.similarity * 100.0,
+    );
+
+    // Table of all matching regions
+    out.push_str("<table>\n<tr><th>#</th><th>First</th><th>Second</th><th>Tokens</th></tr>\n");
+    for (k, tile) in c.tiles.iter().enumerate() {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td><a href=\"#a{}\">{}</a></td>\
+                <td><a href=\"#b{}\">{}</a></td><td>{}</td></tr>\n",
+            k + 1,
+            k,
+            location(a, tile.a, tile.len),
+            k,
+            location(b, tile.b, tile.len),
+            tile.len,
+        ));
+    }
+    out.push_str("</table>\n<div class=\"sides\">\n");
+
+    let regions_a: Vec<_> = c.tiles.iter().map(|t| a.region(t.a, t.len)).collect();
+    let regions_b: Vec<_> = c.tiles.iter().map(|t| b.region(t.b, t.len)).collect();
+    out.push_str(&side(a, &regions_a, 'a'));
+    out.push_str(&side(b, &regions_b, 'b'));
+    out.push_str("</div>\n");
+    out
+}
+
+/// All files of a submission with the given regions (file index and span)
+/// highlighted. The ids of the regions are prefixed with `prefix`.
+fn side(sub: &Submission, regions: &[(usize, Span)], prefix: char) -> String {
+    let mut out = format!(
+        "<div class=\"side\">\n<h2>{}</h2>\n",
+        escape(&sub.path.display().to_string()),
+    );
+    for (idx, file) in sub.files.iter().enumerate() {
+        let mut in_file: Vec<_> = regions.iter()
+            .enumerate()
+            .filter(|&(_, &(f, _))| f == idx)
+            .map(|(k, &(_, span))| (k, span))
+            .collect();
+        in_file.sort_by_key(|&(_, span)| span.lo);
+
+        out.push_str(&format!("<h3>{}</h3>\n<pre>", escape(&file_name(sub, file))));
+        let src = &file.src;
+        let mut pos = 0;
+        for (k, span) in in_file {
+            let (lo, hi) = (span.lo.0 as usize, span.hi.0 as usize);
+            out.push_str(&escape(&src[pos..lo]));
+            out.push_str(&format!(
+                "<span class=\"m{}\" id=\"{}{}\">{}</span>",
+                k % COLORS,
+                prefix,
+                k,
+                escape(&src[lo..hi]),
+            ));
+            pos = hi;
+        }
+        out.push_str(&escape(&src[pos..]));
+        out.push_str("</pre>\n");
+    }
+    out.push_str("</div>\n");
+    out
+}
+
+/// Describes the location of the tokens, e.g. `Foo.java:3-17`.
+fn location(sub: &Submission, start: usize, len: usize) -> String {
+    let (idx, span) = sub.region(start, len);
+    let file = &sub.files[idx];
+    let line = |pos: usize| file.src[..pos].matches('\n').count() + 1;
+    format!(
+        "{}:{}-{}",
+        escape(&file_name(sub, file)),
+        line(span.lo.0 as usize),
+        line(span.hi.0 as usize),
+    )
+}
+
+/// The path of the file relative to the submission directory
+fn file_name(sub: &Submission, file: &SourceFile) -> String {
+    file.path.strip_prefix(&sub.path)
+        .unwrap_or(&file.path)
+        .display()
+        .to_string()
+}
@@ -0,0 +1,246 @@
+//! Similarity of submissions, to find plagiarism.
+//!
+//! Every directory is one submission. The tokens of all Java files in it are
+//! normalized by replacing identifiers and literals with placeholders, so
+//! renaming things doesn't hide a copy. Submissions are compared pairwise
+//! with greedy string tiling: it finds matching regions regardless of their
+//! order, so reordering methods doesn't hide a copy either.
+
+pub mod html;
+
+use analyze;
+use base::code::{FileMap, Span};
+use check;
+use job::Job;
+use std::collections::{BinaryHeap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use syntax::lex::Token;
+
+/// Minimum number of tokens of a matching region
+const MIN_MATCH: usize = 12;
+
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub src: String,
+}
+
+/// All files of one directory
+pub struct Submission {
+    pub path: PathBuf,
+    pub files: Vec<SourceFile>,
+    /// Normalized tokens of all files. The files are separated by a token
+    /// which never matches.
+    syms: Vec<usize>,
+    /// File index and span of every token in `syms`
+    locs: Vec<(usize, Span)>,
+    /// Number of tokens without separators
+    len: usize,
+}
+
+/// A region of `len` tokens which is equal in two submissions, starting at
+/// token `a` in the first one and `b` in the second one.
+#[derive(Clone, Copy, Debug)]
+pub struct Tile {
+    pub a: usize,
+    pub b: usize,
+    pub len: usize,
+}
+
+/// The result of comparing two submissions (given as indices)
+pub struct Comparison {
+    pub a: usize,
+    pub b: usize,
+    /// Share of tokens of both submissions covered by tiles, from 0 to 1
+    pub similarity: f64,
+    pub tiles: Vec<Tile>,
+}
+
+impl Submission {
+    /// Returns the file index and the span of the given tokens.
+    pub fn region(&self, start: usize, len: usize) -> (usize, Span) {
+        let (file, first) = self.locs[start];
+        let (_, last) = self.locs[start + len - 1];
+        (file, Span::new(first.lo, last.hi))
+    }
+}
+
+/// Compares all given submissions with each other, prints a ranking and
+/// writes the HTML report into `output`.
+pub fn compare_all(job: &Job, dirs: &[PathBuf], output: &Path) -> Result<(), ()> {
+    let mut table = HashMap::new();
+    let mut submissions = Vec::new();
+    for dir in dirs {
+        msg!(Checking, "'{}'", dir.display());
+        match load(job, dir, &mut table) {
+            Ok(sub) => submissions.push(sub),
+            Err(e) => {
+                msg!(Error, "Could not read submission '{}': {}", dir.display(), e);
+                return Err(());
+            },
+        }
+    }
+    if submissions.len() < 2 {
+        msg!(Error, "At least two submissions are needed to compare them");
+        return Err(());
+    }
+
+    let mut comparisons = Vec::new();
+    for a in 0..submissions.len() {
+        for b in a + 1..submissions.len() {
+            comparisons.push(compare(&submissions, a, b));
+        }
+    }
+    comparisons.sort_by(|x, y| y.similarity.partial_cmp(&x.similarity).unwrap());
+
+    for (rank, c) in comparisons.iter().enumerate() {
+        println!(
+            "{:>4}. {:>5.1}%  {}  {}",
+            rank + 1,
+            c.similarity * 100.0,
+            submissions[c.a].path.display(),
+            submissions[c.b].path.display(),
+        );
+    }
+
+    msg!(Writing, "similarity report to '{}'", output.display());
+    if let Err(e) = html::write(output, &submissions, &comparisons) {
+        msg!(Error, "Could not write similarity report: {}", e);
+        return Err(());
+    }
+    Ok(())
+}
+
+/// Reads and normalizes all Java files in the directory (recursively).
+fn load(job: &Job, dir: &Path, table: &mut HashMap<String, usize>) -> io::Result<Submission> {
+    let mut paths = Vec::new();
+    try!(java_files(dir, &mut paths));
+    paths.sort();
+
+    let mut sub = Submission {
+        path: dir.to_path_buf(),
+        files: Vec::new(),
+        syms: Vec::new(),
+        locs: Vec::new(),
+        len: 0,
+    };
+    for path in paths {
+        let src = match check::read_file(job, &path) {
+            Ok(src) => src,
+            Err(_) => {
+                msg!(Ignoring, "'{}' (can't be decoded)", path.display());
+                continue;
+            },
+        };
+
+        let idx = sub.files.len();
+        let file_map = FileMap::new(path.to_string_lossy().into_owned(), src.clone());
+        for ts in analyze::code_tokens(&file_map) {
+            let text = match ts.tok {
+                Token::Ident(_) => "<ident>",
+                Token::Literal(_) => "<literal>",
+                _ => &src[ts.span.into_range()],
+            };
+            let next = table.len();
+            sub.syms.push(*table.entry(text.to_string()).or_insert(next));
+            sub.locs.push((idx, ts.span));
+            sub.len += 1;
+        }
+
+        // A separator which is different from all other tokens
+        let sep = table.len();
+        table.insert(format!("\0{}", sep), sep);
+        sub.syms.push(sep);
+        sub.locs.push((idx, Span::dummy()));
+
+        sub.files.push(SourceFile {
+            path: path,
+            src: src,
+        });
+    }
+    Ok(sub)
+}
+
+fn java_files(dir: &Path, out: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in try!(fs::read_dir(dir)) {
+        let path = try!(entry).path();
+        if path.is_dir() {
+            try!(java_files(&path, out));
+        } else if path.extension().map(|ext| ext == "java").unwrap_or(false) {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn compare(submissions: &[Submission], a: usize, b: usize) -> Comparison {
+    let tiles = tiles(&submissions[a].syms, &submissions[b].syms);
+    let matched: usize = tiles.iter().map(|t| t.len).sum();
+    let total = submissions[a].len + submissions[b].len;
+    Comparison {
+        a: a,
+        b: b,
+        similarity: if total == 0 { 0.0 } else { 2.0 * matched as f64 / total as f64 },
+        tiles: tiles,
+    }
+}
+
+/// Greedy string tiling: takes the longest matching regions first, as long
+/// as they don't overlap with already taken ones. Regions which overlap are
+/// shortened to their longest free part and considered again.
+fn tiles(a: &[usize], b: &[usize]) -> Vec<Tile> {
+    let mut starts: HashMap<&[usize], Vec<usize>> = HashMap::new();
+    for (j, window) in b.windows(MIN_MATCH).enumerate() {
+        starts.entry(window).or_insert_with(Vec::new).push(j);
+    }
+
+    // All maximal matches, longest first. The start positions are negated to
+    // prefer earlier regions of the same length.
+    let mut queue = BinaryHeap::new();
+    for (i, window) in a.windows(MIN_MATCH).enumerate() {
+        for &j in starts.get(window).map(|v| &v[..]).unwrap_or(&[]) {
+            if i > 0 && j > 0 && a[i - 1] == b[j - 1] {
+                continue;
+            }
+            let mut len = MIN_MATCH;
+            while i + len < a.len() && j + len < b.len() && a[i + len] == b[j + len] {
+                len += 1;
+            }
+            queue.push((len, !i, !j));
+        }
+    }
+
+    let mut marked_a = vec![false; a.len()];
+    let mut marked_b = vec![false; b.len()];
+    let mut tiles = Vec::new();
+    while let Some((len, not_i, not_j)) = queue.pop() {
+        let (i, j) = (!not_i, !not_j);
+        let mut best = (0, 0);
+        let mut start = 0;
+        for k in 0..len {
+            if marked_a[i + k] || marked_b[j + k] {
+                start = k + 1;
+            } else if k + 1 - start > best.1 - best.0 {
+                best = (start, k + 1);
+            }
+        }
+
+        let free = best.1 - best.0;
+        if free == len {
+            for k in 0..len {
+                marked_a[i + k] = true;
+                marked_b[j + k] = true;
+            }
+            tiles.push(Tile {
+                a: i,
+                b: j,
+                len: len,
+            });
+        } else if free >= MIN_MATCH {
+            queue.push((free, !(i + best.0), !(j + best.0)));
+        }
+    }
+    tiles.sort_by_key(|t| t.a);
+    tiles
+}
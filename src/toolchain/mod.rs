@@ -0,0 +1,246 @@
+//! Discovery of installed JDKs (`jswag toolchains`, `--jdk`).
+//!
+//! JDKs are searched in `JAVA_HOME`, the usual installation directories of
+//! Linux distributions and macOS and in the candidates of SDKMAN. Their
+//! version is read from the `release` file every JDK contains.
+
+use java::{JAVAC_NAME, JAVA_NAME};
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directories containing one JDK per subdirectory
+const JDK_DIRS: &'static [&'static str] = &[
+    "/usr/lib/jvm",
+    "/usr/java",
+    "/Library/Java/JavaVirtualMachines",
+];
+
+/// An installed JDK
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Jdk {
+    /// The directory containing `bin/javac`
+    pub home: PathBuf,
+    /// The full version, e.g. `17.0.2` or `1.8.0_292`
+    pub version: String,
+    /// Where it was found, e.g. `JAVA_HOME`
+    pub source: &'static str,
+}
+
+impl Jdk {
+    /// Returns the JDK installed in `home`, if it contains `javac`.
+    pub fn at(home: &Path, source: &'static str) -> Option<Jdk> {
+        // On macOS, the actual JDK is in a subdirectory of the bundle
+        let bundle_home = home.join("Contents").join("Home");
+        let home = if bundle_home.is_dir() { bundle_home } else { home.to_path_buf() };
+        if !home.join("bin").join(JAVAC_NAME).is_file() {
+            return None;
+        }
+
+        let home = fs::canonicalize(&home).unwrap_or(home);
+        let version = read_version(&home).unwrap_or_else(|| "unknown".into());
+        Some(Jdk {
+            home: home,
+            version: version,
+            source: source,
+        })
+    }
+
+    /// The path of a binary of this JDK, e.g. `javac`
+    pub fn bin(&self, name: &str) -> PathBuf {
+        self.home.join("bin").join(name)
+    }
+
+    /// The major version, e.g. 17 for `17.0.2` and 8 for `1.8.0_292`
+    pub fn major(&self) -> Option<u32> {
+        major_version(&self.version)
+    }
+}
+
+/// The major version of a full version, e.g. 17 for `17.0.2` and 8 for
+/// `1.8.0_292`
+pub fn major_version(version: &str) -> Option<u32> {
+    let mut parts = version.split(|c| c == '.' || c == '_' || c == '-' || c == '+');
+    match parts.next() {
+        Some("1") => parts.next(),
+        first => first,
+    }.and_then(|s| s.parse().ok())
+}
+
+/// The numbers of a full version, e.g. `[17, 0, 10]` for `17.0.10`, which
+/// compare like the versions themselves.
+fn version_numbers(version: &str) -> Vec<u32> {
+    version.split(|c: char| !c.is_digit(10))
+        .filter_map(|s| s.parse().ok())
+        .collect()
+}
+
+/// Searches all installed JDKs, sorted by version (newest first). Every JDK
+/// is only returned once, even if it's found in multiple places.
+pub fn discover() -> Vec<Jdk> {
+    let mut jdks: Vec<Jdk> = Vec::new();
+    let mut add = |jdk: Option<Jdk>| {
+        if let Some(jdk) = jdk {
+            if !jdks.iter().any(|j| j.home == jdk.home) {
+                jdks.push(jdk);
+            }
+        }
+    };
+
+    if let Some(home) = env::var_os("JAVA_HOME") {
+        add(Jdk::at(Path::new(&home), "JAVA_HOME"));
+    }
+    for dir in JDK_DIRS {
+        for home in subdirs(Path::new(dir)) {
+            add(Jdk::at(&home, "system"));
+        }
+    }
+    let sdkman = env::var_os("SDKMAN_DIR")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".sdkman")));
+    if let Some(sdkman) = sdkman {
+        for home in subdirs(&sdkman.join("candidates").join("java")) {
+            add(Jdk::at(&home, "SDKMAN"));
+        }
+    }
+
+    jdks.sort_by(|a, b| {
+        (b.major(), version_numbers(&b.version)).cmp(&(a.major(), version_numbers(&a.version)))
+    });
+    jdks
+}
+
+/// Selects a JDK by its home directory or by its version. A version like
+/// `17` or `17.0` selects the newest discovered JDK starting with it.
+pub fn select(jdk: &str) -> Result<Jdk, String> {
+    let path = Path::new(jdk);
+    if path.is_dir() {
+        return Jdk::at(path, "--jdk").ok_or_else(|| {
+            format!("'{}' doesn't contain a JDK (no 'bin/{}')", jdk, JAVAC_NAME)
+        });
+    }
+
+    let jdks = discover();
+    let found = jdks.iter().find(|j| {
+        j.major().map(|m| m.to_string() == jdk).unwrap_or(false) ||
+            j.version == jdk ||
+            j.version.starts_with(&format!("{}.", jdk))
+    });
+    match found {
+        Some(jdk) => Ok(jdk.clone()),
+        None if jdks.is_empty() => Err(format!("no JDK with version '{}' found", jdk)),
+        None => {
+            let versions: Vec<_> = jdks.iter().map(|j| &j.version[..]).collect();
+            Err(format!(
+                "no JDK with version '{}' found (installed: {})",
+                jdk,
+                versions.join(", "),
+            ))
+        },
+    }
+}
+
+/// Prints all discovered JDKs (`jswag toolchains`).
+pub fn print_all() {
+    let jdks = discover();
+    if jdks.is_empty() {
+        msg!(Note, "No JDK found. `{}` and `{}` from PATH are used.", JAVAC_NAME, JAVA_NAME);
+        return;
+    }
+
+    let width = jdks.iter().map(|j| j.version.len()).max().unwrap_or(0);
+    for jdk in &jdks {
+        println!(
+            "{:<width$}  {}  ({})",
+            jdk.version,
+            jdk.home.display(),
+            jdk.source,
+            width = width,
+        );
+    }
+    msg!(
+        Note,
+        "Select one with `--jdk <version>` or `--jdk <path>`. Without it, `{}` and \
+            `{}` from PATH are used.",
+        JAVAC_NAME,
+        JAVA_NAME
+    );
+}
+
+/// Runs `<binary> -version` and returns the version it prints, e.g. `17.0.2`
+/// for `javac 17.0.2` or `openjdk version "17.0.2" 2022-01-18`.
+pub fn version_of(binary: &Path) -> Option<String> {
+    let out = match Command::new(binary).arg("-version").output() {
+        Ok(out) => out,
+        Err(_) => return None,
+    };
+    if !out.status.success() {
+        return None;
+    }
+
+    // Older versions print to stderr
+    let mut text = String::from_utf8_lossy(&out.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&out.stderr));
+    let first = match text.lines().next() {
+        Some(line) => line,
+        None => return None,
+    };
+    let version = match first.find('"') {
+        Some(start) => first[start + 1..].split('"').next(),
+        None => first.split_whitespace().nth(1),
+    };
+    Some(version.unwrap_or(first).trim().to_string())
+}
+
+/// Reads `JAVA_VERSION="..."` from the `release` file of the JDK.
+fn read_version(home: &Path) -> Option<String> {
+    let mut src = String::new();
+    if File::open(home.join("release")).and_then(|mut f| f.read_to_string(&mut src)).is_err() {
+        return None;
+    }
+    src.lines()
+        .filter(|line| line.starts_with("JAVA_VERSION="))
+        .map(|line| line["JAVA_VERSION=".len()..].trim_matches('"').to_string())
+        .next()
+}
+
+/// All subdirectories of `dir`, sorted by name
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    dirs.sort();
+    dirs
+}
+
+#[cfg(test)]
+mod test {
+    use super::{major_version, version_numbers};
+
+    #[test]
+    fn major_versions() {
+        assert_eq!(major_version("1.8.0_292"), Some(8));
+        assert_eq!(major_version("17.0.10"), Some(17));
+        assert_eq!(major_version("11"), Some(11));
+        assert_eq!(major_version("21-ea"), Some(21));
+        assert_eq!(major_version("17+35"), Some(17));
+        assert_eq!(major_version("unknown"), None);
+    }
+
+    #[test]
+    fn versions_compare_numerically() {
+        let key = |v| (major_version(v), version_numbers(v));
+        assert_eq!(version_numbers("1.8.0_292"), vec![1, 8, 0, 292]);
+        assert_eq!(version_numbers("17.0.10"), vec![17, 0, 10]);
+        assert!(key("1.8.0_292") < key("17.0.10"));
+        assert!(key("1.8.0_292") < key("11.0.2"));
+        assert!(key("17.0.9") < key("17.0.10"));
+        assert!(key("1.8.0_60") < key("1.8.0_292"));
+    }
+}
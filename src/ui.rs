@@ -14,12 +14,15 @@ pub enum MessageType {
     // action
     Checking,
     Compiling,
+    Fixing,
     Running,
+    Writing,
     Ignoring,
     Aborting,
 
     // status
     Fresh,
+    Ok,
 
     Note,
     Debug,
@@ -36,9 +39,12 @@ impl fmt::Display for MessageType {
             MessageType::Aborting => ("Aborting", Magenta.bold()),
             MessageType::Checking => ("Checking", status_style),
             MessageType::Compiling => ("Compiling", status_style),
+            MessageType::Fixing => ("Fixing", status_style),
             MessageType::Running => ("Running", status_style),
+            MessageType::Writing => ("Writing", status_style),
             MessageType::Ignoring => ("Ignoring", White.bold()),
             MessageType::Fresh => ("Fresh", status_style),
+            MessageType::Ok => ("Ok", status_style),
             MessageType::Note => ("Note", White.bold()),
             MessageType::Debug => ("Debug", NotSet.to_style()),
             MessageType::None => {